@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent};
+
+use crate::kiosk::{ADMIN_WINDOW, KIOSK_WINDOW};
+use crate::maintenance;
+use crate::settings::SettingsStore;
+use crate::whatsapp::{now_millis, WhatsAppManager};
+
+/// How often the maintenance thread checks whether a day has passed; by
+/// wall clock, so a PC that sleeps overnight still gets its daily run.
+const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const MAINTENANCE_EVERY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+/// What to do when the admin window is closed during a campaign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseChoice {
+    /// Hide the window; the campaign goes on and the window comes back
+    /// when it finishes or needs the operator.
+    KeepRunning,
+    /// Pause the campaign and hide the window; it comes back when the
+    /// operator is needed, like `KeepRunning`.
+    PauseAndClose,
+    /// Quit the app, stopping the campaign where it is.
+    CancelAndClose,
+}
+
+/// Payload of `close-requested-while-sending`; answer with
+/// `resolve_close_request`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloseRequested {
+    pub campaign_id: Option<String>,
+}
+
+/// Holds back closing the admin window while a campaign is sending and
+/// asks the webview what to do instead; brings it back when the kiosk
+/// closes.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    // The admin window hidden by `PauseAndClose` would otherwise be out of
+    // reach once the kiosk goes
+    if matches!(event, WindowEvent::Destroyed) && window.label() == KIOSK_WINDOW {
+        show_admin_window(window.app_handle());
+        return;
+    }
+    let WindowEvent::CloseRequested { api } = event else {
+        return;
+    };
+    if window.label() != ADMIN_WINDOW {
+        return;
+    }
+    let Some(manager) = window.try_state::<Mutex<WhatsAppManager>>() else {
+        return;
+    };
+    let Some(campaign_id) = manager.lock().ok().and_then(|manager| manager.sending_campaign()) else {
+        return;
+    };
+
+    api.prevent_close();
+    let _ = window.emit("close-requested-while-sending", &CloseRequested { campaign_id: Some(campaign_id) });
+}
+
+pub fn resolve_close(app: &AppHandle, manager: &WhatsAppManager, choice: CloseChoice) -> Result<(), String> {
+    let admin = app
+        .get_webview_window(ADMIN_WINDOW)
+        .ok_or_else(|| "Admin window is not open".to_string())?;
+    manager.note_close_choice(choice);
+
+    match choice {
+        CloseChoice::KeepRunning => admin.hide().map_err(|e| e.to_string()),
+        CloseChoice::PauseAndClose => {
+            manager.pause_bulk_send(app)?;
+            // Hidden, not destroyed: the app has no way to reopen it
+            admin.hide().map_err(|e| e.to_string())
+        }
+        CloseChoice::CancelAndClose => manager.cancel_and_quit(app),
+    }
+}
+
+/// Brings a hidden admin window back, e.g. when a campaign kept running
+/// in the background finishes.
+pub fn show_admin_window(app: &AppHandle) {
+    if let Some(admin) = app.get_webview_window(ADMIN_WINDOW) {
+        if matches!(admin.is_visible(), Ok(false)) {
+            let _ = admin.show();
+            let _ = admin.set_focus();
+        }
+    }
+}
+
+/// Runs the storage cleanup an hour after startup and then once a day. A
+/// run due while maintenance mode holds the app read-only waits for the
+/// next check.
+pub fn spawn_daily_maintenance(app: AppHandle) {
+    thread::spawn(move || {
+        let mut last_run: Option<u64> = None;
+        loop {
+            thread::sleep(MAINTENANCE_CHECK_INTERVAL);
+            let due = last_run.is_none_or(|at| now_millis().saturating_sub(at) >= MAINTENANCE_EVERY_MILLIS);
+            if due && maintenance::ensure_writable().is_ok() && run_maintenance(&app) {
+                last_run = Some(now_millis());
+            }
+        }
+    });
+}
+
+/// Whether the run happened; its report is journaled even when the
+/// webview can't be reached.
+fn run_maintenance(app: &AppHandle) -> bool {
+    let Ok(settings) = app.state::<Mutex<SettingsStore>>().lock().map(|store| store.get().clone()) else {
+        return false;
+    };
+    let Ok(manager) = app.state::<Mutex<WhatsAppManager>>().lock().map(|manager| manager.clone()) else {
+        return false;
+    };
+    let _ = manager.run_maintenance(app, &settings, None);
+    true
+}
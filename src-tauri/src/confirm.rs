@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::whatsapp::now_millis;
+
+/// How long a confirmation token stays valid.
+const CONFIRMATION_TTL_MILLIS: u64 = 60 * 1000;
+
+/// What a destructive command returns: either a token to send back with
+/// the second call, or the command's own result once confirmed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Confirmable<T> {
+    ConfirmationRequired {
+        token: String,
+        /// What the command is about to do, for the confirmation dialog.
+        impact: String,
+        expires_at: u64,
+    },
+    Done {
+        result: T,
+    },
+}
+
+/// A token handed out for one command on one target.
+struct Pending {
+    action: String,
+    expires_at: u64,
+}
+
+static PENDING: Mutex<BTreeMap<String, Pending>> = Mutex::new(BTreeMap::new());
+
+/// Two-step confirmation for a destructive command. Without a token this
+/// hands one out with `impact`, and the command returns it as is; with a
+/// valid one it returns `None` and the command goes ahead. Tokens are
+/// single-use and bound to `action` and `target`:
+///
+/// ```ignore
+/// if let Some(required) = confirm::require("delete_campaign_draft", &draft_id, confirmation, || Ok(impact))? {
+///     return Ok(required);
+/// }
+/// ```
+pub fn require<T>(
+    action: &str,
+    target: &str,
+    token: Option<String>,
+    impact: impl FnOnce() -> Result<String, String>,
+) -> Result<Option<Confirmable<T>>, String> {
+    let action = format!("{}:{}", action, target);
+    if let Some(token) = token {
+        let mut pending = PENDING.lock().map_err(|e| e.to_string())?;
+        return match pending.remove(&token) {
+            Some(entry) if entry.action == action && entry.expires_at > now_millis() => Ok(None),
+            _ => Err("Confirmation expired or doesn't match; try again".to_string()),
+        };
+    }
+
+    let impact = impact()?;
+    let token = uuid::Uuid::new_v4().to_string();
+    let now = now_millis();
+    let expires_at = now + CONFIRMATION_TTL_MILLIS;
+    let mut pending = PENDING.lock().map_err(|e| e.to_string())?;
+    pending.retain(|_, entry| entry.expires_at > now);
+    pending.insert(token.clone(), Pending { action, expires_at });
+    Ok(Some(Confirmable::ConfirmationRequired {
+        token,
+        impact,
+        expires_at,
+    }))
+}
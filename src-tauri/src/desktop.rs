@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::Mutex;
+use sysinfo::System;
+
+#[cfg(target_os = "windows")]
+use winapi::shared::minwindef::{BOOL, LPARAM};
+#[cfg(target_os = "windows")]
+use winapi::shared::windef::HWND;
+#[cfg(target_os = "windows")]
+use winapi::um::winuser::{
+    EnumWindows, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible, PostMessageW,
+    WM_CLOSE,
+};
+
+/// Title of the app picker Windows shows for a URL scheme without a clear
+/// default handler. It is localized, so the host process is checked too.
+#[cfg(target_os = "windows")]
+const OPEN_WITH_DIALOG_TITLE: &str = "How do you want to open this?";
+#[cfg(target_os = "windows")]
+const OPEN_WITH_PROCESS: &str = "openwith.exe";
+
+/// WhatsApp Business installs side by side with regular WhatsApp, under a
+/// different process name, app bundle and URL scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhatsAppVariant {
+    Regular,
+    Business,
+}
+
+impl WhatsAppVariant {
+    pub fn scheme(self) -> &'static str {
+        match self {
+            WhatsAppVariant::Regular => "whatsapp",
+            WhatsAppVariant::Business => "whatsapp-business",
+        }
+    }
+
+    fn of_process(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if !name.contains("whatsapp") {
+            None
+        } else if name.contains("business") {
+            Some(WhatsAppVariant::Business)
+        } else {
+            Some(WhatsAppVariant::Regular)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationInfo {
+    /// The variant sends go through: the preferred one when it is
+    /// installed, otherwise whichever is.
+    pub variant: Option<WhatsAppVariant>,
+    pub installed_variants: Vec<WhatsAppVariant>,
+    pub running_variants: Vec<WhatsAppVariant>,
+    /// Whether the selected variant's URL scheme has a handler; `None`
+    /// where that can't be checked.
+    pub protocol_handler_registered: Option<bool>,
+    /// Whether Windows' "How do you want to open this?" picker is on
+    /// screen; `None` off Windows.
+    pub open_with_dialog_visible: Option<bool>,
+}
+
+static PREFERRED_VARIANT: Mutex<Option<WhatsAppVariant>> = Mutex::new(None);
+
+pub fn set_preferred_variant(variant: Option<WhatsAppVariant>) {
+    if let Ok(mut preferred) = PREFERRED_VARIANT.lock() {
+        *preferred = variant;
+    }
+}
+
+fn preferred_variant() -> Option<WhatsAppVariant> {
+    PREFERRED_VARIANT.lock().ok().and_then(|preferred| *preferred)
+}
+
+/// The variant to send through, without the installation scan: the
+/// preferred one if set, otherwise the one that is running, Business only
+/// when regular WhatsApp isn't.
+pub fn active_variant() -> WhatsAppVariant {
+    if let Some(variant) = preferred_variant() {
+        return variant;
+    }
+
+    let mut system = System::new();
+    system.refresh_processes();
+    let running = running_variants(&system);
+    if running.contains(&WhatsAppVariant::Business) && !running.contains(&WhatsAppVariant::Regular) {
+        WhatsAppVariant::Business
+    } else {
+        WhatsAppVariant::Regular
+    }
+}
+
+fn running_variants(system: &System) -> Vec<WhatsAppVariant> {
+    let mut variants = Vec::new();
+    for process in system.processes().values() {
+        if let Some(variant) = WhatsAppVariant::of_process(process.name()) {
+            if !variants.contains(&variant) {
+                variants.push(variant);
+            }
+        }
+    }
+    variants
+}
+
+fn installed_variants() -> Vec<WhatsAppVariant> {
+    #[cfg(target_os = "windows")]
+    {
+        let output = Command::new("powershell")
+            .arg("-Command")
+            .arg(r#"Get-AppxPackage | Where-Object {$_.Name -like "*WhatsApp*"} | Select-Object -ExpandProperty Name"#)
+            .output();
+
+        let mut variants = Vec::new();
+        if let Ok(result) = output {
+            for name in String::from_utf8_lossy(&result.stdout).lines() {
+                if let Some(variant) = WhatsAppVariant::of_process(name) {
+                    if !variants.contains(&variant) {
+                        variants.push(variant);
+                    }
+                }
+            }
+        }
+        variants
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        [
+            (WhatsAppVariant::Regular, "/Applications/WhatsApp.app"),
+            (WhatsAppVariant::Business, "/Applications/WhatsApp Business.app"),
+        ]
+        .into_iter()
+        .filter(|(_, path)| std::path::Path::new(path).exists())
+        .map(|(variant, _)| variant)
+        .collect()
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // There is no Business client for Linux
+        let snap_check = Command::new("snap")
+            .arg("list")
+            .arg("whatsapp-for-linux")
+            .output();
+
+        match snap_check {
+            Ok(result) if result.status.success() => vec![WhatsAppVariant::Regular],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn protocol_handler_registered(variant: WhatsAppVariant) -> Option<bool> {
+    #[cfg(target_os = "windows")]
+    {
+        let output = Command::new("reg")
+            .arg("query")
+            .arg(format!(r"HKCR\{}", variant.scheme()))
+            .output()
+            .ok()?;
+        Some(output.status.success())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = variant;
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("xdg-mime")
+            .arg("query")
+            .arg("default")
+            .arg(format!("x-scheme-handler/{}", variant.scheme()))
+            .output()
+            .ok()?;
+        Some(output.status.success() && !output.stdout.iter().all(u8::is_ascii_whitespace))
+    }
+}
+
+/// Full scan of what is installed and running, for diagnostics.
+pub fn installation_info() -> InstallationInfo {
+    let mut system = System::new();
+    system.refresh_processes();
+    let running = running_variants(&system);
+
+    // A running app counts as installed even where the scan can't see it
+    let mut installed = installed_variants();
+    for variant in &running {
+        if !installed.contains(variant) {
+            installed.push(*variant);
+        }
+    }
+
+    let variant = match preferred_variant() {
+        Some(preferred) if installed.contains(&preferred) => Some(preferred),
+        _ => [WhatsAppVariant::Regular, WhatsAppVariant::Business]
+            .into_iter()
+            .find(|variant| installed.contains(variant)),
+    };
+
+    InstallationInfo {
+        variant,
+        installed_variants: installed,
+        running_variants: running,
+        protocol_handler_registered: variant.and_then(protocol_handler_registered),
+        open_with_dialog_visible: cfg!(target_os = "windows").then(|| find_open_with_dialog().is_some()),
+    }
+}
+
+/// PIDs of every running WhatsApp process (the desktop app spawns several).
+pub fn whatsapp_pids(system: &System) -> Vec<u32> {
+    system
+        .processes()
+        .values()
+        .filter(|process| process.name().to_lowercase().contains("whatsapp"))
+        .map(|process| process.pid().as_u32())
+        .collect()
+}
+
+pub fn is_whatsapp_running() -> bool {
+    let mut system = System::new();
+    system.refresh_processes();
+    !whatsapp_pids(&system).is_empty()
+}
+
+/// Combined CPU usage of all WhatsApp processes, in percent of one core.
+/// Needs two refreshes of `system` at least `MINIMUM_CPU_UPDATE_INTERVAL` apart.
+pub fn whatsapp_cpu_usage(system: &System) -> f32 {
+    system
+        .processes()
+        .values()
+        .filter(|process| process.name().to_lowercase().contains("whatsapp"))
+        .map(|process| process.cpu_usage())
+        .sum()
+}
+
+/// Hands a URL to the OS protocol handler without waiting for the target app.
+pub fn open_url(url: &str) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let result = Command::new("rundll32")
+        .arg("url.dll,FileProtocolHandler")
+        .arg(url)
+        .output();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).output();
+
+    #[cfg(target_os = "linux")]
+    let result = Command::new("xdg-open").arg(url).output();
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open WhatsApp: {}", e))
+}
+
+/// Handle of a visible "How do you want to open this?" picker. Pressing
+/// Enter on it would set whatever app it highlights as the default, so
+/// key presses must not go out while it is up. Always `None` off Windows.
+pub fn find_open_with_dialog() -> Option<isize> {
+    #[cfg(target_os = "windows")]
+    {
+        let mut system = System::new();
+        system.refresh_processes();
+        let mut search = OpenWithSearch {
+            pids: system
+                .processes()
+                .values()
+                .filter(|process| process.name().to_lowercase() == OPEN_WITH_PROCESS)
+                .map(|process| process.pid().as_u32())
+                .collect(),
+            found: None,
+        };
+        unsafe {
+            EnumWindows(Some(find_open_with_window), &mut search as *mut OpenWithSearch as LPARAM);
+        }
+        search.found
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
+
+/// Asks the picker to close, leaving the handler associations unchanged.
+pub fn close_open_with_dialog(handle: isize) {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        PostMessageW(handle as HWND, WM_CLOSE, 0, 0);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    let _ = handle;
+}
+
+#[cfg(target_os = "windows")]
+struct OpenWithSearch {
+    pids: Vec<u32>,
+    found: Option<isize>,
+}
+
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn find_open_with_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let search = &mut *(lparam as *mut OpenWithSearch);
+    if IsWindowVisible(hwnd) == 0 {
+        return 1;
+    }
+
+    let mut pid: u32 = 0;
+    GetWindowThreadProcessId(hwnd, &mut pid);
+    let mut title = [0u16; 256];
+    let length = GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32).max(0) as usize;
+    let title = String::from_utf16_lossy(&title[..length]);
+
+    if search.pids.contains(&pid) || title.contains(OPEN_WITH_DIALOG_TITLE) {
+        search.found = Some(hwnd as isize);
+        return 0;
+    }
+    1
+}
+
+/// Whether a WhatsApp window currently has keyboard focus, or `None` when
+/// the foreground window can't be determined (e.g. xdotool missing).
+pub fn is_whatsapp_foreground() -> Option<bool> {
+    #[cfg(target_os = "windows")]
+    {
+        let mut system = System::new();
+        system.refresh_processes();
+        let pids = whatsapp_pids(&system);
+
+        let mut foreground_pid: u32 = 0;
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_null() {
+                return Some(false);
+            }
+            GetWindowThreadProcessId(hwnd, &mut foreground_pid);
+        }
+        Some(pids.contains(&foreground_pid))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg(r#"tell application "System Events" to get name of first application process whose frontmost is true"#)
+            .output();
+
+        match output {
+            Ok(result) if result.status.success() => {
+                Some(String::from_utf8_lossy(&result.stdout).to_lowercase().contains("whatsapp"))
+            }
+            _ => None
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let output = Command::new("xdotool")
+            .arg("getactivewindow")
+            .arg("getwindowpid")
+            .output();
+
+        let foreground_pid = match output {
+            Ok(result) => String::from_utf8_lossy(&result.stdout).trim().parse::<u32>().ok()?,
+            Err(_) => return None
+        };
+
+        let mut system = System::new();
+        system.refresh_processes();
+        Some(whatsapp_pids(&system).contains(&foreground_pid))
+    }
+}
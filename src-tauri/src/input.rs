@@ -0,0 +1,633 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::io::Write;
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use std::process::Stdio;
+#[cfg(target_os = "windows")]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::desktop;
+use crate::metrics::{self, Stage};
+
+#[cfg(target_os = "windows")]
+use winapi::shared::minwindef::{DWORD, LPARAM, WPARAM};
+#[cfg(target_os = "windows")]
+use winapi::shared::windef::HWND;
+#[cfg(target_os = "windows")]
+use winapi::um::winuser::{
+    GetForegroundWindow, GetKeyboardLayout, GetWindowThreadProcessId, MapVirtualKeyW, SendInput, SendMessageW, INPUT,
+    INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MAPVK_VK_TO_VSC, VK_CONTROL,
+    VK_RETURN, WM_IME_CONTROL,
+};
+
+#[cfg(target_os = "macos")]
+use core_graphics::event::{CGEvent, CGEventFlags, CGEventType, CGKeyCode};
+#[cfg(target_os = "macos")]
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+
+/// Oldest entries are dropped once the demo log grows past this.
+const MAX_RECORDED_INPUTS: usize = 500;
+/// Typed by `test_injection`: Latin, Devanagari and a symbol, which a
+/// layout-dependent path would mangle.
+const INJECTION_TEST_TEXT: &str = "PATCH input test 123 नमस्ते ✓";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Key {
+    Enter,
+    /// Ctrl+A, or Cmd+A on macOS.
+    SelectAll,
+    /// Ctrl+C, or Cmd+C on macOS.
+    Copy,
+    /// Ctrl+V, or Cmd+V on macOS.
+    Paste,
+}
+
+impl Key {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "Enter" => Ok(Key::Enter),
+            "SelectAll" => Ok(Key::SelectAll),
+            "Copy" => Ok(Key::Copy),
+            "Paste" => Ok(Key::Paste),
+            _ => Err("Unsupported key".to_string()),
+        }
+    }
+}
+
+/// What the single-send commands return. `demo_mode` is set when nothing
+/// was actually sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputResult {
+    pub message: String,
+    pub demo_mode: bool,
+}
+
+impl InputResult {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            demo_mode: demo_mode_enabled(),
+        }
+    }
+}
+
+/// Everything that reaches outside the app to drive WhatsApp: opening chats,
+/// pressing keys and handing it files.
+pub trait InputSimulator: Send + Sync {
+    fn open_url(&self, url: &str) -> Result<(), String>;
+    fn press_key(&self, key: Key) -> Result<(), String>;
+    /// Types `text` as characters, whatever the keyboard layout.
+    fn type_text(&self, text: &str) -> Result<(), String>;
+    /// Puts the file at `path` on the clipboard, for `Key::Paste` into a chat.
+    fn copy_file(&self, path: &str) -> Result<(), String>;
+}
+
+/// Drives the real desktop.
+pub struct SystemInput;
+
+impl InputSimulator for SystemInput {
+    fn open_url(&self, url: &str) -> Result<(), String> {
+        metrics::time(Stage::DeeplinkOpen, || desktop::open_url(url))
+    }
+
+    fn press_key(&self, key: Key) -> Result<(), String> {
+        metrics::time(Stage::KeyInjection, || match key {
+            Key::Enter => press_enter(),
+            Key::SelectAll => press_shortcut('a'),
+            Key::Copy => press_shortcut('c'),
+            Key::Paste => press_shortcut('v'),
+        })
+    }
+
+    fn type_text(&self, text: &str) -> Result<(), String> {
+        metrics::time(Stage::KeyInjection, || type_unicode(text))
+    }
+
+    fn copy_file(&self, path: &str) -> Result<(), String> {
+        copy_file_to_clipboard(path)
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmGetDefaultIMEWnd(window: HWND) -> HWND;
+}
+
+/// `WM_IME_CONTROL` commands; the IME window answers them across processes,
+/// unlike the `Imm*` context functions.
+#[cfg(target_os = "windows")]
+const IMC_GETOPENSTATUS: WPARAM = 0x0005;
+#[cfg(target_os = "windows")]
+const IMC_SETOPENSTATUS: WPARAM = 0x0006;
+
+/// Keyboard layout of the window last typed into, and the last one
+/// handed out by `layout_change`.
+#[cfg(target_os = "windows")]
+static TYPED_LAYOUT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os = "windows")]
+static REPORTED_LAYOUT: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_os = "windows")]
+fn keyboard_input(scan: u16, flags: DWORD) -> INPUT {
+    unsafe {
+        let mut input: INPUT = std::mem::zeroed();
+        input.type_ = INPUT_KEYBOARD;
+        *input.u.ki_mut() = KEYBDINPUT {
+            wVk: 0,
+            wScan: scan,
+            dwFlags: flags,
+            time: 0,
+            dwExtraInfo: 0,
+        };
+        input
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn send_inputs(mut inputs: Vec<INPUT>) -> Result<(), String> {
+    let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_mut_ptr(), std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        return Err(format!("Failed to send key press: {} of {} inputs were blocked", inputs.len() - sent as usize, inputs.len()));
+    }
+    Ok(())
+}
+
+/// Control keys go by scan code, which means the same physical key on
+/// every layout.
+#[cfg(target_os = "windows")]
+fn scan_code(virtual_key: i32) -> u16 {
+    unsafe { MapVirtualKeyW(virtual_key as u32, MAPVK_VK_TO_VSC) as u16 }
+}
+
+#[cfg(target_os = "windows")]
+fn foreground_layout(window: HWND) -> usize {
+    unsafe { GetKeyboardLayout(GetWindowThreadProcessId(window, std::ptr::null_mut())) as usize }
+}
+
+#[cfg(target_os = "windows")]
+fn note_layout(window: HWND) {
+    TYPED_LAYOUT.store(foreground_layout(window), Ordering::Relaxed);
+}
+
+/// Language id of the layout keys went to, when it changed since the last
+/// call; for the caller to journal.
+#[cfg(target_os = "windows")]
+pub fn layout_change() -> Option<String> {
+    let layout = TYPED_LAYOUT.load(Ordering::Relaxed);
+    (layout != 0 && REPORTED_LAYOUT.swap(layout, Ordering::Relaxed) != layout)
+        .then(|| format!("{:#06x}", layout & 0xFFFF))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn layout_change() -> Option<String> {
+    None
+}
+
+/// Language id of the focused window's keyboard layout, e.g. `0x0439` for
+/// Hindi.
+#[cfg(target_os = "windows")]
+pub fn keyboard_layout() -> Option<String> {
+    let window = unsafe { GetForegroundWindow() };
+    if window.is_null() {
+        return None;
+    }
+    Some(format!("{:#06x}", foreground_layout(window) & 0xFFFF))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn keyboard_layout() -> Option<String> {
+    None
+}
+
+/// Turns off an open IME on the focused window, which commits or drops a
+/// pending composition, so Enter sends instead of picking a candidate.
+/// Returns the IME window to reopen afterwards.
+#[cfg(target_os = "windows")]
+fn close_ime(window: HWND) -> Option<HWND> {
+    unsafe {
+        let ime = ImmGetDefaultIMEWnd(window);
+        if ime.is_null() || SendMessageW(ime, WM_IME_CONTROL, IMC_GETOPENSTATUS, 0) == 0 {
+            return None;
+        }
+        SendMessageW(ime, WM_IME_CONTROL, IMC_SETOPENSTATUS, 0 as LPARAM);
+        Some(ime)
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn press_enter() -> Result<(), String> {
+    let window = unsafe { GetForegroundWindow() };
+    note_layout(window);
+    let ime = close_ime(window);
+    if ime.is_some() {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let enter = scan_code(VK_RETURN);
+    let pressed = send_inputs(vec![keyboard_input(enter, KEYEVENTF_SCANCODE)]).and_then(|_| {
+        thread::sleep(Duration::from_millis(50));
+        send_inputs(vec![keyboard_input(enter, KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP)])
+    });
+
+    if let Some(ime) = ime {
+        unsafe {
+            SendMessageW(ime, WM_IME_CONTROL, IMC_SETOPENSTATUS, 1 as LPARAM);
+        }
+    }
+    pressed
+}
+
+#[cfg(target_os = "windows")]
+fn press_shortcut(letter: char) -> Result<(), String> {
+    let control = scan_code(VK_CONTROL);
+    let key = scan_code(letter.to_ascii_uppercase() as i32);
+    send_inputs(vec![
+        keyboard_input(control, KEYEVENTF_SCANCODE),
+        keyboard_input(key, KEYEVENTF_SCANCODE),
+        keyboard_input(key, KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP),
+        keyboard_input(control, KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP),
+    ])
+}
+
+#[cfg(target_os = "windows")]
+fn type_unicode(text: &str) -> Result<(), String> {
+    note_layout(unsafe { GetForegroundWindow() });
+    let inputs = text
+        .encode_utf16()
+        .flat_map(|unit| {
+            [
+                keyboard_input(unit, KEYEVENTF_UNICODE),
+                keyboard_input(unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP),
+            ]
+        })
+        .collect();
+    send_inputs(inputs)
+}
+
+#[cfg(target_os = "macos")]
+fn press_enter() -> Result<(), String> {
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|e| format!("Failed to create event source: {:?}", e))?;
+
+    let key_down = CGEvent::new_keyboard_event(source.clone(), CGKeyCode(0x24), true)
+        .map_err(|e| format!("Failed to create key down event: {:?}", e))?;
+    let key_up = CGEvent::new_keyboard_event(source, CGKeyCode(0x24), false)
+        .map_err(|e| format!("Failed to create key up event: {:?}", e))?;
+
+    key_down.post(CGEventType::KeyDown);
+    thread::sleep(Duration::from_millis(50));
+    key_up.post(CGEventType::KeyUp);
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn press_shortcut(letter: char) -> Result<(), String> {
+    let key_code = match letter {
+        'a' => 0x00,
+        'c' => 0x08,
+        'v' => 0x09,
+        _ => return Err(format!("Unsupported shortcut: Cmd+{}", letter)),
+    };
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|e| format!("Failed to create event source: {:?}", e))?;
+
+    for key_down in [true, false] {
+        let event = CGEvent::new_keyboard_event(source.clone(), CGKeyCode(key_code), key_down)
+            .map_err(|e| format!("Failed to create key event: {:?}", e))?;
+        event.set_flags(CGEventFlags::CGEventFlagCommand);
+        event.post(if key_down { CGEventType::KeyDown } else { CGEventType::KeyUp });
+    }
+    Ok(())
+}
+
+/// Key events carry the characters themselves, so the layout doesn't
+/// matter. macOS reads at most 20 UTF-16 units per event.
+#[cfg(target_os = "macos")]
+fn type_unicode(text: &str) -> Result<(), String> {
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|e| format!("Failed to create event source: {:?}", e))?;
+
+    let units: Vec<u16> = text.encode_utf16().collect();
+    for chunk in units.chunks(20) {
+        let chunk = String::from_utf16_lossy(chunk);
+        for key_down in [true, false] {
+            let event = CGEvent::new_keyboard_event(source.clone(), CGKeyCode(0), key_down)
+                .map_err(|e| format!("Failed to create key event: {:?}", e))?;
+            event.set_string(&chunk);
+            event.post(if key_down { CGEventType::KeyDown } else { CGEventType::KeyUp });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn press_enter() -> Result<(), String> {
+    if Command::new("xdotool").arg("key").arg("Return").output().is_ok() {
+        return Ok(());
+    }
+
+    // Fallback to ydotool
+    Command::new("ydotool")
+        .arg("key")
+        .arg("28:1") // Enter key
+        .arg("28:0")
+        .output()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send key press. Install xdotool or ydotool: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn press_shortcut(letter: char) -> Result<(), String> {
+    if Command::new("xdotool").arg("key").arg(format!("ctrl+{}", letter)).output().is_ok() {
+        return Ok(());
+    }
+
+    let code = match letter {
+        'a' => "30",
+        'c' => "46",
+        'v' => "47",
+        _ => return Err(format!("Unsupported shortcut: Ctrl+{}", letter)),
+    };
+    Command::new("ydotool")
+        .arg("key")
+        .args(["29:1", &format!("{}:1", code), &format!("{}:0", code), "29:0"]) // Ctrl+letter
+        .output()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send key press. Install xdotool or ydotool: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn type_unicode(text: &str) -> Result<(), String> {
+    if Command::new("xdotool").arg("type").arg("--").arg(text).output().is_ok() {
+        return Ok(());
+    }
+
+    Command::new("ydotool")
+        .arg("type")
+        .arg("--")
+        .arg(text)
+        .output()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to type text. Install xdotool or ydotool: {}", e))
+}
+
+/// Outcome of `test_input_injection`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectionTest {
+    pub expected: String,
+    /// What the editor held, read back through the clipboard.
+    pub actual: String,
+    pub matched: bool,
+    pub keyboard_layout: Option<String>,
+}
+
+/// Manual check of the real input path: types a known string into a
+/// scratch file in the system text editor, copies it back and compares.
+/// The editor is left open to close without saving.
+pub fn test_injection() -> Result<InjectionTest, String> {
+    if demo_mode_enabled() {
+        return Err("Demo mode is on; the input test needs real key presses".to_string());
+    }
+
+    let scratch = std::env::temp_dir().join("patch-input-test.txt");
+    fs::write(&scratch, "").map_err(|e| format!("Failed to create scratch file: {}", e))?;
+    open_in_editor(&scratch)?;
+    thread::sleep(Duration::from_millis(2000));
+
+    let keyboard_layout = keyboard_layout();
+    SYSTEM_INPUT.type_text(INJECTION_TEST_TEXT)?;
+    thread::sleep(Duration::from_millis(300));
+    SYSTEM_INPUT.press_key(Key::SelectAll)?;
+    SYSTEM_INPUT.press_key(Key::Copy)?;
+    thread::sleep(Duration::from_millis(300));
+
+    let actual = read_clipboard()?.trim_end().to_string();
+    Ok(InjectionTest {
+        expected: INJECTION_TEST_TEXT.to_string(),
+        matched: actual == INJECTION_TEST_TEXT,
+        actual,
+        keyboard_layout,
+    })
+}
+
+fn open_in_editor(path: &std::path::Path) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let mut command = Command::new("notepad.exe");
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.arg("-e"); // TextEdit
+        command
+    };
+    #[cfg(target_os = "linux")]
+    let mut command = Command::new("xdg-open");
+
+    command
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open text editor: {}", e))
+}
+
+fn read_clipboard() -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", "[Console]::OutputEncoding = [Text.Encoding]::UTF8; Get-Clipboard -Raw"])
+        .output();
+    #[cfg(target_os = "macos")]
+    let output = Command::new("pbpaste").output();
+    #[cfg(target_os = "linux")]
+    let output = Command::new("xclip").args(["-o", "-selection", "clipboard"]).output();
+
+    let output = output.map_err(|e| format!("Failed to read clipboard: {}", e))?;
+    String::from_utf8(output.stdout).map_err(|e| format!("Failed to read clipboard: {}", e))
+}
+
+/// Files go on the clipboard as files, not their contents, so pasting
+/// into a chat attaches them.
+fn copy_file_to_clipboard(path: &str) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", &format!("Set-Clipboard -LiteralPath '{}'", path.replace('\'', "''"))])
+        .output();
+    #[cfg(target_os = "macos")]
+    let output = Command::new("osascript")
+        .args(["-e", &format!("set the clipboard to (POSIX file \"{}\")", path.replace('"', "\\\""))])
+        .output();
+    #[cfg(target_os = "linux")]
+    let output = Command::new("xclip")
+        .args(["-selection", "clipboard", "-t", "text/uri-list"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(format!("file://{}\n", path).as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+
+    let output = output.map_err(|e| format!("Failed to copy attachment {}: {}", path, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to copy attachment {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RecordedInput {
+    OpenUrl { url: String, at: u64 },
+    PressKey { key: Key, at: u64 },
+    TypeText { text: String, at: u64 },
+    CopyFile { path: String, at: u64 },
+}
+
+/// Demo backend: touches nothing and keeps a log of what would have happened.
+pub struct RecordingInput {
+    log: Mutex<Vec<RecordedInput>>,
+}
+
+impl RecordingInput {
+    pub const fn new() -> Self {
+        Self { log: Mutex::new(Vec::new()) }
+    }
+
+    fn record(&self, input: RecordedInput) {
+        if let Ok(mut log) = self.log.lock() {
+            if log.len() >= MAX_RECORDED_INPUTS {
+                log.remove(0);
+            }
+            log.push(input);
+        }
+    }
+
+    pub fn recorded(&self) -> Vec<RecordedInput> {
+        self.log.lock().map(|log| log.clone()).unwrap_or_default()
+    }
+}
+
+impl InputSimulator for RecordingInput {
+    fn open_url(&self, url: &str) -> Result<(), String> {
+        self.record(RecordedInput::OpenUrl {
+            url: url.to_string(),
+            at: crate::whatsapp::now_millis(),
+        });
+        Ok(())
+    }
+
+    fn press_key(&self, key: Key) -> Result<(), String> {
+        self.record(RecordedInput::PressKey {
+            key,
+            at: crate::whatsapp::now_millis(),
+        });
+        Ok(())
+    }
+
+    fn type_text(&self, text: &str) -> Result<(), String> {
+        self.record(RecordedInput::TypeText {
+            text: text.to_string(),
+            at: crate::whatsapp::now_millis(),
+        });
+        Ok(())
+    }
+
+    fn copy_file(&self, path: &str) -> Result<(), String> {
+        self.record(RecordedInput::CopyFile {
+            path: path.to_string(),
+            at: crate::whatsapp::now_millis(),
+        });
+        Ok(())
+    }
+}
+
+/// Rejects every input with the given raw failure, for exercising the
+/// error paths.
+#[cfg(test)]
+pub struct FailingInput(pub &'static str);
+
+#[cfg(test)]
+impl InputSimulator for FailingInput {
+    fn open_url(&self, _url: &str) -> Result<(), String> {
+        Err(self.0.to_string())
+    }
+
+    fn press_key(&self, _key: Key) -> Result<(), String> {
+        Err(self.0.to_string())
+    }
+
+    fn type_text(&self, _text: &str) -> Result<(), String> {
+        Err(self.0.to_string())
+    }
+
+    fn copy_file(&self, _path: &str) -> Result<(), String> {
+        Err(self.0.to_string())
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_INPUT: std::cell::Cell<Option<&'static dyn InputSimulator>> = const { std::cell::Cell::new(None) };
+}
+
+/// Runs `test` with `simulator()` handing out `input` on this thread.
+#[cfg(test)]
+pub fn with_simulator<T>(input: &'static dyn InputSimulator, test: impl FnOnce() -> T) -> T {
+    let previous = TEST_INPUT.with(|cell| cell.replace(Some(input)));
+    let result = test();
+    TEST_INPUT.with(|cell| cell.set(previous));
+    result
+}
+
+static DEMO_MODE: AtomicBool = AtomicBool::new(false);
+static SYSTEM_INPUT: SystemInput = SystemInput;
+pub static DEMO_INPUT: RecordingInput = RecordingInput::new();
+
+pub fn set_demo_mode(enabled: bool) {
+    DEMO_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn demo_mode_enabled() -> bool {
+    DEMO_MODE.load(Ordering::Relaxed)
+}
+
+/// The backend matching the current `demo_mode` setting.
+pub fn simulator() -> &'static dyn InputSimulator {
+    #[cfg(test)]
+    if let Some(input) = TEST_INPUT.with(std::cell::Cell::get) {
+        return input;
+    }
+    if demo_mode_enabled() {
+        &DEMO_INPUT
+    } else {
+        &SYSTEM_INPUT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whatsapp::{ErrorKind, Remediation, SendError};
+
+    static MISSING_XDOTOOL: FailingInput = FailingInput("Failed to run xdotool: No such file or directory");
+
+    #[test]
+    fn input_failures_reach_the_caller_classified() {
+        let error = with_simulator(&MISSING_XDOTOOL, || simulator().press_key(Key::Enter))
+            .map_err(SendError::classified)
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::MissingTool);
+        assert_eq!(error.remediation, Some(Remediation::InstallXdotool));
+    }
+}
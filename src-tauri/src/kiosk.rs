@@ -0,0 +1,41 @@
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, Window};
+
+/// Label of the window from `tauri.conf.json`, where campaigns are run.
+pub const ADMIN_WINDOW: &str = "main";
+pub const KIOSK_WINDOW: &str = "kiosk";
+/// Route of the webview's attendance scanning screen.
+const KIOSK_ROUTE: &str = "index.html#/kiosk";
+
+/// Opens the always-on-top attendance window, or brings it forward when
+/// it is already open.
+pub fn open(app: &AppHandle) -> Result<(), String> {
+    if let Some(kiosk) = app.get_webview_window(KIOSK_WINDOW) {
+        kiosk.show().map_err(|e| e.to_string())?;
+        return kiosk.set_focus().map_err(|e| e.to_string());
+    }
+
+    WebviewWindowBuilder::new(app, KIOSK_WINDOW, WebviewUrl::App(KIOSK_ROUTE.into()))
+        .title("PATCH - Attendance")
+        .always_on_top(true)
+        .inner_size(480.0, 720.0)
+        .resizable(false)
+        .build()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open kiosk window: {}", e))
+}
+
+pub fn close(app: &AppHandle) -> Result<(), String> {
+    match app.get_webview_window(KIOSK_WINDOW) {
+        Some(kiosk) => kiosk.close().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Refuses commands that change anything when they come from the kiosk
+/// window, whoever is logged in there; it only scans attendance and reads.
+pub fn ensure_admin(window: &Window) -> Result<(), String> {
+    if window.label() == KIOSK_WINDOW {
+        return Err("KioskWindow: not available from the attendance kiosk".to_string());
+    }
+    Ok(())
+}
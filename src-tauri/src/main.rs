@@ -5,21 +5,37 @@ use tauri::{command, Manager, State, Emitter};
 use std::process::Command;
 use std::thread;
 use std::time::Duration;
+use std::collections::BTreeMap;
 use std::sync::Mutex;
+use std::path::PathBuf;
 
+mod background;
+mod confirm;
+mod desktop;
+mod input;
+mod kiosk;
+mod maintenance;
+mod metrics;
+mod onboarding;
+mod phone;
+mod privacy;
+mod resources;
+mod settings;
+mod tasks;
 mod whatsapp;
-use whatsapp::{WhatsAppManager, BulkMessageRequest, WhatsAppSession};
-
-#[cfg(target_os = "windows")]
-use winapi::um::winuser::{keybd_event, VK_RETURN, KEYEVENTF_KEYUP};
-
-#[cfg(target_os = "macos")]
-use core_graphics::event::{CGEvent, CGEventType, CGKeyCode};
-#[cfg(target_os = "macos")]
-use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
-
-#[cfg(target_os = "linux")]
-use std::process::Stdio;
+use background::CloseChoice;
+use confirm::Confirmable;
+use desktop::InstallationInfo;
+use input::{InjectionTest, InputResult, Key, RecordedInput};
+use maintenance::MaintenanceState;
+use onboarding::{OnboardingState, OnboardingStep, OnboardingStore};
+use settings::{AppSettings, SettingChange, SettingsStore};
+use tasks::{TaskRegistry, TaskStatus};
+use whatsapp::{WhatsAppManager, BulkMessageRequest, WhatsAppSession, CampaignRecord, CampaignStore, IncompleteCampaign, BulkSendSummary, ResendAttempt};
+use whatsapp::{BufferedEvent, CampaignDraft, EventJournal, TraceReplay, DraftStore, DraftSummary, ExclusionListStore};
+use whatsapp::{CampaignOptions, CloneOverrides, CsvColumnMapping, MessageLogExport, MessagePreview, StudentMessage, LARGE_EXPORT_ROWS};
+use whatsapp::{BenchmarkResult, BenchmarkStore, EncodedMessage, ErrorKind, Remediation};
+use whatsapp::{Artifact, DeprecatedToken, MaintenanceReport, StorageUsage, TokenRename};
 
 #[command]
 async fn check_whatsapp_desktop() -> Result<bool, String> {
@@ -27,7 +43,7 @@ async fn check_whatsapp_desktop() -> Result<bool, String> {
     {
         let output = Command::new("powershell")
             .arg("-Command")
-            .arg("Get-Process WhatsApp -ErrorAction SilentlyContinue")
+            .arg("Get-Process *WhatsApp* -ErrorAction SilentlyContinue")
             .output();
         
         match output {
@@ -60,8 +76,10 @@ async fn check_whatsapp_desktop() -> Result<bool, String> {
                 // Check if WhatsApp is installed
                 let install_check = Command::new("find")
                     .arg("/Applications")
+                    .arg("-maxdepth")
+                    .arg("1")
                     .arg("-name")
-                    .arg("WhatsApp.app")
+                    .arg("WhatsApp*.app")
                     .output();
                 
                 match install_check {
@@ -101,198 +119,665 @@ async fn check_whatsapp_desktop() -> Result<bool, String> {
 }
 
 #[command]
-async fn open_whatsapp_and_send(phone: String, message: String) -> Result<String, String> {
-    let encoded_message = urlencoding::encode(&message);
-    let url = format!("whatsapp://send?phone={}&text={}", phone, encoded_message);
-    
+async fn get_whatsapp_installation() -> Result<InstallationInfo, String> {
+    Ok(desktop::installation_info())
+}
+
+#[command]
+async fn open_whatsapp_and_send(
+    window: tauri::Window,
+    phone: String,
+    message: String,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<InputResult, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let close_dialog = settings_store.lock().map_err(|e| e.to_string())?.get().close_open_with_dialog;
+    let url = whatsapp::build_send_url(desktop::active_variant(), &phone, &message)?.url;
+    let simulator = input::simulator();
+
     // Open WhatsApp with the URL
-    #[cfg(target_os = "windows")]
-    {
-        let result = Command::new("rundll32")
-            .arg("url.dll,FileProtocolHandler")
-            .arg(&url)
-            .output();
-        
-        match result {
-            Ok(_) => {
-                // Wait for WhatsApp to open and load
-                thread::sleep(Duration::from_millis(3000));
-                
-                // Send Enter key to actually send the message
-                unsafe {
-                    keybd_event(VK_RETURN as u8, 0, 0, 0);
-                    thread::sleep(Duration::from_millis(50));
-                    keybd_event(VK_RETURN as u8, 0, KEYEVENTF_KEYUP, 0);
-                }
-                
-                Ok("Message sent successfully".to_string())
-            }
-            Err(e) => Err(format!("Failed to open WhatsApp: {}", e))
-        }
+    simulator.open_url(&url)?;
+
+    // Wait for WhatsApp to open and load
+    thread::sleep(Duration::from_millis(3000));
+
+    // Enter on Windows' app picker would change the default app
+    if !input::demo_mode_enabled() {
+        whatsapp::check_open_with_dialog(close_dialog).map_err(|e| e.message)?;
     }
-    
-    #[cfg(target_os = "macos")]
-    {
-        let result = Command::new("open")
-            .arg(&url)
-            .output();
-        
-        match result {
-            Ok(_) => {
-                // Wait for WhatsApp to open and load
-                thread::sleep(Duration::from_millis(3000));
-                
-                // Send Enter key using Core Graphics
-                let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-                    .map_err(|e| format!("Failed to create event source: {:?}", e))?;
-                
-                let key_down = CGEvent::new_keyboard_event(source.clone(), CGKeyCode(0x24), true)
-                    .map_err(|e| format!("Failed to create key down event: {:?}", e))?;
-                let key_up = CGEvent::new_keyboard_event(source, CGKeyCode(0x24), false)
-                    .map_err(|e| format!("Failed to create key up event: {:?}", e))?;
-                
-                key_down.post(CGEventType::KeyDown);
-                thread::sleep(Duration::from_millis(50));
-                key_up.post(CGEventType::KeyUp);
-                
-                Ok("Message sent successfully".to_string())
-            }
-            Err(e) => Err(format!("Failed to open WhatsApp: {}", e))
-        }
+
+    // Send Enter key to actually send the message
+    simulator.press_key(Key::Enter)?;
+
+    Ok(InputResult::new("Message sent successfully"))
+}
+
+/// The exact deeplink a message would be sent with, for diagnosing garbled
+/// text without sending anything.
+#[command]
+async fn debug_encode_message(text: String, phone: Option<String>) -> Result<EncodedMessage, String> {
+    whatsapp::build_send_url(desktop::active_variant(), phone.as_deref().unwrap_or(""), &text)
+}
+
+/// Fix-it text for a failure shown in progress events or campaign reports.
+/// Only English text exists for now.
+#[command]
+async fn explain_error(kind: ErrorKind, remediation: Option<Remediation>) -> Result<String, String> {
+    Ok(remediation
+        .or_else(|| Remediation::for_kind(kind))
+        .map(Remediation::explanation)
+        .unwrap_or("No specific fix is known. Check that WhatsApp Desktop is open and logged in, then retry.")
+        .to_string())
+}
+
+#[command]
+async fn simulate_key_press(window: tauri::Window, key: String) -> Result<InputResult, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let key = Key::parse(&key)?;
+    input::simulator().press_key(key)?;
+    Ok(InputResult::new(format!("{:?} key pressed", key)))
+}
+
+/// Manual check that typed text survives the current keyboard layout.
+/// Takes over the keyboard for a few seconds.
+#[command]
+async fn test_input_injection(window: tauri::Window) -> Result<InjectionTest, String> {
+    kiosk::ensure_admin(&window)?;
+    input::test_injection()
+}
+
+#[command]
+async fn focus_whatsapp_window() -> Result<(), String> {
+    whatsapp::focus_whatsapp_window()
+}
+
+#[command]
+async fn get_demo_mode(
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<bool, String> {
+    let store = settings_store.lock().map_err(|e| e.to_string())?;
+    Ok(store.get().demo_mode)
+}
+
+#[command]
+async fn set_demo_mode(
+    window: tauri::Window,
+    enabled: bool,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<bool, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+    let mut settings = store.get().clone();
+    settings.demo_mode = enabled;
+    store.update(settings)?;
+    Ok(enabled)
+}
+
+#[command]
+async fn get_demo_input_log() -> Result<Vec<RecordedInput>, String> {
+    Ok(input::DEMO_INPUT.recorded())
+}
+
+#[command]
+async fn initialize_whatsapp_session(
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<WhatsAppSession, String> {
+    // Not under the lock, which would block every command during the QR wait
+    let mut manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    let session = manager.initialize_session(&window).await?;
+    *whatsapp_manager.lock().map_err(|e| e.to_string())? = manager;
+    Ok(session)
+}
+
+#[command]
+async fn send_bulk_whatsapp_messages(
+    request: BulkMessageRequest,
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<BulkSendSummary, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    // Run on a clone so the lock isn't held for the whole run and the
+    // control commands below stay responsive
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    manager.send_bulk_messages(request, &settings, &window).await
+}
+
+#[command]
+async fn start_streamed_campaign(
+    window: tauri::Window,
+    options: CampaignOptions,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<String, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let default_country = settings_store.lock().map_err(|e| e.to_string())?.get().default_country.clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.start_streamed_campaign(options, &default_country)
+}
+
+#[command]
+async fn append_campaign_students(
+    window: tauri::Window,
+    campaign_id: String,
+    chunk: Vec<StudentMessage>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<usize, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let default_country = settings_store.lock().map_err(|e| e.to_string())?.get().default_country.clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.append_campaign_students(&campaign_id, &chunk, &default_country)
+}
+
+#[command]
+async fn list_exclusion_lists(
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.list_exclusion_lists())
+}
+
+#[command]
+async fn save_exclusion_list(
+    window: tauri::Window,
+    name: String,
+    members: Vec<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.save_exclusion_list(&name, members)
+}
+
+/// Asks for confirmation first; see `confirm::require`.
+#[command]
+async fn delete_exclusion_list(
+    window: tauri::Window,
+    name: String,
+    confirmation: Option<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Confirmable<()>, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    if let Some(required) = confirm::require("delete_exclusion_list", &name, confirmation, || {
+        let members = manager.list_exclusion_lists().get(&name).map_or(0, Vec::len);
+        Ok(format!("Deletes exclusion list {} with {} numbers", name, members))
+    })? {
+        return Ok(required);
     }
-    
-    #[cfg(target_os = "linux")]
-    {
-        let result = Command::new("xdg-open")
-            .arg(&url)
-            .output();
-        
-        match result {
-            Ok(_) => {
-                // Wait for WhatsApp to open and load
-                thread::sleep(Duration::from_millis(3000));
-                
-                // Send Enter key using xdotool
-                let key_result = Command::new("xdotool")
-                    .arg("key")
-                    .arg("Return")
-                    .output();
-                
-                match key_result {
-                    Ok(_) => Ok("Message sent successfully".to_string()),
-                    Err(_) => {
-                        // Fallback: try with ydotool
-                        let ydotool_result = Command::new("ydotool")
-                            .arg("key")
-                            .arg("28:1")  // Enter key
-                            .arg("28:0")
-                            .output();
-                        
-                        match ydotool_result {
-                            Ok(_) => Ok("Message sent successfully".to_string()),
-                            Err(e) => Err(format!("Failed to send key press. Install xdotool or ydotool: {}", e))
-                        }
-                    }
-                }
-            }
-            Err(e) => Err(format!("Failed to open WhatsApp: {}", e))
+    manager.delete_exclusion_list(&name).map(|result| Confirmable::Done { result })
+}
+
+#[command]
+async fn finalize_streamed_campaign(
+    campaign_id: String,
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<BulkSendSummary, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    manager.finalize_streamed_campaign(&campaign_id, &settings, &window).await
+}
+
+#[command]
+async fn resume_pending_bulk_send(
+    campaign_id: Option<String>,
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<BulkSendSummary, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    manager.resume_pending_bulk_send(campaign_id.as_deref(), &settings, &window).await
+}
+
+#[command]
+async fn resend_campaign_message(
+    window: tauri::Window,
+    campaign_id: String,
+    student_id: String,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<ResendAttempt, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    manager.resend_campaign_message(&campaign_id, &student_id, &settings).await
+}
+
+#[command]
+async fn list_incomplete_jobs(
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Vec<IncompleteCampaign>, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.list_incomplete_jobs()
+}
+
+#[command]
+async fn preview_campaign(
+    campaign_id: String,
+    limit: Option<usize>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Vec<MessagePreview>, String> {
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.preview_campaign(&campaign_id, limit.unwrap_or(20), &settings)
+}
+
+/// Renames a template token in every draft and unsent campaign. Without
+/// `apply` it only reports what would change; applying asks for
+/// confirmation first and also keeps the old name working as an alias.
+#[command]
+async fn rename_token(
+    window: tauri::Window,
+    old: String,
+    new: String,
+    apply: Option<bool>,
+    confirmation: Option<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Confirmable<TokenRename>, String> {
+    kiosk::ensure_admin(&window)?;
+    let apply = apply.unwrap_or(false);
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    if apply {
+        maintenance::ensure_writable()?;
+        let target = format!("{}->{}", old, new);
+        if let Some(required) = confirm::require("rename_token", &target, confirmation, || {
+            let report = manager.rename_token(&old, &new, false)?;
+            Ok(format!("Rewrites {} templates from {{{}}} to {{{}}}", report.changes.len(), old, new))
+        })? {
+            return Ok(required);
         }
     }
+    let rename = manager.rename_token(&old, &new, apply)?;
+
+    if rename.applied {
+        let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+        let mut settings = store.get().clone();
+        settings.token_aliases.insert(old, new);
+        store.update(settings)?;
+    }
+    Ok(Confirmable::Done { result: rename })
 }
 
+/// Tokens in `template` that only resolve through an alias.
 #[command]
-async fn simulate_key_press(key: String) -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    {
-        match key.as_str() {
-            "Enter" => {
-                unsafe {
-                    keybd_event(VK_RETURN as u8, 0, 0, 0);
-                    thread::sleep(Duration::from_millis(50));
-                    keybd_event(VK_RETURN as u8, 0, KEYEVENTF_KEYUP, 0);
-                }
-                Ok("Enter key pressed".to_string())
-            }
-            _ => Err("Unsupported key".to_string())
-        }
+async fn check_template_tokens(
+    template: String,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Vec<DeprecatedToken>, String> {
+    let settings = settings_store.lock().map_err(|e| e.to_string())?;
+    Ok(whatsapp::deprecated_tokens(&template, &settings.get().token_aliases))
+}
+
+#[command]
+async fn abort_pending_campaign(
+    window: tauri::Window,
+    campaign_id: String,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.abort_pending_campaign(&campaign_id)
+}
+
+#[command]
+async fn pause_bulk_send(
+    window: tauri::Window,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    if manager.sending_campaign().is_none() {
+        return Err("No bulk send to pause".to_string());
     }
-    
-    #[cfg(target_os = "macos")]
-    {
-        match key.as_str() {
-            "Enter" => {
-                let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-                    .map_err(|e| format!("Failed to create event source: {:?}", e))?;
-                
-                let key_down = CGEvent::new_keyboard_event(source.clone(), CGKeyCode(0x24), true)
-                    .map_err(|e| format!("Failed to create key down event: {:?}", e))?;
-                let key_up = CGEvent::new_keyboard_event(source, CGKeyCode(0x24), false)
-                    .map_err(|e| format!("Failed to create key up event: {:?}", e))?;
-                
-                key_down.post(CGEventType::KeyDown);
-                thread::sleep(Duration::from_millis(50));
-                key_up.post(CGEventType::KeyUp);
-                
-                Ok("Enter key pressed".to_string())
-            }
-            _ => Err("Unsupported key".to_string())
-        }
+    manager.pause_bulk_send(&app)
+}
+
+#[command]
+async fn resume_bulk_send(
+    window: tauri::Window,
+    force: Option<bool>,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.resume_bulk_send(force.unwrap_or(false), &app)
+}
+
+#[command]
+async fn cancel_bulk_send(
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.cancel_bulk_send()
+}
+
+#[command]
+async fn get_active_campaign(
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Option<CampaignRecord>, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.get_active_campaign()
+}
+
+#[command]
+async fn get_campaign_detail(
+    campaign_id: String,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<CampaignRecord, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.get_campaign_detail(&campaign_id)
+}
+
+#[command]
+async fn reveal_phone(
+    window: tauri::Window,
+    campaign_id: String,
+    student_id: String,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<String, String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.reveal_phone(&campaign_id, &student_id)
+}
+
+#[command]
+async fn list_campaigns(
+    label: Option<String>,
+    search: Option<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Vec<CampaignRecord>, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.list_campaigns(label.as_deref(), search.as_deref())
+}
+
+#[command]
+async fn save_campaign_draft(
+    window: tauri::Window,
+    draft: CampaignDraft,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<CampaignDraft, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let retention = settings_store.lock().map_err(|e| e.to_string())?.get().draft_retention();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.save_campaign_draft(draft, retention)
+}
+
+#[command]
+async fn list_campaign_drafts(
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Vec<DraftSummary>, String> {
+    let retention = settings_store.lock().map_err(|e| e.to_string())?.get().draft_retention();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.list_campaign_drafts(retention)
+}
+
+#[command]
+async fn load_campaign_draft(
+    draft_id: String,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<CampaignDraft, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.load_campaign_draft(&draft_id)
+}
+
+/// Asks for confirmation first; see `confirm::require`.
+#[command]
+async fn delete_campaign_draft(
+    window: tauri::Window,
+    draft_id: String,
+    confirmation: Option<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Confirmable<()>, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    if let Some(required) = confirm::require("delete_campaign_draft", &draft_id, confirmation, || {
+        let draft = manager.load_campaign_draft(&draft_id)?;
+        let name = draft.options.name.unwrap_or(draft_id.clone());
+        Ok(format!("Deletes draft {} with {} students", name, draft.students.len()))
+    })? {
+        return Ok(required);
     }
-    
-    #[cfg(target_os = "linux")]
-    {
-        match key.as_str() {
-            "Enter" => {
-                let result = Command::new("xdotool")
-                    .arg("key")
-                    .arg("Return")
-                    .output();
-                
-                match result {
-                    Ok(_) => Ok("Enter key pressed".to_string()),
-                    Err(_) => {
-                        // Fallback to ydotool
-                        let ydotool_result = Command::new("ydotool")
-                            .arg("key")
-                            .arg("28:1")
-                            .arg("28:0")
-                            .output();
-                        
-                        match ydotool_result {
-                            Ok(_) => Ok("Enter key pressed".to_string()),
-                            Err(e) => Err(format!("Key press failed: {}", e))
-                        }
-                    }
-                }
-            }
-            _ => Err("Unsupported key".to_string())
+    manager.delete_campaign_draft(&draft_id).map(|result| Confirmable::Done { result })
+}
+
+#[command]
+async fn clone_campaign(
+    window: tauri::Window,
+    campaign_id: String,
+    overrides: Option<CloneOverrides>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<CampaignRecord, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.clone_campaign(&campaign_id, overrides.unwrap_or_default())
+}
+
+/// Extracts one campaign's event journal entries to `destination`, for
+/// attaching to a support ticket. Runs as a task whose result is how many
+/// entries were written.
+#[command]
+async fn export_event_journal(
+    window: tauri::Window,
+    campaign_id: String,
+    destination: String,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    task_registry: State<'_, TaskRegistry>
+) -> Result<String, String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    resources::ensure_disk_space(std::path::Path::new(&destination), manager.journal_bytes())?;
+    task_registry.start(&app, "export_event_journal", move |task| {
+        manager.export_event_journal(&campaign_id, std::path::Path::new(&destination), task)
+    })
+}
+
+/// Writes the outcome of every message the journal still holds, filtered,
+/// as CSV or JSON lines. Runs as a task whose result is how many rows were
+/// written; asks for confirmation first above `LARGE_EXPORT_ROWS`.
+#[command]
+async fn export_message_log(
+    window: tauri::Window,
+    export: MessageLogExport,
+    confirmation: Option<String>,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    task_registry: State<'_, TaskRegistry>
+) -> Result<Confirmable<String>, String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    let estimate = manager.estimate_message_log_rows();
+    if estimate > LARGE_EXPORT_ROWS {
+        if let Some(required) = confirm::require("export_message_log", &export.destination, confirmation, || {
+            Ok(format!("Exports up to {} messages to {}", estimate, export.destination))
+        })? {
+            return Ok(required);
         }
     }
+    resources::ensure_disk_space(std::path::Path::new(&export.destination), manager.journal_bytes())?;
+    task_registry
+        .start(&app, "export_message_log", move |task| manager.export_message_log(&export, task))
+        .map(|result| Confirmable::Done { result })
 }
 
+/// Runs the daily storage cleanup now, or just the part for `only`; the
+/// report also goes out as `maintenance-report`.
 #[command]
-async fn initialize_whatsapp_session(
+async fn run_maintenance_now(
+    window: tauri::Window,
+    only: Option<Artifact>,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<MaintenanceReport, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let settings = settings_store.lock().map_err(|e| e.to_string())?.get().clone();
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    manager.run_maintenance(&app, &settings, only)
+}
+
+/// Space each category of the app data takes up; `cleanup` names what
+/// `run_maintenance_now` takes to clean it.
+#[command]
+async fn get_storage_usage(
     window: tauri::Window,
     whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
-) -> Result<WhatsAppSession, String> {
-    let mut manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
-    manager.initialize_session(&window).await
+) -> Result<StorageUsage, String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.storage_usage())
 }
 
+/// Developer tool: redoes a recorded campaign's ordering and rendering
+/// without sending and reports where it differs from the recording.
 #[command]
-async fn send_bulk_whatsapp_messages(
-    request: BulkMessageRequest,
+async fn replay_campaign_trace(
     window: tauri::Window,
+    path: String,
     whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
-) -> Result<(), String> {
+) -> Result<TraceReplay, String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    manager.replay_campaign_trace(std::path::Path::new(&path))
+}
+
+#[command]
+async fn update_campaign_meta(
+    window: tauri::Window,
+    campaign_id: String,
+    name: Option<String>,
+    label: Option<String>,
+    notes: Option<String>,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<CampaignRecord, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
     let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
-    manager.send_bulk_messages(request, &window).await
+    manager.update_campaign_meta(&campaign_id, name, label, notes)
+}
+
+/// Starts the import as a task and returns its id; the task's result is a
+/// `CsvCampaignImport`.
+#[command]
+async fn build_campaign_from_csv(
+    window: tauri::Window,
+    path: String,
+    column_mapping: CsvColumnMapping,
+    app: tauri::AppHandle,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+    task_registry: State<'_, TaskRegistry>
+) -> Result<String, String> {
+    kiosk::ensure_admin(&window)?;
+    let default_country = settings_store.lock().map_err(|e| e.to_string())?.get().default_country.clone();
+    let csv_bytes = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?.len();
+    resources::ensure_memory(csv_bytes.saturating_mul(resources::CSV_IMPORT_MEMORY_FACTOR))?;
+    task_registry.start(&app, "build_campaign_from_csv", move |task| {
+        whatsapp::build_campaign_from_csv(std::path::Path::new(&path), &column_mapping, &default_country, task)
+    })
+}
+
+/// Timings of the send pipeline and hot commands: totals since start and
+/// the change since the previous call.
+#[command]
+async fn get_performance_metrics() -> Result<metrics::PerformanceMetrics, String> {
+    Ok(metrics::snapshot())
+}
+
+/// Progress and, once finished, the result or error of a task started by
+/// a heavy command; for catching up after a webview reload.
+#[command]
+async fn get_task_status(
+    task_id: String,
+    task_registry: State<'_, TaskRegistry>
+) -> Result<TaskStatus, String> {
+    task_registry.status(&task_id)
+}
+
+/// Asks a running task to stop; it cleans up what it wrote and ends as
+/// `cancelled`.
+#[command]
+async fn cancel_task(
+    window: tauri::Window,
+    task_id: String,
+    task_registry: State<'_, TaskRegistry>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    task_registry.cancel(&task_id)
+}
+
+/// Measures this machine's per-message overhead in demo mode. Pass a
+/// campaign size (and interval) to get a projected run time.
+#[command]
+async fn benchmark_send_pipeline(
+    window: tauri::Window,
+    sample_size: usize,
+    campaign_size: Option<usize>,
+    interval_seconds: Option<u64>,
+    benchmark_store: State<'_, BenchmarkStore>
+) -> Result<BenchmarkResult, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut result = whatsapp::run_benchmark(sample_size)?;
+    benchmark_store.save(&result)?;
+
+    if let Some(campaign_size) = campaign_size {
+        result.projection = Some(result.project(campaign_size, interval_seconds.unwrap_or(0)));
+    }
+    Ok(result)
+}
+
+#[command]
+async fn get_send_benchmark(
+    campaign_size: Option<usize>,
+    interval_seconds: Option<u64>,
+    benchmark_store: State<'_, BenchmarkStore>
+) -> Result<Option<BenchmarkResult>, String> {
+    let mut result = benchmark_store.latest(&whatsapp::machine_id());
+    if let (Some(result), Some(campaign_size)) = (result.as_mut(), campaign_size) {
+        result.projection = Some(result.project(campaign_size, interval_seconds.unwrap_or(0)));
+    }
+    Ok(result)
 }
 
 #[command]
 async fn disconnect_whatsapp_session(
+    window: tauri::Window,
     whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
 ) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
     let mut manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
     manager.disconnect();
     Ok(())
@@ -306,17 +791,344 @@ async fn get_whatsapp_status(
     Ok(manager.is_connected())
 }
 
+#[command]
+async fn get_settings(
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<AppSettings, String> {
+    let store = settings_store.lock().map_err(|e| e.to_string())?;
+    Ok(store.get().clone())
+}
+
+#[command]
+async fn update_settings(
+    window: tauri::Window,
+    settings: AppSettings,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<AppSettings, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+    store.update(settings)?;
+    Ok(store.get().clone())
+}
+
+/// Makes every mutating command fail and pauses a running send, e.g.
+/// while a backup is restored. Ends on its own after `max_minutes`.
+#[command]
+async fn enter_maintenance_mode(
+    reason: String,
+    max_minutes: Option<u64>,
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<MaintenanceState, String> {
+    kiosk::ensure_admin(&window)?;
+    let state = maintenance::enter(&reason, max_minutes.unwrap_or(maintenance::DEFAULT_MAINTENANCE_MINUTES));
+    whatsapp_manager.lock().map_err(|e| e.to_string())?.pause_for_maintenance(window.app_handle())?;
+    window.emit("maintenance-mode-changed", &state).map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+#[command]
+async fn exit_maintenance_mode(
+    window: tauri::Window,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<MaintenanceState, String> {
+    kiosk::ensure_admin(&window)?;
+    let state = maintenance::exit();
+    whatsapp_manager.lock().map_err(|e| e.to_string())?.resume_after_maintenance(window.app_handle())?;
+    window.emit("maintenance-mode-changed", &state).map_err(|e| e.to_string())?;
+    Ok(state)
+}
+
+/// Opens the attendance kiosk next to the admin window. Commands from the
+/// kiosk that change anything are refused; campaign events only go to
+/// the admin window.
+#[command]
+async fn open_kiosk_window(window: tauri::Window, app: tauri::AppHandle) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    kiosk::open(&app)
+}
+
+/// Answers `close-requested-while-sending`.
+#[command]
+async fn resolve_close_request(
+    window: tauri::Window,
+    choice: CloseChoice,
+    app: tauri::AppHandle,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?.clone();
+    background::resolve_close(&app, &manager, choice)
+}
+
+/// Events emitted after `sequence`, to catch up once the window is shown
+/// again.
+#[command]
+async fn get_missed_events(
+    sequence: u64,
+    whatsapp_manager: State<'_, Mutex<WhatsAppManager>>
+) -> Result<Vec<BufferedEvent>, String> {
+    let manager = whatsapp_manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.events_since(sequence))
+}
+
+#[command]
+async fn close_kiosk_window(app: tauri::AppHandle) -> Result<(), String> {
+    kiosk::close(&app)
+}
+
+#[command]
+async fn get_maintenance_state() -> Result<MaintenanceState, String> {
+    Ok(maintenance::state())
+}
+
+/// Hooks and the supervisor's number are left out; `include_secrets` is
+/// refused while archives are unencrypted.
+#[command]
+async fn export_settings(
+    window: tauri::Window,
+    path: String,
+    include_secrets: Option<bool>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<(), String> {
+    kiosk::ensure_admin(&window)?;
+    let store = settings_store.lock().map_err(|e| e.to_string())?;
+    store.export(std::path::Path::new(&path), include_secrets.unwrap_or(false))
+}
+
+#[command]
+async fn preview_settings_import(
+    path: String,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Vec<SettingChange>, String> {
+    let store = settings_store.lock().map_err(|e| e.to_string())?;
+    store.preview_import(std::path::Path::new(&path))
+}
+
+/// Device-scoped fields (automation and window behaviour) keep this
+/// machine's values unless `include_device_fields` is set, and secret ones
+/// (hooks, supervisor number) unless `include_secrets` is. Asks for
+/// confirmation first; see `confirm::require`.
+#[command]
+async fn import_settings(
+    window: tauri::Window,
+    path: String,
+    include_device_fields: Option<bool>,
+    include_secrets: Option<bool>,
+    confirmation: Option<String>,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<Confirmable<Vec<SettingChange>>, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+    let path = std::path::PathBuf::from(path);
+    let (include_device_fields, include_secrets) = (include_device_fields.unwrap_or(false), include_secrets.unwrap_or(false));
+    if let Some(required) = confirm::require("import_settings", &path.display().to_string(), confirmation, || {
+        let changes = store
+            .preview_import(&path)?
+            .into_iter()
+            .filter(|change| (include_device_fields || !change.device_scoped) && (include_secrets || !change.secret))
+            .count();
+        Ok(format!("Overwrites {} settings from {}", changes, path.display()))
+    })? {
+        return Ok(required);
+    }
+    store
+        .import(&path, include_device_fields, include_secrets)
+        .map(|result| Confirmable::Done { result })
+}
+
+#[command]
+async fn rollback_settings_import(
+    window: tauri::Window,
+    settings_store: State<'_, Mutex<SettingsStore>>
+) -> Result<AppSettings, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+    store.rollback_import()?;
+    Ok(store.get().clone())
+}
+
+#[command]
+async fn get_onboarding_state(
+    onboarding_store: State<'_, Mutex<OnboardingStore>>
+) -> Result<OnboardingState, String> {
+    let store = onboarding_store.lock().map_err(|e| e.to_string())?;
+    Ok(store.state())
+}
+
+#[command]
+async fn complete_onboarding_step(
+    window: tauri::Window,
+    step: OnboardingStep,
+    payload: serde_json::Value,
+    settings_store: State<'_, Mutex<SettingsStore>>,
+    onboarding_store: State<'_, Mutex<OnboardingStore>>
+) -> Result<OnboardingState, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    // Diagnostics check the variant sends will actually go through
+    let whatsapp_found = step == OnboardingStep::WhatsappDiagnostics && {
+        let info = desktop::installation_info();
+        if info.open_with_dialog_visible == Some(true) {
+            return Err("Windows is asking which app should open WhatsApp links. Choose WhatsApp, tick \"Always use this app\" and run the check again".to_string());
+        }
+        info.variant.is_some() && info.protocol_handler_registered != Some(false)
+    };
+
+    {
+        let mut store = settings_store.lock().map_err(|e| e.to_string())?;
+        let mut settings = store.get().clone();
+        onboarding::apply_step(step, payload, &mut settings, whatsapp_found)?;
+        store.update(settings)?;
+    }
+
+    let mut store = onboarding_store.lock().map_err(|e| e.to_string())?;
+    store.complete(step)?;
+    Ok(store.state())
+}
+
+#[command]
+async fn complete_onboarding_from_backup(
+    window: tauri::Window,
+    onboarding_store: State<'_, Mutex<OnboardingStore>>
+) -> Result<OnboardingState, String> {
+    kiosk::ensure_admin(&window)?;
+    maintenance::ensure_writable()?;
+    let mut store = onboarding_store.lock().map_err(|e| e.to_string())?;
+    store.complete_from_backup()?;
+    Ok(store.state())
+}
+
+/// Must match `identifier` in tauri.conf.json; the watchdog check runs
+/// without starting Tauri, so it resolves the data dir itself.
+const APP_IDENTIFIER: &str = "com.arpitupadhyay.patch-smart-library";
+
+/// Tauri's app data dir, worked out from the environment.
+fn cli_data_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+
+    #[cfg(target_os = "linux")]
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+
+    base.map(|base| base.join(APP_IDENTIFIER))
+}
+
 fn main() {
+    // `--watchdog-check [data dir]` is for Task Scheduler and similar
+    // monitors: it exits non-zero when a running campaign's heartbeat has
+    // gone stale
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--watchdog-check") {
+        let data_dir = args.get(position + 1).map(PathBuf::from).or_else(cli_data_dir);
+        let code = match data_dir {
+            Some(data_dir) => whatsapp::check_heartbeat(&whatsapp::heartbeat_path(&data_dir)),
+            None => {
+                eprintln!("Could not determine the app data directory");
+                2
+            }
+        };
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
-        .manage(Mutex::new(WhatsAppManager::new()))
+        .setup(|app| {
+            let config_dir = app.path().app_config_dir()?;
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(Mutex::new(SettingsStore::load(config_dir.clone())));
+            app.manage(Mutex::new(OnboardingStore::load(config_dir)));
+            app.manage(BenchmarkStore::new(data_dir.clone()));
+            app.manage(TaskRegistry::default());
+            app.manage(Mutex::new(WhatsAppManager::new(
+                CampaignStore::new(data_dir.clone()),
+                DraftStore::new(data_dir.clone()),
+                ExclusionListStore::new(data_dir.clone()),
+                whatsapp::heartbeat_path(&data_dir),
+                EventJournal::open(&data_dir),
+                whatsapp::trace_dir(&data_dir),
+                whatsapp::token_rename_dir(&data_dir),
+            )));
+            background::spawn_daily_maintenance(app.handle().clone());
+            Ok(())
+        })
+        .on_window_event(background::on_window_event)
         .invoke_handler(tauri::generate_handler![
             check_whatsapp_desktop,
+            get_whatsapp_installation,
             open_whatsapp_and_send,
+            debug_encode_message,
+            explain_error,
             simulate_key_press,
+            test_input_injection,
+            focus_whatsapp_window,
+            get_demo_mode,
+            set_demo_mode,
+            get_demo_input_log,
             initialize_whatsapp_session,
             send_bulk_whatsapp_messages,
+            start_streamed_campaign,
+            append_campaign_students,
+            finalize_streamed_campaign,
+            resume_pending_bulk_send,
+            resend_campaign_message,
+            list_incomplete_jobs,
+            list_exclusion_lists,
+            save_exclusion_list,
+            delete_exclusion_list,
+            preview_campaign,
+            abort_pending_campaign,
+            rename_token,
+            check_template_tokens,
+            pause_bulk_send,
+            resume_bulk_send,
+            cancel_bulk_send,
+            get_active_campaign,
+            get_campaign_detail,
+            reveal_phone,
+            list_campaigns,
+            update_campaign_meta,
+            export_event_journal,
+            export_message_log,
+            run_maintenance_now,
+            get_storage_usage,
+            replay_campaign_trace,
+            clone_campaign,
+            save_campaign_draft,
+            list_campaign_drafts,
+            load_campaign_draft,
+            delete_campaign_draft,
+            build_campaign_from_csv,
+            get_task_status,
+            get_performance_metrics,
+            cancel_task,
+            benchmark_send_pipeline,
+            get_send_benchmark,
             disconnect_whatsapp_session,
-            get_whatsapp_status
+            get_whatsapp_status,
+            get_settings,
+            update_settings,
+            export_settings,
+            preview_settings_import,
+            import_settings,
+            rollback_settings_import,
+            enter_maintenance_mode,
+            exit_maintenance_mode,
+            get_maintenance_state,
+            open_kiosk_window,
+            close_kiosk_window,
+            resolve_close_request,
+            get_missed_events,
+            get_onboarding_state,
+            complete_onboarding_step,
+            complete_onboarding_from_backup
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
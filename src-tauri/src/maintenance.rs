@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::whatsapp::now_millis;
+
+/// Maintenance mode ends on its own after this long unless a shorter limit
+/// is given, so a crashed restore can't leave the app read-only.
+pub const DEFAULT_MAINTENANCE_MINUTES: u64 = 15;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceState {
+    pub active: bool,
+    pub reason: Option<String>,
+    pub started_at: Option<u64>,
+    pub expires_at: Option<u64>,
+}
+
+struct Maintenance {
+    reason: String,
+    started_at: u64,
+    expires_at: u64,
+}
+
+static MAINTENANCE: Mutex<Option<Maintenance>> = Mutex::new(None);
+
+pub fn enter(reason: &str, max_minutes: u64) -> MaintenanceState {
+    let started_at = now_millis();
+    if let Ok(mut maintenance) = MAINTENANCE.lock() {
+        *maintenance = Some(Maintenance {
+            reason: reason.to_string(),
+            started_at,
+            expires_at: started_at + max_minutes * 60 * 1000,
+        });
+    }
+    state()
+}
+
+pub fn exit() -> MaintenanceState {
+    if let Ok(mut maintenance) = MAINTENANCE.lock() {
+        *maintenance = None;
+    }
+    state()
+}
+
+pub fn state() -> MaintenanceState {
+    let Ok(mut maintenance) = MAINTENANCE.lock() else {
+        return MaintenanceState::default();
+    };
+    if maintenance.as_ref().is_some_and(|m| now_millis() >= m.expires_at) {
+        *maintenance = None;
+    }
+
+    match maintenance.as_ref() {
+        Some(m) => MaintenanceState {
+            active: true,
+            reason: Some(m.reason.clone()),
+            started_at: Some(m.started_at),
+            expires_at: Some(m.expires_at),
+        },
+        None => MaintenanceState::default(),
+    }
+}
+
+/// Fails mutating commands while maintenance mode is on; reads go through.
+pub fn ensure_writable() -> Result<(), String> {
+    match state().reason {
+        Some(reason) => Err(format!("MaintenanceMode: {}", reason)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::whatsapp::now_millis;
+
+/// Upper bounds of the histogram buckets; a last bucket takes the rest.
+const BUCKET_BOUNDS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 5000, 30000];
+const BUCKETS: usize = BUCKET_BOUNDS_MS.len() + 1;
+
+/// Timed stages of the send pipeline and the hot commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Handing a whatsapp:// link to the OS.
+    DeeplinkOpen,
+    /// A run held because WhatsApp wasn't in front.
+    FocusWait,
+    /// Pressing keys or typing text into WhatsApp.
+    KeyInjection,
+    /// Journaling, buffering and emitting one event to the webview.
+    EventEmit,
+    ListCampaigns,
+    ListDrafts,
+}
+
+const STAGES: [Stage; 6] = [
+    Stage::DeeplinkOpen,
+    Stage::FocusWait,
+    Stage::KeyInjection,
+    Stage::EventEmit,
+    Stage::ListCampaigns,
+    Stage::ListDrafts,
+];
+
+/// Plain atomics, so recording costs a few increments whether or not
+/// anyone reads the numbers.
+struct Histogram {
+    count: AtomicU64,
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+    /// Largest sample since the last `get_performance_metrics`.
+    recent_max_micros: AtomicU64,
+    buckets: [AtomicU64; BUCKETS],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Histogram = Histogram {
+    count: ZERO,
+    total_micros: ZERO,
+    max_micros: ZERO,
+    recent_max_micros: ZERO,
+    buckets: [ZERO; BUCKETS],
+};
+
+static HISTOGRAMS: [Histogram; STAGES.len()] = [EMPTY; STAGES.len()];
+/// Counters as of the last read, for the deltas.
+static LAST_READ: Mutex<Option<(u64, Vec<Counters>)>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+struct Counters {
+    count: u64,
+    total_micros: u64,
+    buckets: [u64; BUCKETS],
+}
+
+impl Histogram {
+    fn record(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let millis = micros / 1000;
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|bound| millis < *bound).unwrap_or(BUCKETS - 1);
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+        self.recent_max_micros.fetch_max(micros, Ordering::Relaxed);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn counters(&self) -> Counters {
+        let mut buckets = [0; BUCKETS];
+        for (count, bucket) in buckets.iter_mut().zip(&self.buckets) {
+            *count = bucket.load(Ordering::Relaxed);
+        }
+        Counters {
+            count: self.count.load(Ordering::Relaxed),
+            total_micros: self.total_micros.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+pub fn record(stage: Stage, elapsed: Duration) {
+    HISTOGRAMS[stage as usize].record(elapsed);
+}
+
+/// Runs `work` and records how long it took under `stage`.
+pub fn time<T>(stage: Stage, work: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = work();
+    record(stage, started.elapsed());
+    result
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketCount {
+    /// `None` for the last, open-ended bucket.
+    pub below_ms: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageMetrics {
+    pub stage: Stage,
+    pub count: u64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    /// Estimated from the buckets: the bound of the bucket holding it.
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub buckets: Vec<BucketCount>,
+}
+
+impl StageMetrics {
+    fn new(stage: Stage, counters: &Counters, max_micros: u64) -> Self {
+        Self {
+            stage,
+            count: counters.count,
+            mean_ms: match counters.count {
+                0 => 0.0,
+                count => counters.total_micros as f64 / count as f64 / 1000.0,
+            },
+            max_ms: max_micros as f64 / 1000.0,
+            p50_ms: percentile(counters, 0.50),
+            p95_ms: percentile(counters, 0.95),
+            buckets: counters
+                .buckets
+                .iter()
+                .enumerate()
+                .map(|(index, count)| BucketCount {
+                    below_ms: BUCKET_BOUNDS_MS.get(index).copied(),
+                    count: *count,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn percentile(counters: &Counters, quantile: f64) -> Option<u64> {
+    if counters.count == 0 {
+        return None;
+    }
+    let target = (counters.count as f64 * quantile).ceil() as u64;
+    let mut seen = 0;
+    for (index, count) in counters.buckets.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return BUCKET_BOUNDS_MS.get(index).copied();
+        }
+    }
+    None
+}
+
+/// What `get_performance_metrics` returns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub read_at: u64,
+    /// Since the app started.
+    pub totals: Vec<StageMetrics>,
+    /// Since the previous read, or since the app started on the first.
+    pub since_last_read: Vec<StageMetrics>,
+    pub last_read_at: Option<u64>,
+}
+
+/// Current aggregates; also starts a new delta window.
+pub fn snapshot() -> PerformanceMetrics {
+    let read_at = now_millis();
+    let current: Vec<Counters> = HISTOGRAMS.iter().map(Histogram::counters).collect();
+    let previous = LAST_READ
+        .lock()
+        .map(|mut last| last.replace((read_at, current.clone())))
+        .unwrap_or_default();
+
+    let mut totals = Vec::new();
+    let mut since_last_read = Vec::new();
+    for (index, stage) in STAGES.iter().enumerate() {
+        let histogram = &HISTOGRAMS[index];
+        totals.push(StageMetrics::new(*stage, &current[index], histogram.max_micros.load(Ordering::Relaxed)));
+
+        let mut delta = current[index];
+        if let Some((_, previous)) = &previous {
+            // Saturating: two reads at once may see each other's counters
+            delta.count = delta.count.saturating_sub(previous[index].count);
+            delta.total_micros = delta.total_micros.saturating_sub(previous[index].total_micros);
+            for (count, before) in delta.buckets.iter_mut().zip(previous[index].buckets) {
+                *count = count.saturating_sub(before);
+            }
+        }
+        since_last_read.push(StageMetrics::new(*stage, &delta, histogram.recent_max_micros.swap(0, Ordering::Relaxed)));
+    }
+
+    PerformanceMetrics {
+        read_at,
+        totals,
+        since_last_read,
+        last_read_at: previous.map(|(at, _)| at),
+    }
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::phone::normalize_to_e164;
+use crate::settings::{AppSettings, LibraryProfile};
+use crate::whatsapp::now_millis;
+
+const ONBOARDING_FILE: &str = "onboarding.json";
+const MAX_FOOTER_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    LibraryProfile,
+    DefaultCountry,
+    WhatsappDiagnostics,
+    FirstStudentImport,
+    MessageFooter,
+}
+
+impl OnboardingStep {
+    /// Wizard order.
+    pub const ALL: [OnboardingStep; 5] = [
+        OnboardingStep::LibraryProfile,
+        OnboardingStep::DefaultCountry,
+        OnboardingStep::WhatsappDiagnostics,
+        OnboardingStep::FirstStudentImport,
+        OnboardingStep::MessageFooter,
+    ];
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OnboardingRecord {
+    /// Step -> when it was first completed. Entries are never removed.
+    completed: HashMap<OnboardingStep, u64>,
+    restored_from_backup_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingStepState {
+    pub step: OnboardingStep,
+    pub completed_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingState {
+    pub complete: bool,
+    pub steps: Vec<OnboardingStepState>,
+    pub restored_from_backup_at: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefaultCountryPayload {
+    country: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FirstStudentImportPayload {
+    imported_count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageFooterPayload {
+    footer: String,
+}
+
+pub struct OnboardingStore {
+    path: PathBuf,
+    record: OnboardingRecord,
+}
+
+impl OnboardingStore {
+    pub fn load(config_dir: PathBuf) -> Self {
+        let path = config_dir.join(ONBOARDING_FILE);
+        let record = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self { path, record }
+    }
+
+    pub fn state(&self) -> OnboardingState {
+        let steps: Vec<OnboardingStepState> = OnboardingStep::ALL
+            .iter()
+            .map(|step| OnboardingStepState {
+                step: *step,
+                completed_at: self.record.completed.get(step).copied(),
+            })
+            .collect();
+
+        OnboardingState {
+            complete: steps.iter().all(|s| s.completed_at.is_some()),
+            steps,
+            restored_from_backup_at: self.record.restored_from_backup_at,
+        }
+    }
+
+    /// Marks a step done. Completing it again keeps the original timestamp,
+    /// so re-running diagnostics later never puts the wizard back.
+    pub fn complete(&mut self, step: OnboardingStep) -> Result<(), String> {
+        self.record.completed.entry(step).or_insert_with(now_millis);
+        self.save()
+    }
+
+    /// A restored backup brings a configured library with it.
+    pub fn complete_from_backup(&mut self) -> Result<(), String> {
+        let now = now_millis();
+        for step in OnboardingStep::ALL {
+            self.record.completed.entry(step).or_insert(now);
+        }
+        self.record.restored_from_backup_at = Some(now);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(&self.record).map_err(|e| e.to_string())?;
+        fs::write(&self.path, contents).map_err(|e| format!("Failed to save onboarding state: {}", e))
+    }
+}
+
+/// Validates a step's payload and writes it into `settings`. The caller
+/// persists the settings and marks the step complete on success.
+pub fn apply_step(
+    step: OnboardingStep,
+    payload: Value,
+    settings: &mut AppSettings,
+    whatsapp_found: bool,
+) -> Result<(), String> {
+    match step {
+        OnboardingStep::LibraryProfile => {
+            let profile: LibraryProfile = parse_payload(payload)?;
+            if profile.name.trim().is_empty() {
+                return Err("Library name is required".to_string());
+            }
+            if let Some(phone) = profile.contact_phone.as_deref().filter(|p| !p.trim().is_empty()) {
+                if normalize_to_e164(phone, &settings.default_country).is_none() {
+                    return Err("Invalid contact phone number".to_string());
+                }
+            }
+            settings.library_profile = Some(profile);
+        }
+        OnboardingStep::DefaultCountry => {
+            let payload: DefaultCountryPayload = parse_payload(payload)?;
+            let country = payload.country.trim().to_uppercase();
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err("Country must be a two-letter ISO code, e.g. IN".to_string());
+            }
+            settings.default_country = country;
+        }
+        OnboardingStep::WhatsappDiagnostics => {
+            if !whatsapp_found {
+                return Err("WhatsApp Desktop was not found. Install or start it and run the check again".to_string());
+            }
+        }
+        OnboardingStep::FirstStudentImport => {
+            // Students live in the webview; it reports how many it imported
+            let payload: FirstStudentImportPayload = parse_payload(payload)?;
+            if payload.imported_count == 0 {
+                return Err("Import at least one student to finish this step".to_string());
+            }
+        }
+        OnboardingStep::MessageFooter => {
+            let payload: MessageFooterPayload = parse_payload(payload)?;
+            let footer = payload.footer.trim();
+            if footer.chars().count() > MAX_FOOTER_LENGTH {
+                return Err(format!("Footer must be at most {} characters", MAX_FOOTER_LENGTH));
+            }
+            settings.message_footer = footer.to_string();
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_payload<T: serde::de::DeserializeOwned>(payload: Value) -> Result<T, String> {
+    serde_json::from_value(payload).map_err(|e| format!("Invalid payload: {}", e))
+}
@@ -0,0 +1,31 @@
+/// Normalizes a raw phone number to E.164 (`+919876543210`), mirroring
+/// `normalizeToE164` in src/utils/phone.ts so both sides agree on what a
+/// valid number is. Returns `None` for anything that can't be dialled.
+pub fn normalize_to_e164(raw: &str, default_country: &str) -> Option<String> {
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return None;
+    }
+
+    if default_country == "IN" {
+        // Already has the 91 country code
+        if digits.starts_with("91") && digits.len() == 12 {
+            return Some(format!("+{}", digits));
+        }
+
+        let without_leading_zero = digits.strip_prefix('0').unwrap_or(&digits);
+
+        // Indian mobile numbers are 10 digits starting with 6-9
+        if without_leading_zero.len() == 10 && without_leading_zero.starts_with(['6', '7', '8', '9']) {
+            return Some(format!("+91{}", without_leading_zero));
+        }
+
+        return None;
+    }
+
+    if (10..=15).contains(&digits.len()) {
+        return Some(format!("+{}", digits));
+    }
+
+    None
+}
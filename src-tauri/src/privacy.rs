@@ -0,0 +1,128 @@
+use serde::{Deserialize, Deserializer, Serializer};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static MASK_PHONE_NUMBERS: AtomicBool = AtomicBool::new(false);
+
+/// Masked phones handed to the webview, for taking them back; `None` when
+/// two numbers mask alike.
+static MASKED_PHONES: Mutex<Option<HashMap<String, Option<String>>>> = Mutex::new(None);
+
+thread_local! {
+    static UNMASKED: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_phone_masking(enabled: bool) {
+    MASK_PHONE_NUMBERS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn phone_masking_enabled() -> bool {
+    MASK_PHONE_NUMBERS.load(Ordering::Relaxed)
+}
+
+/// Masks every digit except the first two and the last two,
+/// e.g. `9876543221` becomes `98XXXXXX21`.
+pub fn mask_phone(phone: &str) -> String {
+    let digit_count = phone.chars().filter(|c| c.is_ascii_digit()).count();
+    if digit_count < 5 {
+        return phone.chars().map(|c| if c.is_ascii_digit() { 'X' } else { c }).collect();
+    }
+
+    let mut seen = 0;
+    phone
+        .chars()
+        .map(|c| {
+            if !c.is_ascii_digit() {
+                return c;
+            }
+            seen += 1;
+            if seen <= 2 || seen > digit_count - 2 {
+                c
+            } else {
+                'X'
+            }
+        })
+        .collect()
+}
+
+/// Serializer for phone fields. Everything serialized is assumed to be
+/// headed for the webview and masked; files the backend keeps are written
+/// inside `unmasked`.
+pub fn serialize_phone<S: Serializer>(phone: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&screen(phone))
+}
+
+pub fn serialize_optional_phone<S: Serializer>(phone: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match phone {
+        Some(phone) => serialize_phone(phone, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// For lists mixing student ids and phones; only what looks like a phone
+/// is masked.
+pub fn serialize_entries<S: Serializer>(entries: &HashSet<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(entries.iter().map(|entry| if looks_like_phone(entry) { screen(entry) } else { entry.clone() }))
+}
+
+/// Deserializer for phone fields the webview sends back: a phone it was
+/// given masked becomes the real one again.
+pub fn deserialize_phone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let phone = String::deserialize(deserializer)?;
+    unmask(phone).map_err(serde::de::Error::custom)
+}
+
+pub fn deserialize_optional_phone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(unmask)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Runs `write` with phones serialized in full, for what goes to disk or
+/// to hook scripts rather than the webview.
+pub fn unmasked<T>(write: impl FnOnce() -> T) -> T {
+    let previous = UNMASKED.with(|unmasked| unmasked.replace(true));
+    let result = write();
+    UNMASKED.with(|unmasked| unmasked.set(previous));
+    result
+}
+
+/// `phone` as the webview may see it, remembering masked ones.
+pub fn screen(phone: &str) -> String {
+    if !phone_masking_enabled() || UNMASKED.with(Cell::get) {
+        return phone.to_string();
+    }
+
+    let masked = mask_phone(phone);
+    if masked != phone {
+        if let Ok(mut known) = MASKED_PHONES.lock() {
+            known
+                .get_or_insert_with(HashMap::new)
+                .entry(masked.clone())
+                .and_modify(|real| {
+                    if real.as_deref() != Some(phone) {
+                        *real = None;
+                    }
+                })
+                .or_insert_with(|| Some(phone.to_string()));
+        }
+    }
+    masked
+}
+
+fn looks_like_phone(entry: &str) -> bool {
+    entry.chars().all(|c| c.is_ascii_digit() || "+-() ".contains(c))
+        && entry.chars().filter(|c| c.is_ascii_digit()).count() >= 7
+}
+
+fn unmask(phone: String) -> Result<String, String> {
+    let known = MASKED_PHONES.lock().map_err(|e| e.to_string())?;
+    match known.as_ref().and_then(|known| known.get(&phone)) {
+        Some(Some(real)) => Ok(real.clone()),
+        Some(None) => Err(format!("Phone {} is masked and matches several numbers; reveal it first", phone)),
+        None => Ok(phone),
+    }
+}
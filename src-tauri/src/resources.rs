@@ -0,0 +1,58 @@
+use std::path::Path;
+use sysinfo::{Disks, System};
+
+/// Estimates are padded by this much for filesystem overhead and whatever
+/// else writes meanwhile.
+const SAFETY_FACTOR: f64 = 1.2;
+/// Left free on top of every estimate, so the journal and settings can
+/// still be written once the task is done.
+const DISK_RESERVE_BYTES: u64 = 50 * 1024 * 1024;
+/// A CSV is held as text, as parsed rows and as students at once.
+pub const CSV_IMPORT_MEMORY_FACTOR: u64 = 4;
+
+/// Free bytes on the volume holding `path`, which needn't exist yet.
+/// `None` when no mounted disk contains it.
+pub fn available_space(path: &Path) -> Option<u64> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Fails with `InsufficientDiskSpace` before a task writes an estimated
+/// `bytes` next to `path`. Passes when the volume can't be told, so a
+/// failed lookup never blocks the work.
+pub fn ensure_disk_space(path: &Path, bytes: u64) -> Result<(), String> {
+    let Some(available) = available_space(path) else {
+        return Ok(());
+    };
+    let needed = padded(bytes) + DISK_RESERVE_BYTES;
+    if available < needed {
+        return Err(format!("InsufficientDiskSpace: needed {} bytes, {} available", needed, available));
+    }
+    Ok(())
+}
+
+/// Fails with `InsufficientMemory` before a task holds an estimated
+/// `bytes` in memory. Passes where the platform doesn't report memory.
+pub fn ensure_memory(bytes: u64) -> Result<(), String> {
+    let mut system = System::new();
+    system.refresh_memory();
+    let available = system.available_memory();
+    if available == 0 {
+        return Ok(());
+    }
+    let needed = padded(bytes);
+    if available < needed {
+        return Err(format!("InsufficientMemory: needed {} bytes, {} available", needed, available));
+    }
+    Ok(())
+}
+
+fn padded(bytes: u64) -> u64 {
+    (bytes as f64 * SAFETY_FACTOR).ceil() as u64
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::desktop::{self, WhatsAppVariant};
+use crate::input;
+use crate::privacy;
+use crate::whatsapp::{
+    self, DraftRetention, ErrorKind, HookCommand, HookSettings, RetentionSettings, RetryPolicy, StatusVerbosity, WarmupSettings,
+    DEFAULT_SUMMARY_TEMPLATE,
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+/// Settings as they were before the last import; removed at the next start.
+const ROLLBACK_FILE: &str = "settings.rollback.json";
+const SETTINGS_ARCHIVE_VERSION: u32 = 1;
+
+/// Fields that describe this machine rather than the library. An import
+/// leaves them alone unless asked to take them over.
+const DEVICE_FIELDS: [&str; 6] = [
+    "preferred_variant",
+    "demo_mode",
+    "hold_until_focused",
+    "auto_refocus_after_seconds",
+    "warmup",
+    "close_open_with_dialog",
+];
+
+/// Fields that never move between machines unprompted: hooks and the
+/// receipt generator run arbitrary programs and the supervisor's number
+/// is personal. Left out of exports, and of imports unless secrets are
+/// asked for.
+const SECRET_FIELDS: [&str; 3] = ["hooks", "receipt_generator", "supervisor_number"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Show phones as `98XXXXXX21` in everything returned to the webview.
+    pub mask_phone_numbers: bool,
+    /// Per-error-kind overrides of the built-in retry policies.
+    pub retry_policies: HashMap<ErrorKind, RetryPolicy>,
+    /// Country assumed for numbers entered without a country code.
+    pub default_country: String,
+    pub warmup: WarmupSettings,
+    /// Which app to send through when both WhatsApp and WhatsApp Business
+    /// are installed; `None` picks automatically.
+    pub preferred_variant: Option<WhatsAppVariant>,
+    /// Record key presses and chat opens instead of performing them.
+    pub demo_mode: bool,
+    /// Hold the next send of a run while another window has focus.
+    pub hold_until_focused: bool,
+    /// Bring WhatsApp back to the front after holding this long; `None`
+    /// just waits for the operator.
+    pub auto_refocus_after_seconds: Option<u64>,
+    /// Scripts run before a campaign, after each sent message and after
+    /// the campaign; off by default.
+    pub hooks: HookSettings,
+    /// Makes the receipts of campaigns with `lazy_receipts`: run with the
+    /// student's `receipt_spec` and an `output` path as JSON on stdin, it
+    /// writes the PDF there.
+    pub receipt_generator: Option<HookCommand>,
+    pub library_profile: Option<LibraryProfile>,
+    /// Appended to outgoing messages by the webview; empty for none.
+    pub message_footer: String,
+    /// Library's offset from UTC, used for `{today}` and friends; IST by
+    /// default.
+    pub utc_offset_minutes: i32,
+    /// Close Windows' "How do you want to open this?" picker when a send
+    /// trips over it, instead of leaving it up for the operator.
+    pub close_open_with_dialog: bool,
+    /// Campaign drafts older than this are discarded.
+    pub draft_retention_days: u64,
+    /// Only the most recently saved drafts up to this count are kept.
+    pub max_campaign_drafts: usize,
+    /// How long journals, traces and other generated files are kept; see
+    /// `run_maintenance_now`.
+    pub retention: RetentionSettings,
+    /// Countdown between finalizing a campaign and its first send, during
+    /// which it can still be aborted; 0 starts right away.
+    pub send_confirmation_delay_seconds: u64,
+    /// Gets a summary of campaigns sent with `notify_supervisor`.
+    #[serde(
+        serialize_with = "privacy::serialize_optional_phone",
+        deserialize_with = "privacy::deserialize_optional_phone"
+    )]
+    pub supervisor_number: Option<String>,
+    /// Rendered like a campaign message; see `CampaignSummary::render` for
+    /// the tokens. Write it in the supervisor's language.
+    pub supervisor_summary_template: String,
+    /// Debugging aid: save each campaign's inputs and decisions to
+    /// `traces/` for `replay_campaign_trace`.
+    pub record_campaign_trace: bool,
+    /// Renamed tokens, old name to new, that still resolve in templates
+    /// for one release; pre-flight warns when a campaign uses them.
+    pub token_aliases: HashMap<String, String>,
+    /// Phrasing of the `status_text` sent with progress and campaign events.
+    pub status_verbosity: StatusVerbosity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryProfile {
+    pub name: String,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub contact_phone: Option<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            mask_phone_numbers: false,
+            retry_policies: HashMap::new(),
+            default_country: "IN".to_string(),
+            warmup: WarmupSettings::default(),
+            preferred_variant: None,
+            demo_mode: false,
+            hold_until_focused: true,
+            auto_refocus_after_seconds: None,
+            hooks: HookSettings::default(),
+            receipt_generator: None,
+            library_profile: None,
+            message_footer: String::new(),
+            utc_offset_minutes: 330,
+            close_open_with_dialog: true,
+            draft_retention_days: 30,
+            max_campaign_drafts: 20,
+            retention: RetentionSettings::default(),
+            send_confirmation_delay_seconds: 10,
+            supervisor_number: None,
+            supervisor_summary_template: DEFAULT_SUMMARY_TEMPLATE.to_string(),
+            record_campaign_trace: false,
+            token_aliases: HashMap::new(),
+            status_verbosity: StatusVerbosity::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn draft_retention(&self) -> DraftRetention {
+        DraftRetention {
+            max_age_days: self.draft_retention_days,
+            max_count: self.max_campaign_drafts,
+        }
+    }
+}
+
+/// Settings as written by `export_settings`, for moving to a new PC.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsArchive {
+    pub version: u32,
+    pub exported_at: u64,
+    pub machine_id: String,
+    /// Whether `settings` carries the secret fields; never for archives
+    /// written now, which are unencrypted.
+    #[serde(default)]
+    pub includes_secrets: bool,
+    pub settings: Value,
+}
+
+/// One field an import would change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub field: String,
+    pub current: Value,
+    pub incoming: Value,
+    /// Left alone unless the import includes device fields.
+    pub device_scoped: bool,
+    /// Left alone unless the import includes secrets.
+    pub secret: bool,
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: AppSettings,
+}
+
+impl SettingsStore {
+    /// Loads settings from the app config dir, falling back to defaults when
+    /// the file is missing or unreadable.
+    pub fn load(config_dir: PathBuf) -> Self {
+        // An import is only undoable within the session that made it
+        let _ = fs::remove_file(config_dir.join(ROLLBACK_FILE));
+
+        let path = config_dir.join(SETTINGS_FILE);
+        let settings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        let store = Self { path, settings };
+        store.apply();
+        store
+    }
+
+    pub fn get(&self) -> &AppSettings {
+        &self.settings
+    }
+
+    pub fn update(&mut self, settings: AppSettings) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        // Write then rename so a crash mid-write never leaves a truncated file
+        let contents = privacy::unmasked(|| serde_json::to_string_pretty(&settings)).map_err(|e| e.to_string())?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, contents).map_err(|e| format!("Failed to save settings: {}", e))?;
+        fs::rename(&temp_path, &self.path).map_err(|e| format!("Failed to save settings: {}", e))?;
+
+        self.settings = settings;
+        self.apply();
+        Ok(())
+    }
+
+    /// Writes the settings to `path` without the secret fields. Archives
+    /// are plain JSON, so `include_secrets` is refused until they can be
+    /// encrypted.
+    pub fn export(&self, path: &Path, include_secrets: bool) -> Result<(), String> {
+        if include_secrets {
+            return Err("Exporting hooks, the receipt generator and the supervisor's number isn't supported: settings archives aren't encrypted".to_string());
+        }
+        let mut settings = privacy::unmasked(|| serde_json::to_value(&self.settings)).map_err(|e| e.to_string())?;
+        if let Value::Object(fields) = &mut settings {
+            for field in SECRET_FIELDS {
+                fields.remove(field);
+            }
+        }
+        let archive = SettingsArchive {
+            version: SETTINGS_ARCHIVE_VERSION,
+            exported_at: crate::whatsapp::now_millis(),
+            machine_id: crate::whatsapp::machine_id(),
+            includes_secrets: false,
+            settings,
+        };
+        let contents = serde_json::to_string_pretty(&archive).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| format!("Failed to export settings: {}", e))
+    }
+
+    /// Every field the archive at `path` would change.
+    pub fn preview_import(&self, path: &Path) -> Result<Vec<SettingChange>, String> {
+        self.changes_from(path).map(screened)
+    }
+
+    fn changes_from(&self, path: &Path) -> Result<Vec<SettingChange>, String> {
+        let incoming = read_archive(path)?;
+        let current = privacy::unmasked(|| serde_json::to_value(&self.settings)).map_err(|e| e.to_string())?;
+
+        let mut changes: Vec<SettingChange> = incoming
+            .into_iter()
+            .filter(|(field, value)| current.get(field).is_some_and(|current| current != value))
+            .map(|(field, incoming)| SettingChange {
+                current: current.get(&field).cloned().unwrap_or(Value::Null),
+                device_scoped: DEVICE_FIELDS.contains(&field.as_str()),
+                secret: SECRET_FIELDS.contains(&field.as_str()),
+                field,
+                incoming,
+            })
+            .collect();
+        changes.sort_by(|a, b| a.field.cmp(&b.field));
+        Ok(changes)
+    }
+
+    /// Applies the archive at `path` in one write, keeping the previous
+    /// settings for `rollback_import`. Device fields are only taken over
+    /// with `include_device_fields`, secret ones with `include_secrets`.
+    /// Returns what changed.
+    pub fn import(
+        &mut self,
+        path: &Path,
+        include_device_fields: bool,
+        include_secrets: bool,
+    ) -> Result<Vec<SettingChange>, String> {
+        let changes: Vec<SettingChange> = self
+            .changes_from(path)?
+            .into_iter()
+            .filter(|change| include_device_fields || !change.device_scoped)
+            .filter(|change| include_secrets || !change.secret)
+            .collect();
+
+        let mut merged = match privacy::unmasked(|| serde_json::to_value(&self.settings)).map_err(|e| e.to_string())? {
+            Value::Object(merged) => merged,
+            _ => return Err("Failed to merge settings".to_string()),
+        };
+        for change in &changes {
+            merged.insert(change.field.clone(), change.incoming.clone());
+        }
+        let settings: AppSettings = serde_json::from_value(Value::Object(merged))
+            .map_err(|e| format!("Settings archive has invalid values: {}", e))?;
+
+        let previous = privacy::unmasked(|| serde_json::to_string_pretty(&self.settings)).map_err(|e| e.to_string())?;
+        fs::write(self.rollback_path(), previous).map_err(|e| format!("Failed to save rollback copy: {}", e))?;
+        self.update(settings)?;
+        Ok(screened(changes))
+    }
+
+    /// Restores the settings from before the last import of this session.
+    pub fn rollback_import(&mut self) -> Result<(), String> {
+        let rollback_path = self.rollback_path();
+        let contents = fs::read_to_string(&rollback_path)
+            .map_err(|_| "No settings import to roll back".to_string())?;
+        let settings: AppSettings = serde_json::from_str(&contents)
+            .map_err(|e| format!("Corrupt settings rollback copy: {}", e))?;
+
+        self.update(settings)?;
+        fs::remove_file(rollback_path).map_err(|e| format!("Failed to remove rollback copy: {}", e))
+    }
+
+    fn rollback_path(&self) -> PathBuf {
+        self.path.with_file_name(ROLLBACK_FILE)
+    }
+
+    fn apply(&self) {
+        privacy::set_phone_masking(self.settings.mask_phone_numbers);
+        input::set_demo_mode(self.settings.demo_mode);
+        desktop::set_preferred_variant(self.settings.preferred_variant);
+        whatsapp::set_status_verbosity(self.settings.status_verbosity);
+    }
+}
+
+/// `changes` with the supervisor's number masked for the webview.
+fn screened(mut changes: Vec<SettingChange>) -> Vec<SettingChange> {
+    for change in changes.iter_mut().filter(|change| change.field == "supervisor_number") {
+        for value in [&mut change.current, &mut change.incoming] {
+            if let Value::String(phone) = value {
+                *phone = privacy::screen(phone);
+            }
+        }
+    }
+    changes
+}
+
+/// The settings object of an archive, after checking its version.
+fn read_archive(path: &Path) -> Result<serde_json::Map<String, Value>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read settings archive: {}", e))?;
+    let archive: SettingsArchive = serde_json::from_str(&contents)
+        .map_err(|e| format!("Not a settings archive: {}", e))?;
+    if archive.version > SETTINGS_ARCHIVE_VERSION {
+        return Err(format!(
+            "Settings archive version {} is newer than this app supports ({})",
+            archive.version, SETTINGS_ARCHIVE_VERSION
+        ));
+    }
+
+    match archive.settings {
+        Value::Object(settings) => Ok(settings),
+        _ => Err("Settings archive has no settings object".to_string()),
+    }
+}
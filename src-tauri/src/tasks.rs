@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter};
+
+use crate::whatsapp::now_millis;
+
+/// Finished tasks stay pollable this long, e.g. across a webview reload.
+const KEEP_FINISHED_MILLIS: u64 = 60 * 60 * 1000;
+/// Error a task returns when it stopped because `cancel_task` asked it to.
+pub const TASK_CANCELLED: &str = "TaskCancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of `task-progress`, the same for every kind of task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskProgress {
+    pub task_id: String,
+    pub kind: String,
+    pub state: TaskState,
+    pub stage: String,
+    pub percent: u8,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatus {
+    #[serde(flatten)]
+    pub progress: TaskProgress,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    /// What the command would have returned, once completed.
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+struct Task {
+    status: Mutex<TaskStatus>,
+    cancel_requested: AtomicBool,
+}
+
+/// Given to the work of a task to report progress and notice cancellation.
+pub struct TaskHandle {
+    task: Arc<Task>,
+    app: AppHandle,
+}
+
+impl TaskHandle {
+    pub fn progress(&self, stage: &str, percent: u8, detail: Option<String>) {
+        let progress = match self.task.status.lock() {
+            Ok(mut status) => {
+                status.progress.stage = stage.to_string();
+                status.progress.percent = percent.min(100);
+                status.progress.detail = detail;
+                status.progress.clone()
+            }
+            Err(_) => return,
+        };
+        let _ = self.app.emit("task-progress", &progress);
+    }
+
+    /// Errors with `TASK_CANCELLED` once cancellation was requested; the
+    /// work cleans up its partial output and returns the error.
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.task.cancel_requested.load(Ordering::SeqCst) {
+            return Err(TASK_CANCELLED.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Heavy commands run as tasks: they return a task id at once and report
+/// through `task-progress`, `get_task_status` and `cancel_task`.
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<HashMap<String, Arc<Task>>>,
+}
+
+impl TaskRegistry {
+    /// Runs `work` on its own thread and returns the task id.
+    pub fn start<T, F>(&self, app: &AppHandle, kind: &str, work: F) -> Result<String, String>
+    where
+        T: Serialize,
+        F: FnOnce(&TaskHandle) -> Result<T, String> + Send + 'static,
+    {
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let task = Arc::new(Task {
+            status: Mutex::new(TaskStatus {
+                progress: TaskProgress {
+                    task_id: task_id.clone(),
+                    kind: kind.to_string(),
+                    state: TaskState::Running,
+                    stage: "starting".to_string(),
+                    percent: 0,
+                    detail: None,
+                },
+                started_at: now_millis(),
+                finished_at: None,
+                result: None,
+                error: None,
+            }),
+            cancel_requested: AtomicBool::new(false),
+        });
+
+        {
+            let mut tasks = self.tasks.lock().map_err(|e| e.to_string())?;
+            let now = now_millis();
+            tasks.retain(|_, task| {
+                task.status.lock().is_ok_and(|status| {
+                    status.finished_at.is_none_or(|finished| now - finished < KEEP_FINISHED_MILLIS)
+                })
+            });
+            tasks.insert(task_id.clone(), task.clone());
+        }
+
+        let handle = TaskHandle { task, app: app.clone() };
+        thread::spawn(move || {
+            let outcome = work(&handle).and_then(|result| serde_json::to_value(result).map_err(|e| e.to_string()));
+            let progress = match handle.task.status.lock() {
+                Ok(mut status) => {
+                    status.finished_at = Some(now_millis());
+                    match outcome {
+                        Ok(result) => {
+                            status.progress.state = TaskState::Completed;
+                            status.progress.percent = 100;
+                            status.result = Some(result);
+                        }
+                        Err(error) if error == TASK_CANCELLED => status.progress.state = TaskState::Cancelled,
+                        Err(error) => {
+                            status.progress.state = TaskState::Failed;
+                            status.error = Some(error);
+                        }
+                    }
+                    status.progress.clone()
+                }
+                Err(_) => return,
+            };
+            let _ = handle.app.emit("task-progress", &progress);
+        });
+
+        Ok(task_id)
+    }
+
+    /// Asks the task to stop at its next check; it ends as `cancelled`.
+    pub fn cancel(&self, task_id: &str) -> Result<(), String> {
+        let task = self.task(task_id)?;
+        let running = task.status.lock().map_err(|e| e.to_string())?.progress.state == TaskState::Running;
+        if !running {
+            return Err(format!("Task {} has already finished", task_id));
+        }
+        task.cancel_requested.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn status(&self, task_id: &str) -> Result<TaskStatus, String> {
+        let task = self.task(task_id)?;
+        let status = task.status.lock().map_err(|e| e.to_string())?;
+        Ok(status.clone())
+    }
+
+    fn task(&self, task_id: &str) -> Result<Arc<Task>, String> {
+        self.tasks
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(task_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown task {}", task_id))
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+/// WhatsApp's document size limit.
+pub const MAX_ATTACHMENT_BYTES: u64 = 100 * 1024 * 1024;
+/// Wait for WhatsApp to show a pasted file's preview before captioning it.
+pub const PREVIEW_DELAY: Duration = Duration::from_secs(2);
+/// Wait after sending a file so its upload starts before the next paste.
+pub const UPLOAD_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub path: String,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+/// Pre-flight check of the campaign-wide attachments, done once per run
+/// rather than once per student.
+pub fn validate(attachments: &[Attachment]) -> Result<(), String> {
+    for attachment in attachments {
+        let metadata = fs::metadata(&attachment.path)
+            .map_err(|e| format!("Attachment {} is not readable: {}", attachment.path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("Attachment {} is not a file", attachment.path));
+        }
+        if metadata.len() > MAX_ATTACHMENT_BYTES {
+            return Err(format!("Attachment {} is larger than 100 MB", attachment.path));
+        }
+    }
+    Ok(())
+}
+
+/// Time `count` attachments add to a message.
+pub fn send_time(count: usize) -> Duration {
+    (PREVIEW_DELAY + UPLOAD_DELAY) * count as u32
+}
+
+/// What goes out after a student's text: common attachments first, then
+/// the student's own receipt.
+pub fn for_student(common: &[Attachment], receipt_path: Option<&String>, attach_receipt: bool) -> Vec<Attachment> {
+    let mut attachments = common.to_vec();
+    if attach_receipt {
+        if let Some(path) = receipt_path {
+            attachments.push(Attachment {
+                path: path.clone(),
+                caption: None,
+            });
+        }
+    }
+    attachments
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::errors::{ErrorKind, Remediation};
+use super::pause::PauseReason;
+use super::status_text;
+
+/// Don't judge the failure rate on the first handful of messages.
+const MIN_MESSAGES_FOR_FAILURE_RATE: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoPauseNotice {
+    pub reason: String,
+    pub processed: usize,
+    pub total: usize,
+    pub failed: usize,
+    pub consecutive_failures: u32,
+    pub failure_rate: f32,
+    pub dominant_error_kind: Option<ErrorKind>,
+    pub remediation: Option<Remediation>,
+    pub status_text: String,
+}
+
+#[derive(Default)]
+pub struct FailureStats {
+    processed: usize,
+    failed: usize,
+    /// Messages since the run last resumed from an auto-pause; the failure
+    /// rate is judged on these so the failures that caused the pause
+    /// don't trip it again straight away.
+    window_processed: usize,
+    window_failed: usize,
+    consecutive: u32,
+    errors: HashMap<ErrorKind, usize>,
+    /// Kind of the latest failure, cleared by a success.
+    last_error: Option<ErrorKind>,
+}
+
+impl FailureStats {
+    pub fn record_success(&mut self) {
+        self.processed += 1;
+        self.window_processed += 1;
+        self.consecutive = 0;
+        self.last_error = None;
+    }
+
+    pub fn record_failure(&mut self, kind: ErrorKind) {
+        self.processed += 1;
+        self.failed += 1;
+        self.window_processed += 1;
+        self.window_failed += 1;
+        self.consecutive += 1;
+        *self.errors.entry(kind).or_insert(0) += 1;
+        self.last_error = Some(kind);
+    }
+
+    /// Starts judging afresh once the operator resumes an auto-paused run.
+    pub fn resumed(&mut self) {
+        self.window_processed = 0;
+        self.window_failed = 0;
+        self.consecutive = 0;
+        self.last_error = None;
+    }
+
+    /// Failure rate since the last resume.
+    pub fn failure_rate(&self) -> f32 {
+        if self.window_processed == 0 {
+            0.0
+        } else {
+            self.window_failed as f32 / self.window_processed as f32
+        }
+    }
+
+    /// The error kind seen most often so far, so the operator knows what to fix.
+    pub fn dominant_error_kind(&self) -> Option<ErrorKind> {
+        self.errors
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Returns why the run should pause if one of the limits is breached.
+    pub fn breached(&self, max_failure_rate: Option<f32>, max_consecutive: Option<u32>) -> Option<String> {
+        if self.last_error.is_some_and(ErrorKind::pauses_campaign) {
+            return Some("WhatsApp link opened an app picker instead of WhatsApp".to_string());
+        }
+
+        if let Some(limit) = max_consecutive {
+            if self.consecutive >= limit {
+                return Some(format!("{} consecutive failures", self.consecutive));
+            }
+        }
+
+        if let Some(limit) = max_failure_rate {
+            if self.window_processed >= MIN_MESSAGES_FOR_FAILURE_RATE && self.failure_rate() >= limit {
+                return Some(format!("Failure rate {:.0}% reached", self.failure_rate() * 100.0));
+            }
+        }
+
+        None
+    }
+
+    pub fn pause_reason(&self) -> PauseReason {
+        PauseReason::for_failure(self.last_error)
+    }
+
+    pub fn notice(&self, reason: String, total: usize) -> AutoPauseNotice {
+        AutoPauseNotice {
+            reason,
+            processed: self.processed,
+            total,
+            failed: self.failed,
+            consecutive_failures: self.consecutive,
+            failure_rate: self.failure_rate(),
+            dominant_error_kind: self.dominant_error_kind(),
+            remediation: self.last_error.or(self.dominant_error_kind()).and_then(Remediation::for_kind),
+            status_text: status_text::auto_paused(self.processed, total, self.failed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_FAILURE_RATE: Option<f32> = Some(0.5);
+    const MAX_CONSECUTIVE: Option<u32> = Some(3);
+
+    fn breached(stats: &FailureStats) -> Option<String> {
+        stats.breached(MAX_FAILURE_RATE, MAX_CONSECUTIVE)
+    }
+
+    #[test]
+    fn failure_rate_waits_for_enough_messages() {
+        let mut stats = FailureStats::default();
+        stats.record_failure(ErrorKind::Timeout);
+        stats.record_success();
+        stats.record_failure(ErrorKind::Timeout);
+        stats.record_success();
+        assert_eq!(breached(&stats), None);
+
+        stats.record_failure(ErrorKind::Timeout);
+        assert_eq!(breached(&stats).as_deref(), Some("Failure rate 60% reached"));
+    }
+
+    #[test]
+    fn consecutive_failures_breach() {
+        let mut stats = FailureStats::default();
+        for _ in 0..3 {
+            stats.record_failure(ErrorKind::SessionDisconnected);
+        }
+        assert_eq!(breached(&stats).as_deref(), Some("3 consecutive failures"));
+        assert_eq!(stats.pause_reason(), PauseReason::Disconnected);
+    }
+
+    #[test]
+    fn resume_does_not_breach_again_straight_away() {
+        let mut stats = FailureStats::default();
+        for _ in 0..4 {
+            stats.record_success();
+        }
+        for _ in 0..6 {
+            stats.record_failure(ErrorKind::Timeout);
+        }
+        assert!(breached(&stats).is_some());
+
+        stats.resumed();
+        assert_eq!(breached(&stats), None);
+
+        // The next message after the fix goes through
+        stats.record_success();
+        assert_eq!(breached(&stats), None);
+        // and a lone failure is judged on its own window, not the old one
+        stats.record_failure(ErrorKind::Timeout);
+        assert_eq!(breached(&stats), None);
+    }
+
+    #[test]
+    fn failures_after_resume_breach_again() {
+        let mut stats = FailureStats::default();
+        for _ in 0..5 {
+            stats.record_failure(ErrorKind::Timeout);
+        }
+        stats.resumed();
+        for _ in 0..3 {
+            stats.record_success();
+            stats.record_failure(ErrorKind::Timeout);
+        }
+        assert_eq!(breached(&stats).as_deref(), Some("Failure rate 50% reached"));
+    }
+
+    #[test]
+    fn notice_keeps_run_totals_after_resume() {
+        let mut stats = FailureStats::default();
+        for _ in 0..5 {
+            stats.record_failure(ErrorKind::Timeout);
+        }
+        stats.resumed();
+        stats.record_success();
+
+        let notice = stats.notice("test".to_string(), 10);
+        assert_eq!(notice.processed, 6);
+        assert_eq!(notice.failed, 5);
+        assert_eq!(notice.consecutive_failures, 0);
+        assert_eq!(notice.dominant_error_kind, Some(ErrorKind::Timeout));
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+use sysinfo::System;
+
+use super::campaign::now_millis;
+use super::deeplink::build_send_url;
+use super::MessageProgress;
+use crate::desktop;
+use crate::input::{InputSimulator, Key, RecordingInput};
+
+const BENCHMARKS_FILE: &str = "benchmarks.json";
+pub const MAX_BENCHMARK_SAMPLES: usize = 10_000;
+
+const SAMPLE_TEMPLATE: &str =
+    "Dear {name}, your library fee of Rs. {amount} for {month} is due on {due_date}. Seat {seat}, {shift} shift. Thank you!";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Render,
+    BuildUrl,
+    Input,
+    Log,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: PipelineStage,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignProjection {
+    pub campaign_size: usize,
+    pub interval_seconds: u64,
+    pub estimated_seconds_p50: f64,
+    pub estimated_seconds_p95: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub machine_id: String,
+    pub measured_at: u64,
+    pub sample_size: usize,
+    pub stages: Vec<StageTiming>,
+    /// Overhead of one message with every stage, excluding the interval.
+    pub per_message_p50_ms: f64,
+    pub per_message_p95_ms: f64,
+    pub projection: Option<CampaignProjection>,
+}
+
+impl BenchmarkResult {
+    /// Expected wall time of a run on this machine: per-message overhead
+    /// for every recipient plus the interval between them.
+    pub fn project(&self, campaign_size: usize, interval_seconds: u64) -> CampaignProjection {
+        let waits = campaign_size.saturating_sub(1) as f64 * interval_seconds as f64;
+        CampaignProjection {
+            campaign_size,
+            interval_seconds,
+            estimated_seconds_p50: campaign_size as f64 * self.per_message_p50_ms / 1000.0 + waits,
+            estimated_seconds_p95: campaign_size as f64 * self.per_message_p95_ms / 1000.0 + waits,
+        }
+    }
+}
+
+/// Pushes `sample_size` synthetic messages through the send pipeline with
+/// the recording input backend, so nothing reaches WhatsApp.
+pub fn run_benchmark(sample_size: usize) -> Result<BenchmarkResult, String> {
+    if sample_size == 0 || sample_size > MAX_BENCHMARK_SAMPLES {
+        return Err(format!("Sample size must be between 1 and {}", MAX_BENCHMARK_SAMPLES));
+    }
+
+    let input = RecordingInput::new();
+    let variant = desktop::active_variant();
+    let mut render = Vec::with_capacity(sample_size);
+    let mut build_url = Vec::with_capacity(sample_size);
+    let mut send = Vec::with_capacity(sample_size);
+    let mut log = Vec::with_capacity(sample_size);
+    let mut total = Vec::with_capacity(sample_size);
+
+    for index in 0..sample_size {
+        let tokens = sample_tokens(index);
+        let phone = format!("9198765{:05}", index % 100_000);
+
+        let started = Instant::now();
+        let mut message = SAMPLE_TEMPLATE.to_string();
+        for (token, value) in &tokens {
+            message = message.replace(&format!("{{{}}}", token), value);
+        }
+        let rendered = Instant::now();
+
+        let url = build_send_url(variant, &phone, &message)?.url;
+        let url_built = Instant::now();
+
+        input.open_url(&url)?;
+        input.press_key(Key::Enter)?;
+        let sent = Instant::now();
+
+        let progress = MessageProgress {
+            student_id: format!("benchmark-{}", index),
+            name: tokens["name"].clone(),
+            phone,
+            status: "sent".to_string(),
+            status_text: super::status_text::message_progress(&tokens["name"], "sent", None, index + 1, sample_size),
+            error: None,
+            error_kind: None,
+            remediation: None,
+            attempt: 1,
+            processed: index + 1,
+            total: sample_size,
+            campaign_id: "benchmark".to_string(),
+            demo_mode: true,
+            rendered_message: Some(message),
+        };
+        serde_json::to_string(&progress).map_err(|e| e.to_string())?;
+        let logged = Instant::now();
+
+        render.push(millis_between(started, rendered));
+        build_url.push(millis_between(rendered, url_built));
+        send.push(millis_between(url_built, sent));
+        log.push(millis_between(sent, logged));
+        total.push(millis_between(started, logged));
+    }
+
+    let stages = [
+        (PipelineStage::Render, render),
+        (PipelineStage::BuildUrl, build_url),
+        (PipelineStage::Input, send),
+        (PipelineStage::Log, log),
+    ]
+    .into_iter()
+    .map(|(stage, mut samples)| StageTiming {
+        stage,
+        p50_ms: percentile(&mut samples, 0.50),
+        p95_ms: percentile(&mut samples, 0.95),
+    })
+    .collect();
+
+    Ok(BenchmarkResult {
+        machine_id: machine_id(),
+        measured_at: now_millis(),
+        sample_size,
+        stages,
+        per_message_p50_ms: percentile(&mut total, 0.50),
+        per_message_p95_ms: percentile(&mut total, 0.95),
+        projection: None,
+    })
+}
+
+fn sample_tokens(index: usize) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("name", format!("Student {}", index + 1)),
+        ("amount", (1500 + (index % 10) * 100).to_string()),
+        ("month", "March 2025".to_string()),
+        ("due_date", "05/03/2025".to_string()),
+        ("seat", (index % 120 + 1).to_string()),
+        ("shift", "Morning".to_string()),
+    ])
+}
+
+fn millis_between(start: Instant, end: Instant) -> f64 {
+    end.duration_since(start).as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile.
+fn percentile(samples: &mut [f64], p: f64) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let rank = (p * samples.len() as f64).ceil() as usize;
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+/// Results are kept per machine so a settings file synced between PCs
+/// doesn't carry another machine's timings.
+pub fn machine_id() -> String {
+    System::host_name().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Latest benchmark per machine, in the app data dir.
+pub struct BenchmarkStore {
+    path: PathBuf,
+}
+
+impl BenchmarkStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            path: data_dir.join(BENCHMARKS_FILE),
+        }
+    }
+
+    fn load_all(&self) -> HashMap<String, BenchmarkResult> {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn latest(&self, machine_id: &str) -> Option<BenchmarkResult> {
+        self.load_all().remove(machine_id)
+    }
+
+    pub fn save(&self, result: &BenchmarkResult) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let mut results = self.load_all();
+        results.insert(result.machine_id.clone(), result.clone());
+        let contents = serde_json::to_string_pretty(&results).map_err(|e| e.to_string())?;
+        fs::write(&self.path, contents).map_err(|e| format!("Failed to save benchmark: {}", e))
+    }
+}
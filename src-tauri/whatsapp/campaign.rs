@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::completion::CompletionActionRun;
+use super::hooks::HookRun;
+use super::retry::RetryJournalEntry;
+use super::exclusions::Exclusions;
+use super::pause::PauseReason;
+use super::summary::BulkSendSummary;
+use super::{CampaignOptions, StudentMessage};
+
+/// Where the recipients of a campaign came from, when not the student list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CampaignSource {
+    CsvImport { file_name: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    /// Students are still being appended.
+    Building,
+    /// Finalized, in the confirmation countdown before sending.
+    PendingStart,
+    Sending,
+    /// Records written before streamed campaigns existed are all finished runs.
+    #[default]
+    Finished,
+    /// Stopped by `cancel_bulk_send` before every message was sent.
+    Cancelled,
+}
+
+/// One line of `<id>.progress.jsonl`, written after each message so a run
+/// cut short by a crash resumes without sending to anyone twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentProgress {
+    pub student_id: String,
+    pub status: String,
+}
+
+/// A message sent again by hand after the campaign ended. Attempts carry on
+/// from the run's, and a failed one is journaled like the run's were.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResendAttempt {
+    /// Every student the message covers; the first carries the phone.
+    pub student_ids: Vec<String>,
+    pub attempt: u32,
+    /// `sent` or `failed`.
+    pub status: String,
+    pub error: Option<String>,
+    pub at: u64,
+}
+
+/// A campaign left sending by a crash or reboot, for `list_incomplete_jobs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncompleteCampaign {
+    pub campaign_id: String,
+    pub name: Option<String>,
+    pub started_at: u64,
+    pub total: usize,
+    /// Students already sent to, who are skipped on resume.
+    pub sent: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedMessage {
+    pub student_ids: Vec<String>,
+}
+
+/// What is kept on disk about a bulk run, one JSON file per campaign. The
+/// recipients live next to it in `<id>.students.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignRecord {
+    pub campaign_id: String,
+    #[serde(default)]
+    pub status: CampaignStatus,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub source: Option<CampaignSource>,
+    #[serde(default)]
+    pub options: Option<CampaignOptions>,
+    /// Creation time until the campaign starts sending.
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub total: usize,
+    pub retry_journal: Vec<RetryJournalEntry>,
+    #[serde(default)]
+    pub hook_runs: Vec<HookRun>,
+    /// Messages that covered several students sharing one phone.
+    #[serde(default)]
+    pub merged_messages: Vec<MergedMessage>,
+    /// Run with demo mode on: nothing was actually sent.
+    #[serde(default)]
+    pub demo_mode: bool,
+    #[serde(default)]
+    pub draft_id: Option<String>,
+    /// When the confirmation countdown ran out; set once per campaign.
+    #[serde(default)]
+    pub start_confirmed_at: Option<u64>,
+    #[serde(default)]
+    pub exclusions: Exclusions,
+    /// Students left out by the exclusions, reported as skipped_excluded.
+    #[serde(default)]
+    pub excluded: Vec<String>,
+    /// What was pausing the run when the record was last saved.
+    #[serde(default)]
+    pub pause_reasons: Vec<PauseReason>,
+    #[serde(default)]
+    pub completion_runs: Vec<CompletionActionRun>,
+    #[serde(default)]
+    pub resends: Vec<ResendAttempt>,
+    /// Set once the campaign has finished or been cancelled.
+    #[serde(default)]
+    pub summary: Option<BulkSendSummary>,
+}
+
+impl CampaignRecord {
+    pub fn new(options: CampaignOptions) -> Self {
+        Self {
+            campaign_id: uuid::Uuid::new_v4().to_string(),
+            status: CampaignStatus::Building,
+            name: options.name.clone(),
+            label: options.label.clone(),
+            notes: options.notes.clone(),
+            source: options.source.clone(),
+            draft_id: options.draft_id.clone(),
+            start_confirmed_at: None,
+            exclusions: Exclusions::default(),
+            excluded: Vec::new(),
+            pause_reasons: Vec::new(),
+            options: Some(options),
+            started_at: now_millis(),
+            finished_at: None,
+            total: 0,
+            retry_journal: Vec::new(),
+            hook_runs: Vec::new(),
+            completion_runs: Vec::new(),
+            resends: Vec::new(),
+            summary: None,
+            merged_messages: Vec::new(),
+            demo_mode: false,
+        }
+    }
+
+    fn matches(&self, label: Option<&str>, search: Option<&str>) -> bool {
+        if let Some(label) = label {
+            if self.label.as_deref() != Some(label) {
+                return false;
+            }
+        }
+
+        match search.map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()) {
+            Some(search) => [&self.name, &self.notes]
+                .iter()
+                .any(|field| field.as_ref().is_some_and(|text| text.to_lowercase().contains(&search))),
+            None => true,
+        }
+    }
+}
+
+pub struct CampaignStore {
+    dir: PathBuf,
+}
+
+impl CampaignStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            dir: data_dir.join("campaigns"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save(&self, record: &CampaignRecord) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create campaign directory: {}", e))?;
+
+        // Write then rename so a crash mid-write never leaves a truncated record
+        let path = self.path_for(&record.campaign_id)?;
+        let temp_path = path.with_extension("json.tmp");
+        let contents = crate::privacy::unmasked(|| serde_json::to_string_pretty(record)).map_err(|e| e.to_string())?;
+        fs::write(&temp_path, contents).map_err(|e| format!("Failed to save campaign: {}", e))?;
+        fs::rename(&temp_path, &path).map_err(|e| format!("Failed to save campaign: {}", e))
+    }
+
+    pub fn load(&self, campaign_id: &str) -> Result<CampaignRecord, String> {
+        let contents = fs::read_to_string(self.path_for(campaign_id)?)
+            .map_err(|_| format!("Campaign {} not found", campaign_id))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Corrupt campaign record {}: {}", campaign_id, e))
+    }
+
+    /// All saved campaigns matching the filters, newest first.
+    pub fn list(&self, label: Option<&str>, search: Option<&str>) -> Result<Vec<CampaignRecord>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let mut records: Vec<CampaignRecord> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|contents| serde_json::from_str::<CampaignRecord>(&contents).ok())
+            .filter(|record| record.matches(label, search))
+            .collect();
+
+        records.sort_by_key(|record| Reverse(record.started_at));
+        Ok(records)
+    }
+
+    /// Appends recipients as JSON lines; an empty chunk just creates the file.
+    pub fn append_students<'a>(
+        &self,
+        campaign_id: &str,
+        students: impl IntoIterator<Item = &'a StudentMessage>,
+    ) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create campaign directory: {}", e))?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.students_path_for(campaign_id)?)
+            .map_err(|e| format!("Failed to open campaign students: {}", e))?;
+        let mut writer = BufWriter::new(file);
+
+        for student in students {
+            let line = crate::privacy::unmasked(|| serde_json::to_string(student)).map_err(|e| e.to_string())?;
+            writeln!(writer, "{}", line).map_err(|e| format!("Failed to save campaign students: {}", e))?;
+        }
+        writer.flush().map_err(|e| format!("Failed to save campaign students: {}", e))
+    }
+
+    /// Reads the recipients back lazily, in the order they were appended.
+    pub fn students(
+        &self,
+        campaign_id: &str,
+    ) -> Result<impl Iterator<Item = Result<StudentMessage, String>>, String> {
+        let file = fs::File::open(self.students_path_for(campaign_id)?)
+            .map_err(|_| format!("Students of campaign {} not found", campaign_id))?;
+
+        Ok(BufReader::new(file)
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+            .map(|line| {
+                let line = line.map_err(|e| format!("Failed to read campaign students: {}", e))?;
+                serde_json::from_str(&line).map_err(|e| format!("Corrupt campaign student: {}", e))
+            }))
+    }
+
+    pub fn append_progress(
+        &self,
+        campaign_id: &str,
+        entries: impl IntoIterator<Item = StudentProgress>,
+    ) -> Result<(), String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.progress_path_for(campaign_id)?)
+            .map_err(|e| format!("Failed to open campaign progress: {}", e))?;
+        let mut writer = BufWriter::new(file);
+
+        for entry in entries {
+            let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+            writeln!(writer, "{}", line).map_err(|e| format!("Failed to save campaign progress: {}", e))?;
+        }
+        writer.flush().map_err(|e| format!("Failed to save campaign progress: {}", e))
+    }
+
+    /// The latest status of each student so far; empty before the first
+    /// message. A line torn by a crash is ignored.
+    pub fn progress(&self, campaign_id: &str) -> Result<HashMap<String, String>, String> {
+        let Ok(file) = fs::File::open(self.progress_path_for(campaign_id)?) else {
+            return Ok(HashMap::new());
+        };
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<StudentProgress>(&line).ok())
+            .map(|entry| (entry.student_id, entry.status))
+            .collect())
+    }
+
+    /// Drops the progress of a campaign that has ended; the record keeps
+    /// the totals.
+    pub fn remove_progress(&self, campaign_id: &str) -> Result<(), String> {
+        match fs::remove_file(self.progress_path_for(campaign_id)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove campaign progress: {}", e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn path_for(&self, campaign_id: &str) -> Result<PathBuf, String> {
+        Self::validate_id(campaign_id)?;
+        Ok(self.dir.join(format!("{}.json", campaign_id)))
+    }
+
+    fn students_path_for(&self, campaign_id: &str) -> Result<PathBuf, String> {
+        Self::validate_id(campaign_id)?;
+        Ok(self.dir.join(format!("{}.students.jsonl", campaign_id)))
+    }
+
+    fn progress_path_for(&self, campaign_id: &str) -> Result<PathBuf, String> {
+        Self::validate_id(campaign_id)?;
+        Ok(self.dir.join(format!("{}.progress.jsonl", campaign_id)))
+    }
+
+    fn validate_id(campaign_id: &str) -> Result<(), String> {
+        if campaign_id.is_empty() || !campaign_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid campaign id: {}", campaign_id));
+        }
+        Ok(())
+    }
+}
+
+/// Milliseconds since the Unix epoch, matching the webview's `Date.now()`.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
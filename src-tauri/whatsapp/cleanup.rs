@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// How long one kind of generated file is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "keep", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    /// Files last written more than this many days ago are deleted.
+    Days { days: u64 },
+    /// Only the most recently written files up to this count are kept.
+    Files { count: usize },
+    Unlimited,
+}
+
+/// What the daily maintenance run keeps of each generated artifact.
+/// Drafts keep following `draft_retention_days` and `max_campaign_drafts`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Rotated event journals, which the message log is read from; the
+    /// live file is never deleted.
+    pub journals: RetentionPolicy,
+    pub traces: RetentionPolicy,
+    /// Template backups written by `rename_token`.
+    pub token_renames: RetentionPolicy,
+    /// A heartbeat left behind by a run that crashed.
+    pub heartbeat: RetentionPolicy,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            journals: RetentionPolicy::Days { days: 180 },
+            traces: RetentionPolicy::Days { days: 30 },
+            token_renames: RetentionPolicy::Files { count: 20 },
+            heartbeat: RetentionPolicy::Days { days: 1 },
+        }
+    }
+}
+
+impl RetentionSettings {
+    /// Campaigns that ended at or after this are still in the message log,
+    /// so their files are kept whatever their own policy says. Journals
+    /// not kept by age have no such bound and every campaign counts.
+    pub fn message_log_cutoff(&self, now: u64) -> u64 {
+        match self.journals {
+            RetentionPolicy::Days { days } => now.saturating_sub(days.saturating_mul(MILLIS_PER_DAY)),
+            RetentionPolicy::Files { .. } | RetentionPolicy::Unlimited => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Artifact {
+    Journal,
+    Trace,
+    TokenRename,
+    Heartbeat,
+    Draft,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedArtifact {
+    pub artifact: Artifact,
+    pub file: String,
+    pub bytes: u64,
+    /// The campaign a trace belonged to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<String>,
+}
+
+/// Payload of `maintenance-report`; also returned by `run_maintenance_now`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub ran_at: u64,
+    pub deleted: Vec<DeletedArtifact>,
+    pub reclaimed_bytes: u64,
+    /// Expired traces kept because their campaign is still in the message log.
+    pub kept_referenced: usize,
+    /// Files that couldn't be deleted; the run carries on past them.
+    pub errors: Vec<String>,
+}
+
+impl MaintenanceReport {
+    pub fn new(ran_at: u64) -> Self {
+        Self {
+            ran_at,
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&mut self, artifact: Artifact, file: String, bytes: u64) {
+        let campaign_id = (artifact == Artifact::Trace)
+            .then(|| file.strip_suffix(".json").map(str::to_string))
+            .flatten();
+        self.reclaimed_bytes += bytes;
+        self.deleted.push(DeletedArtifact { artifact, file, bytes, campaign_id });
+    }
+
+    /// Deletes `files`, noting each as `artifact`.
+    pub fn remove(&mut self, artifact: Artifact, files: Vec<StoredFile>) {
+        for file in files {
+            match fs::remove_file(&file.path) {
+                Ok(()) => self.record(artifact, file_name(&file.path), file.bytes),
+                Err(e) => self.errors.push(format!("Failed to delete {}: {}", file.path.display(), e)),
+            }
+        }
+    }
+}
+
+/// A generated file, as retention sees it.
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    pub path: PathBuf,
+    /// Epoch ms of the last write.
+    pub modified: u64,
+    pub bytes: u64,
+}
+
+impl StoredFile {
+    /// `None` when there is no file at `path`.
+    pub fn at(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok().filter(|metadata| metadata.is_file())?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |age| age.as_millis() as u64);
+        Some(Self {
+            path: path.to_path_buf(),
+            modified,
+            bytes: metadata.len(),
+        })
+    }
+}
+
+fn files_in(dir: &Path) -> Vec<StoredFile> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| StoredFile::at(&entry.path()))
+        .collect()
+}
+
+/// The `.json` files directly in `dir`.
+pub fn json_files(dir: &Path) -> Vec<StoredFile> {
+    let mut files = files_in(dir);
+    files.retain(|file| file.path.extension().is_some_and(|ext| ext == "json"));
+    files
+}
+
+/// The files `policy` no longer keeps at `now`.
+pub fn expired(policy: RetentionPolicy, mut files: Vec<StoredFile>, now: u64) -> Vec<StoredFile> {
+    match policy {
+        RetentionPolicy::Unlimited => Vec::new(),
+        RetentionPolicy::Days { days } => {
+            let cutoff = now.saturating_sub(days.saturating_mul(MILLIS_PER_DAY));
+            files.retain(|file| file.modified < cutoff);
+            files
+        }
+        RetentionPolicy::Files { count } => {
+            files.sort_by_key(|file| Reverse(file.modified));
+            files.split_off(count.min(files.len()))
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// What `get_storage_usage` reports a category of the app data for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    Campaigns,
+    Journal,
+    Traces,
+    TokenRenames,
+    Drafts,
+    Heartbeat,
+    ExclusionLists,
+}
+
+impl StorageCategory {
+    /// The artifact `run_maintenance_now` cleans this category as, if any;
+    /// campaigns and exclusion lists are only ever removed by the operator.
+    pub fn cleanup(self) -> Option<Artifact> {
+        match self {
+            StorageCategory::Journal => Some(Artifact::Journal),
+            StorageCategory::Traces => Some(Artifact::Trace),
+            StorageCategory::TokenRenames => Some(Artifact::TokenRename),
+            StorageCategory::Drafts => Some(Artifact::Draft),
+            StorageCategory::Heartbeat => Some(Artifact::Heartbeat),
+            StorageCategory::Campaigns | StorageCategory::ExclusionLists => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub bytes: u64,
+    pub files: usize,
+    /// Pass to `run_maintenance_now` to clean just this category.
+    pub cleanup: Option<Artifact>,
+}
+
+/// Returned by `get_storage_usage`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub categories: Vec<CategoryUsage>,
+    pub total_bytes: u64,
+    /// Free space on the volume of the app data; `None` when unknown.
+    pub available_bytes: Option<u64>,
+}
+
+impl StorageUsage {
+    /// Sizes each category from its file or the files directly in its
+    /// folder.
+    pub fn measure(categories: &[(StorageCategory, &Path)], available_bytes: Option<u64>) -> Self {
+        let categories: Vec<CategoryUsage> = categories
+            .iter()
+            .map(|&(category, path)| {
+                let files = match StoredFile::at(path) {
+                    Some(file) => vec![file],
+                    None => files_in(path),
+                };
+                CategoryUsage {
+                    category,
+                    bytes: files.iter().map(|file| file.bytes).sum(),
+                    files: files.len(),
+                    cleanup: category.cleanup(),
+                }
+            })
+            .collect();
+        Self {
+            total_bytes: categories.iter().map(|usage| usage.bytes).sum(),
+            categories,
+            available_bytes,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::campaign::now_millis;
+use super::errors::ErrorKind;
+
+/// Built-in step run by the backend once a campaign has ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CompletionActionKind {
+    /// Writes the students whose message failed to a CSV file.
+    ExportFailuresCsv { path: String },
+    /// Zips the receipts of the campaign's students.
+    ZipReceipts { path: String },
+    /// Shows `message` to the operator.
+    Notify { message: String },
+    /// Quits the app once the campaign and journal are saved, e.g. after
+    /// an overnight run.
+    Shutdown { if_no_failures: bool },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionAction {
+    #[serde(flatten)]
+    pub kind: CompletionActionKind,
+    /// Also run when the campaign is cancelled instead of finishing.
+    #[serde(default)]
+    pub run_on_cancel: bool,
+}
+
+/// How a campaign ended, for choosing which actions run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignEnd {
+    Finished,
+    Cancelled,
+}
+
+impl CompletionAction {
+    pub fn runs_after(&self, end: CampaignEnd) -> bool {
+        end == CampaignEnd::Finished || self.run_on_cancel
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionOutcome {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+/// One completion action, journaled on the campaign record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionActionRun {
+    #[serde(flatten)]
+    pub action: CompletionActionKind,
+    pub ran_at: u64,
+    pub outcome: CompletionOutcome,
+    pub detail: Option<String>,
+}
+
+impl CompletionActionRun {
+    pub fn new(action: &CompletionActionKind, result: Result<(CompletionOutcome, String), String>) -> Self {
+        let (outcome, detail) = match result {
+            Ok((outcome, detail)) => (outcome, detail),
+            Err(error) => (CompletionOutcome::Failed, error),
+        };
+        Self {
+            action: action.clone(),
+            ran_at: now_millis(),
+            outcome,
+            detail: Some(detail),
+        }
+    }
+}
+
+/// Payload of `whatsapp-completion-notice`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionNotice {
+    pub campaign_id: String,
+    pub message: String,
+}
+
+/// A student whose message failed, as exported by `ExportFailuresCsv`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedMessage {
+    pub student_id: String,
+    pub name: String,
+    #[serde(serialize_with = "crate::privacy::serialize_phone")]
+    pub phone: String,
+    pub error_kind: Option<ErrorKind>,
+    pub error: String,
+}
+
+pub fn export_failures_csv(path: &Path, failures: &[FailedMessage]) -> Result<String, String> {
+    let mut csv = String::from("student_id,name,phone,error_kind,error\n");
+    for failure in failures {
+        let kind = failure
+            .error_kind
+            .and_then(|kind| serde_json::to_value(kind).ok())
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let fields = [&failure.student_id, &failure.name, &failure.phone, &kind, &failure.error];
+        let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    fs::write(path, csv).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(format!("{} failed students written to {}", failures.len(), path.display()))
+}
+
+pub(super) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Copies the receipts that still exist into a staging folder and zips
+/// it with the system archiver.
+pub fn zip_receipts(path: &Path, receipts: &[(String, PathBuf)]) -> Result<String, String> {
+    let staging = std::env::temp_dir().join(format!("patch-receipts-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create staging folder: {}", e))?;
+    let result = stage_and_zip(&staging, path, receipts);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn stage_and_zip(staging: &Path, path: &Path, receipts: &[(String, PathBuf)]) -> Result<String, String> {
+    let mut staged = 0;
+    for (student_id, receipt) in receipts {
+        let Some(file_name) = receipt.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !receipt.is_file() {
+            continue;
+        }
+        let mut target = staging.join(file_name);
+        if target.exists() {
+            target = staging.join(format!("{}_{}", student_id, file_name));
+        }
+        fs::copy(receipt, &target).map_err(|e| format!("Failed to copy {}: {}", receipt.display(), e))?;
+        staged += 1;
+    }
+    if staged == 0 {
+        return Err("No receipts found to zip".to_string());
+    }
+
+    let _ = fs::remove_file(path);
+    #[cfg(target_os = "windows")]
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "Compress-Archive -Path '{}' -DestinationPath '{}' -Force",
+                staging.join("*").display().to_string().replace('\'', "''"),
+                path.display().to_string().replace('\'', "''"),
+            ),
+        ])
+        .output();
+    #[cfg(not(target_os = "windows"))]
+    let output = Command::new("zip").arg("-j").arg("-q").arg("-r").arg(path).arg(staging).output();
+
+    let output = output.map_err(|e| format!("Failed to run the zip tool: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to zip receipts: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(format!("{} receipts zipped to {}", staged, path.display()))
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentLevel {
+    #[default]
+    None,
+    Transactional,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignKind {
+    /// Fee reminders, receipts and other messages about the membership itself.
+    #[default]
+    Transactional,
+    Promotional,
+}
+
+impl CampaignKind {
+    /// Promotional messages need explicit consent to everything; a student
+    /// whose consent is unknown is treated as not having given it.
+    pub fn permits(self, consent: Option<ConsentLevel>) -> bool {
+        match self {
+            CampaignKind::Transactional => true,
+            CampaignKind::Promotional => consent == Some(ConsentLevel::All),
+        }
+    }
+}
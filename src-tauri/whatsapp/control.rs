@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration, Instant};
+
+use super::pause::PauseReason;
+
+/// State shared between a running bulk send and the commands that steer it.
+/// The run works on a clone of the manager, so these commands never wait
+/// for the manager lock.
+#[derive(Default)]
+pub struct BulkSendControl {
+    running: AtomicBool,
+    paused: AtomicBool,
+    /// Set when maintenance mode paused the run, so leaving it resumes
+    /// only what it paused.
+    paused_for_maintenance: AtomicBool,
+    /// In the confirmation countdown before a campaign starts.
+    pending: AtomicBool,
+    abort_requested: AtomicBool,
+    /// Set by `cancel_bulk_send`; the run stops before its next message.
+    cancel_requested: AtomicBool,
+    changed: Notify,
+    campaign_id: Mutex<Option<String>>,
+    /// Everything pausing or holding the run right now.
+    reasons: Mutex<BTreeSet<PauseReason>>,
+}
+
+impl BulkSendControl {
+    /// Marks a bulk send as running, or returns `None` if one already is.
+    pub fn try_start(&self) -> Option<RunGuard<'_>> {
+        self.running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| {
+                self.cancel_requested.store(false, Ordering::SeqCst);
+                RunGuard { control: self }
+            })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn set_campaign_id(&self, campaign_id: &str) {
+        if let Ok(mut current) = self.campaign_id.lock() {
+            *current = Some(campaign_id.to_string());
+        }
+    }
+
+    /// Id of the campaign being sent right now, if any.
+    pub fn campaign_id(&self) -> Option<String> {
+        self.campaign_id.lock().ok().and_then(|current| current.clone())
+    }
+
+    /// Pauses the run for `reason`; returns whether that added a reason.
+    pub fn pause_for(&self, reason: PauseReason) -> bool {
+        let added = self.hold(reason);
+        self.paused.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+        added
+    }
+
+    /// Resumes the run, clearing every reason it was paused for.
+    pub fn resume(&self) {
+        if let Ok(mut reasons) = self.reasons.lock() {
+            reasons.clear();
+        }
+        self.paused_for_maintenance.store(false, Ordering::SeqCst);
+        self.paused.store(false, Ordering::SeqCst);
+        self.changed.notify_waiters();
+    }
+
+    /// Notes a reason the run is holding without pausing it, e.g. while
+    /// waiting for focus; returns whether it was new.
+    pub fn hold(&self, reason: PauseReason) -> bool {
+        self.reasons.lock().is_ok_and(|mut reasons| reasons.insert(reason))
+    }
+
+    /// Returns whether `reason` was active.
+    pub fn release(&self, reason: PauseReason) -> bool {
+        self.reasons.lock().is_ok_and(|mut reasons| reasons.remove(&reason))
+    }
+
+    pub fn pause_reasons(&self) -> Vec<PauseReason> {
+        self.reasons
+            .lock()
+            .map(|reasons| reasons.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Holds the start for `delay`, returning `true` if
+    /// `abort_pending` was called in the meantime.
+    pub async fn wait_pending(&self, delay: Duration) -> bool {
+        self.abort_requested.store(false, Ordering::SeqCst);
+        self.pending.store(true, Ordering::SeqCst);
+        let deadline = Instant::now() + delay;
+
+        let aborted = loop {
+            let changed = self.changed.notified();
+            if self.abort_requested.load(Ordering::SeqCst) {
+                break true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || timeout(remaining, changed).await.is_err() {
+                break self.abort_requested.load(Ordering::SeqCst);
+            }
+        };
+
+        self.pending.store(false, Ordering::SeqCst);
+        aborted
+    }
+
+    /// Cancels a campaign still in its countdown; returns whether there was one.
+    pub fn abort_pending(&self) -> bool {
+        if !self.pending.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.abort_requested.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+        true
+    }
+
+    /// Pauses a running send for maintenance; returns whether that added
+    /// a reason. An already paused run only gains the reason, so leaving
+    /// maintenance doesn't resume what someone else paused.
+    pub fn pause_for_maintenance(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        if !self.is_paused() {
+            self.paused_for_maintenance.store(true, Ordering::SeqCst);
+        }
+        self.pause_for(PauseReason::Maintenance)
+    }
+
+    /// Drops the maintenance reason and resumes what
+    /// `pause_for_maintenance` paused, unless something else paused the run
+    /// meanwhile; returns whether the reasons changed.
+    pub fn resume_after_maintenance(&self) -> bool {
+        if !self.release(PauseReason::Maintenance) {
+            return false;
+        }
+        let only_self_clearing = self.pause_reasons().iter().all(|reason| reason.clears_itself());
+        if self.paused_for_maintenance.swap(false, Ordering::SeqCst) && only_self_clearing {
+            self.paused.store(false, Ordering::SeqCst);
+            self.changed.notify_waiters();
+        }
+        true
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Asks the running send to stop; returns whether one was running.
+    pub fn cancel(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.cancel_requested.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+        true
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_requested.load(Ordering::SeqCst)
+    }
+
+    /// Returns once resumed or cancelled.
+    pub async fn wait_while_paused(&self) {
+        loop {
+            // Register before checking the flag so a resume in between isn't missed
+            let changed = self.changed.notified();
+            if !self.is_paused() || self.is_cancelled() {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// The wait between messages, cut short by a pause or cancel.
+    pub async fn sleep_interruptibly(&self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        loop {
+            let changed = self.changed.notified();
+            if self.is_paused() || self.is_cancelled() {
+                return;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || timeout(remaining, changed).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Clears the running state when a bulk send ends, however it ends.
+pub struct RunGuard<'a> {
+    control: &'a BulkSendControl,
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut current) = self.control.campaign_id.lock() {
+            *current = None;
+        }
+        if let Ok(mut reasons) = self.control.reasons.lock() {
+            reasons.clear();
+        }
+        self.control.paused.store(false, Ordering::SeqCst);
+        self.control.paused_for_maintenance.store(false, Ordering::SeqCst);
+        self.control.pending.store(false, Ordering::SeqCst);
+        self.control.cancel_requested.store(false, Ordering::SeqCst);
+        self.control.running.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{CampaignSource, StudentMessage};
+use crate::phone::normalize_to_e164;
+use crate::tasks::TaskHandle;
+
+/// Rows between progress reports and cancellation checks.
+const PROGRESS_EVERY_ROWS: usize = 100;
+
+/// Which CSV headers hold the required fields. Every other column becomes a
+/// personalization token, named by `tokens` or, failing that, by its header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvColumnMapping {
+    pub name: String,
+    pub phone: String,
+    #[serde(default)]
+    pub student_id: Option<String>,
+    #[serde(default)]
+    pub tokens: HashMap<String, String>,
+    /// Header of an ISO due-date column, for `{days_overdue}`.
+    #[serde(default)]
+    pub due_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvRowIssue {
+    /// 1-based line in the file, counting the header.
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedCsvRow {
+    pub row: usize,
+    pub name: String,
+    #[serde(serialize_with = "crate::privacy::serialize_phone")]
+    pub phone: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsvCampaignImport {
+    pub students: Vec<StudentMessage>,
+    pub rejected: Vec<RejectedCsvRow>,
+    pub issues: Vec<CsvRowIssue>,
+    pub source: CampaignSource,
+}
+
+/// Builds campaign recipients from an ad-hoc CSV file entirely in memory;
+/// nothing is written to the student records, so cancelling leaves nothing
+/// behind.
+pub fn build_campaign_from_csv(
+    path: &Path,
+    mapping: &CsvColumnMapping,
+    default_country: &str,
+    task: &TaskHandle,
+) -> Result<CsvCampaignImport, String> {
+    task.progress("reading", 0, Some(path.display().to_string()));
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let rows = parse_csv(contents.trim_start_matches('\u{feff}'))?;
+    let row_count = rows.len().saturating_sub(1);
+    let mut rows = rows.into_iter();
+
+    let headers: Vec<String> = rows
+        .next()
+        .ok_or_else(|| "CSV file is empty".to_string())?
+        .into_iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    let column = |header: &str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(header.trim()))
+            .ok_or_else(|| format!("Column \"{}\" not found in CSV header", header))
+    };
+    let name_col = column(&mapping.name)?;
+    let phone_col = column(&mapping.phone)?;
+    let id_col = mapping.student_id.as_deref().map(column).transpose()?;
+    let due_date_col = mapping.due_date.as_deref().map(column).transpose()?;
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut import = CsvCampaignImport {
+        students: Vec::new(),
+        rejected: Vec::new(),
+        issues: Vec::new(),
+        source: CampaignSource::CsvImport { file_name },
+    };
+    let mut seen_phones: HashMap<String, usize> = HashMap::new();
+
+    for (offset, fields) in rows.enumerate() {
+        let row = offset + 2;
+        if offset % PROGRESS_EVERY_ROWS == 0 {
+            task.check_cancelled()?;
+            let percent = (offset * 100 / row_count.max(1)) as u8;
+            task.progress("parsing", percent, Some(format!("{} of {} rows", offset, row_count)));
+        }
+        if fields.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+
+        if fields.len() != headers.len() {
+            import.issues.push(CsvRowIssue {
+                row,
+                message: format!("Expected {} columns, found {}", headers.len(), fields.len()),
+            });
+        }
+
+        let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or("");
+        let name = field(name_col).to_string();
+        let raw_phone = field(phone_col).to_string();
+
+        let phone = match normalize_to_e164(&raw_phone, default_country) {
+            Some(phone) => phone,
+            None => {
+                import.rejected.push(RejectedCsvRow {
+                    row,
+                    name,
+                    reason: if raw_phone.is_empty() {
+                        "Missing phone number".to_string()
+                    } else {
+                        "Invalid phone number format".to_string()
+                    },
+                    phone: raw_phone,
+                });
+                continue;
+            }
+        };
+
+        if name.is_empty() {
+            import.issues.push(CsvRowIssue {
+                row,
+                message: "Missing name".to_string(),
+            });
+        }
+
+        if let Some(first_row) = seen_phones.insert(phone.clone(), row) {
+            import.issues.push(CsvRowIssue {
+                row,
+                message: format!("Same phone as row {}", first_row),
+            });
+        }
+
+        let mut personalization_tokens = HashMap::new();
+        personalization_tokens.insert("name".to_string(), name.clone());
+        for (index, header) in headers.iter().enumerate() {
+            if index == phone_col || index == name_col || Some(index) == id_col || header.is_empty() {
+                continue;
+            }
+            let token = mapping.tokens.get(header).cloned().unwrap_or_else(|| header.clone());
+            personalization_tokens.insert(token, field(index).to_string());
+        }
+
+        let due_date = due_date_col.map(field).filter(|date| !date.is_empty()).map(str::to_string);
+        let student_id = match id_col.map(field).filter(|id| !id.is_empty()) {
+            Some(id) => id.to_string(),
+            None => format!("csv-row-{}", row),
+        };
+
+        import.students.push(StudentMessage {
+            student_id,
+            name,
+            phone,
+            receipt_path: None,
+            receipt_spec: None,
+            personalization_tokens,
+            consent: None,
+            due_date,
+        });
+    }
+
+    Ok(import)
+}
+
+/// Minimal RFC 4180 parser: quoted fields, doubled quotes and line breaks
+/// inside quotes.
+fn parse_csv(input: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("CSV file ends inside a quoted field".to_string());
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use super::errors::{ErrorKind, SendError};
+use crate::desktop::{self, WhatsAppVariant};
+
+/// Share of the text made up of `%XX` escapes above which it is taken to
+/// be encoded already. Real messages with a stray `%` stay far below it.
+const ENCODED_ESCAPE_DENSITY: f32 = 0.15;
+const MIN_ENCODED_ESCAPES: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncodedMessage {
+    pub url: String,
+    /// The text after normalization, i.e. what the recipient will see.
+    pub text: String,
+    /// The input was percent-encoded already and has been decoded once.
+    pub was_encoded: bool,
+}
+
+fn is_hex_escape(bytes: &[u8]) -> bool {
+    bytes.len() >= 3 && bytes[0] == b'%' && bytes[1].is_ascii_hexdigit() && bytes[2].is_ascii_hexdigit()
+}
+
+/// Heuristic for text that went through `encodeURIComponent` (or similar)
+/// before reaching us; encoding it again is what garbles Hindi, emoji and ₹.
+pub fn looks_percent_encoded(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let escapes = (0..bytes.len()).filter(|&i| is_hex_escape(&bytes[i..])).count();
+    if escapes < MIN_ENCODED_ESCAPES {
+        return false;
+    }
+
+    // Encoded text never has raw whitespace left in it
+    let has_raw_whitespace = text.chars().any(char::is_whitespace);
+    let density = (escapes * 3) as f32 / bytes.len() as f32;
+    !has_raw_whitespace && density >= ENCODED_ESCAPE_DENSITY
+}
+
+/// Builds the `send` deeplink for `variant`, encoding the text exactly once.
+pub fn build_send_url(variant: WhatsAppVariant, phone: &str, text: &str) -> Result<EncodedMessage, String> {
+    let was_encoded = looks_percent_encoded(text);
+    let text = if was_encoded {
+        urlencoding::decode(text)
+            .map_err(|e| format!("Message looks URL-encoded but does not decode to valid text: {}", e))?
+            .into_owned()
+    } else {
+        text.to_string()
+    };
+
+    let url = format!("{}://send?phone={}&text={}", variant.scheme(), phone, urlencoding::encode(&text));
+    Ok(EncodedMessage { url, text, was_encoded })
+}
+
+/// Fails when Windows put up its app picker instead of opening WhatsApp,
+/// closing the picker first if `close` is set. Call after opening the
+/// link and before any key press.
+pub fn check_open_with_dialog(close: bool) -> Result<(), SendError> {
+    let Some(dialog) = desktop::find_open_with_dialog() else {
+        return Ok(());
+    };
+    if close {
+        desktop::close_open_with_dialog(dialog);
+    }
+    Err(SendError::new(
+        ErrorKind::ProtocolHandlerAmbiguous,
+        "Windows asked which app should open the WhatsApp link; nothing was sent",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHONE: &str = "919876543210";
+
+    /// Combined pairwise below, so every fragment is also seen next to
+    /// every other.
+    const FRAGMENTS: [&str; 10] = [
+        "Hello Asha",
+        "नमस्ते आशा",
+        "फीस ₹1,500 बाकी है",
+        "🎉📚👍🏽",
+        "Tom & Jerry",
+        "Room #12",
+        "1 + 1",
+        "100% done",
+        "Line one\nLine two\r\n",
+        "a=b?c/d",
+    ];
+
+    fn corpus() -> Vec<String> {
+        let mut corpus: Vec<String> = FRAGMENTS.iter().map(|f| f.to_string()).collect();
+        for a in FRAGMENTS {
+            for b in FRAGMENTS {
+                corpus.push(format!("{} {}", a, b));
+            }
+        }
+        corpus
+    }
+
+    fn text_param(url: &str) -> &str {
+        url.split_once("&text=").map(|(_, text)| text).expect("url has a text parameter")
+    }
+
+    #[test]
+    fn corpus_round_trips_through_the_url() {
+        for text in corpus() {
+            let encoded = build_send_url(WhatsAppVariant::Regular, PHONE, &text).unwrap();
+            assert!(!encoded.was_encoded, "plain text taken for encoded: {:?}", text);
+            assert_eq!(encoded.text, text);
+            assert_eq!(urlencoding::decode(text_param(&encoded.url)).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn reserved_characters_never_appear_raw() {
+        for text in corpus() {
+            let encoded = build_send_url(WhatsAppVariant::Regular, PHONE, &text).unwrap();
+            let param = text_param(&encoded.url);
+            for reserved in ['&', '#', '+', ' ', '\n', '\r', '?', '='] {
+                assert!(!param.contains(reserved), "{:?} left raw in {:?}", reserved, param);
+            }
+        }
+    }
+
+    #[test]
+    fn already_encoded_text_is_encoded_once() {
+        for text in corpus() {
+            let once = build_send_url(WhatsAppVariant::Regular, PHONE, &text).unwrap();
+            let pre_encoded = text_param(&once.url);
+            if !looks_percent_encoded(pre_encoded) {
+                continue;
+            }
+
+            let again = build_send_url(WhatsAppVariant::Regular, PHONE, pre_encoded).unwrap();
+            assert!(again.was_encoded);
+            assert_eq!(again.url, once.url, "encoded twice: {:?}", text);
+            assert_eq!(again.text, text);
+        }
+    }
+
+    #[test]
+    fn non_latin_text_is_recognized_once_encoded() {
+        for text in ["नमस्ते आशा", "फीस ₹1,500 बाकी है", "🎉📚👍🏽", "Tom & Jerry #12 + 100%"] {
+            let once = build_send_url(WhatsAppVariant::Regular, PHONE, text).unwrap();
+            assert!(looks_percent_encoded(text_param(&once.url)), "not detected: {:?}", text);
+        }
+    }
+
+    #[test]
+    fn stray_percent_signs_are_not_taken_for_encoding() {
+        for text in ["100% done", "50%off", "a%20b", "Discount: 10%, due 5%"] {
+            assert!(!looks_percent_encoded(text), "{:?}", text);
+        }
+    }
+}
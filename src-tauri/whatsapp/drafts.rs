@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::campaign::now_millis;
+use super::{CampaignOptions, StudentMessage};
+
+const MILLIS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// A campaign being put together in the webview, saved so an update or
+/// crash doesn't lose it. Everything a bulk send request has, plus
+/// whatever UI state the frontend wants back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CampaignDraft {
+    /// `None` for a draft that hasn't been saved yet.
+    #[serde(default)]
+    pub draft_id: Option<String>,
+    #[serde(default)]
+    pub saved_at: u64,
+    pub students: Vec<StudentMessage>,
+    #[serde(flatten)]
+    pub options: CampaignOptions,
+    #[serde(default)]
+    pub ui_state: Value,
+}
+
+/// Drafts as listed, without the student lists.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DraftSummary {
+    pub draft_id: String,
+    pub name: Option<String>,
+    pub saved_at: u64,
+    pub student_count: usize,
+}
+
+/// Limits on what is kept; older or surplus drafts are pruned on save and
+/// list.
+#[derive(Debug, Clone, Copy)]
+pub struct DraftRetention {
+    pub max_age_days: u64,
+    pub max_count: usize,
+}
+
+/// One JSON file per draft, in the app data dir.
+pub struct DraftStore {
+    dir: PathBuf,
+}
+
+impl DraftStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            dir: data_dir.join("drafts"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save(&self, mut draft: CampaignDraft, retention: DraftRetention) -> Result<CampaignDraft, String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create draft directory: {}", e))?;
+
+        draft.draft_id.get_or_insert_with(|| uuid::Uuid::new_v4().to_string());
+        draft.saved_at = now_millis();
+        self.write(&draft)?;
+
+        self.prune(retention)?;
+        Ok(draft)
+    }
+
+    /// Rewrites a saved draft as is, keeping its `saved_at`, e.g. when a
+    /// token is renamed in its template.
+    pub fn replace(&self, draft: &CampaignDraft) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create draft directory: {}", e))?;
+        self.write(draft)
+    }
+
+    fn write(&self, draft: &CampaignDraft) -> Result<(), String> {
+        let draft_id = draft.draft_id.as_deref().ok_or_else(|| "Draft has no id".to_string())?;
+        let path = self.path_for(draft_id)?;
+        let temp_path = path.with_extension("json.tmp");
+        let contents = crate::privacy::unmasked(|| serde_json::to_string(draft)).map_err(|e| e.to_string())?;
+        fs::write(&temp_path, contents).map_err(|e| format!("Failed to save draft: {}", e))?;
+        fs::rename(&temp_path, &path).map_err(|e| format!("Failed to save draft: {}", e))
+    }
+
+    pub fn load(&self, draft_id: &str) -> Result<CampaignDraft, String> {
+        let contents = fs::read_to_string(self.path_for(draft_id)?)
+            .map_err(|_| format!("Draft {} not found", draft_id))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Corrupt draft {}: {}", draft_id, e))
+    }
+
+    pub fn delete(&self, draft_id: &str) -> Result<(), String> {
+        let path = self.path_for(draft_id)?;
+        if !path.exists() {
+            return Err(format!("Draft {} not found", draft_id));
+        }
+        fs::remove_file(path).map_err(|e| format!("Failed to delete draft: {}", e))
+    }
+
+    /// Saved drafts, most recently saved first.
+    pub fn list(&self, retention: DraftRetention) -> Result<Vec<DraftSummary>, String> {
+        self.prune(retention)?;
+        Ok(self
+            .load_all()
+            .into_iter()
+            .map(|draft| DraftSummary {
+                draft_id: draft.draft_id.unwrap_or_default(),
+                name: draft.options.name,
+                saved_at: draft.saved_at,
+                student_count: draft.students.len(),
+            })
+            .collect())
+    }
+
+    /// Removes drafts past the age limit, then the oldest beyond the cap;
+    /// returns the file name and size of each one removed.
+    pub fn prune(&self, retention: DraftRetention) -> Result<Vec<(String, u64)>, String> {
+        let cutoff = now_millis().saturating_sub(retention.max_age_days.saturating_mul(MILLIS_PER_DAY));
+        let mut removed = Vec::new();
+        for (index, draft) in self.load_all().iter().enumerate() {
+            if index >= retention.max_count || draft.saved_at < cutoff {
+                if let Some(draft_id) = &draft.draft_id {
+                    let bytes = fs::metadata(self.path_for(draft_id)?).map_or(0, |metadata| metadata.len());
+                    self.delete(draft_id)?;
+                    removed.push((format!("{}.json", draft_id), bytes));
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Every saved draft, most recently saved first.
+    pub fn load_all(&self) -> Vec<CampaignDraft> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut drafts: Vec<CampaignDraft> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|contents| serde_json::from_str::<CampaignDraft>(&contents).ok())
+            .collect();
+
+        drafts.sort_by_key(|draft| Reverse(draft.saved_at));
+        drafts
+    }
+
+    fn path_for(&self, draft_id: &str) -> Result<PathBuf, String> {
+        if draft_id.is_empty() || !draft_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid draft id: {}", draft_id));
+        }
+        Ok(self.dir.join(format!("{}.json", draft_id)))
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use super::receipts;
+use super::{check_phone, CampaignOptions, StudentMessage};
+use crate::settings::AppSettings;
+
+/// `{token}` placeholders left in a rendered message, in order of
+/// appearance.
+pub fn unresolved_tokens(message: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut rest = message;
+    while let Some(open) = rest.find('{') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find('}') else {
+            break;
+        };
+        let token = &rest[..close];
+        if !token.is_empty()
+            && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !tokens.iter().any(|seen| seen == token)
+        {
+            tokens.push(token.to_string());
+        }
+    }
+    tokens
+}
+
+/// Why the real run would fail this message, found without sending it;
+/// empty when it would go out. Lazy receipts have their spec checked but
+/// aren't generated.
+pub fn problems(
+    message: &str,
+    students: &[StudentMessage],
+    options: &CampaignOptions,
+    settings: &AppSettings,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let unresolved = unresolved_tokens(message);
+    if !unresolved.is_empty() {
+        let names: Vec<String> = unresolved.iter().map(|token| format!("{{{}}}", token)).collect();
+        problems.push(format!("Unresolved tokens: {}", names.join(", ")));
+    }
+
+    // The first student of a shared phone carries the number and receipt
+    if let Some(student) = students.first() {
+        if let Err(error) = check_phone(&student.phone, &settings.default_country) {
+            problems.push(error.message);
+        }
+        if options.attach_receipt && options.lazy_receipts {
+            let generator = settings.receipt_generator.as_ref();
+            problems.extend(receipts::spec_problem(student.receipt_spec.as_ref(), generator));
+        } else if options.attach_receipt {
+            if let Some(path) = student.receipt_path.as_ref().filter(|path| !Path::new(path).is_file()) {
+                problems.push(format!("Receipt {} not found", path));
+            }
+        }
+    }
+    problems
+}
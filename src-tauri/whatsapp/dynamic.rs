@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use super::campaign::now_millis;
+use super::StudentMessage;
+
+/// Tokens resolved from the clock at the moment of sending rather than
+/// taken from the student's token map when the campaign was built.
+pub const DYNAMIC_TOKENS: [&str; 3] = ["today", "send_time", "days_overdue"];
+
+/// Whether `template` uses `token` as one of its dynamic tokens.
+pub fn is_used_in(template: &str, token: &str) -> bool {
+    DYNAMIC_TOKENS.contains(&token) && template.contains(&format!("{{{}}}", token))
+}
+
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// The send moment, in the library's local time.
+#[derive(Debug, Clone, Copy)]
+pub struct SendClock {
+    pub as_of: u64,
+    local_millis: i64,
+}
+
+impl SendClock {
+    pub fn now(utc_offset_minutes: i32) -> Self {
+        Self::at(now_millis(), utc_offset_minutes)
+    }
+
+    /// The clock as it read at `as_of`, e.g. to replay a recorded campaign.
+    pub fn at(as_of: u64, utc_offset_minutes: i32) -> Self {
+        Self {
+            as_of,
+            local_millis: as_of as i64 + utc_offset_minutes as i64 * 60 * 1000,
+        }
+    }
+
+    fn today(&self) -> i64 {
+        self.local_millis.div_euclid(MILLIS_PER_DAY)
+    }
+
+    /// Values of the dynamic tokens for `student` as of this moment.
+    /// `days_overdue` is left out when the student has no due date.
+    pub fn values_for(&self, student: &StudentMessage) -> HashMap<String, String> {
+        let today = self.today();
+        let (year, month, day) = civil_from_days(today);
+        let minute_of_day = self.local_millis.rem_euclid(MILLIS_PER_DAY) / 60_000;
+
+        let mut values = HashMap::new();
+        values.insert("today".to_string(), format!("{:02}/{:02}/{}", day, month, year));
+        values.insert(
+            "send_time".to_string(),
+            format!("{:02}:{:02}", minute_of_day / 60, minute_of_day % 60),
+        );
+        if let Some(due) = student.due_date.as_deref().and_then(parse_iso_date) {
+            values.insert("days_overdue".to_string(), (today - due).max(0).to_string());
+        }
+        values
+    }
+}
+
+/// Days since 1970-01-01 of a `YYYY-MM-DD` date; a longer ISO timestamp
+/// is cut to its date.
+fn parse_iso_date(date: &str) -> Option<i64> {
+    let mut parts = date.get(..10)?.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+// Proleptic Gregorian conversions after Howard Hinnant's date algorithms
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
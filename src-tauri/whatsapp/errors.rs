@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Broad cause of a failed send, used to pick a retry policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    InvalidPhone,
+    Timeout,
+    WrongWindowFocused,
+    SessionDisconnected,
+    /// A helper program the input backend needs isn't installed.
+    MissingTool,
+    /// The OS refused us input or window access.
+    PermissionDenied,
+    /// Windows asked which app should open the WhatsApp link.
+    ProtocolHandlerAmbiguous,
+    /// A lazily generated receipt couldn't be made.
+    AttachmentMissing,
+    Unknown,
+}
+
+/// What the operator can do about a failure; the frontend shows the
+/// matching fix-it hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Remediation {
+    InstallXdotool,
+    GrantAccessibility,
+    RepairProtocolHandler,
+    RePairSession,
+    FocusWhatsapp,
+    RestartWhatsapp,
+    CheckPhoneNumber,
+    CheckReceiptGenerator,
+}
+
+impl Remediation {
+    /// Hint for a failure of `kind` when nothing more specific is known.
+    pub fn for_kind(kind: ErrorKind) -> Option<Self> {
+        match kind {
+            ErrorKind::InvalidPhone => Some(Self::CheckPhoneNumber),
+            ErrorKind::Timeout => Some(Self::RestartWhatsapp),
+            ErrorKind::WrongWindowFocused => Some(Self::FocusWhatsapp),
+            ErrorKind::SessionDisconnected => Some(Self::RePairSession),
+            ErrorKind::MissingTool => Some(Self::InstallXdotool),
+            ErrorKind::PermissionDenied => Some(Self::GrantAccessibility),
+            ErrorKind::ProtocolHandlerAmbiguous => Some(Self::RepairProtocolHandler),
+            ErrorKind::AttachmentMissing => Some(Self::CheckReceiptGenerator),
+            ErrorKind::Unknown => None,
+        }
+    }
+
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Self::InstallXdotool => {
+                "Sending needs a key-press helper. Install xdotool (X11) or ydotool (Wayland) from your package manager and try again."
+            }
+            Self::GrantAccessibility => {
+                "The app isn't allowed to press keys for you. On macOS open System Settings > Privacy & Security > Accessibility and enable this app; elsewhere run it as the logged-in desktop user."
+            }
+            Self::RepairProtocolHandler => {
+                "WhatsApp links aren't opening in WhatsApp. Reinstall WhatsApp Desktop or set it as the default app for whatsapp:// links."
+            }
+            Self::RePairSession => {
+                "WhatsApp is logged out. Open WhatsApp Desktop, scan the QR code with your phone and resume the campaign."
+            }
+            Self::FocusWhatsapp => {
+                "Another window was in front of WhatsApp. Bring WhatsApp to the front and avoid using the computer while a campaign runs."
+            }
+            Self::RestartWhatsapp => {
+                "WhatsApp didn't respond in time. Close and reopen WhatsApp Desktop, then resume."
+            }
+            Self::CheckPhoneNumber => {
+                "The phone number isn't valid. Correct it in the student's record, including the country code."
+            }
+            Self::CheckReceiptGenerator => {
+                "The receipt couldn't be generated. Check the receipt generator in settings and the payment's receipt details, then re-send."
+            }
+        }
+    }
+}
+
+/// Substrings of raw failure text (lowercased) and what they mean. The
+/// first match wins, so narrower symptoms go before broader ones.
+const CLASSIFICATION_RULES: &[(&str, ErrorKind, Remediation)] = &[
+    ("invalid phone", ErrorKind::InvalidPhone, Remediation::CheckPhoneNumber),
+    ("xdotool", ErrorKind::MissingTool, Remediation::InstallXdotool),
+    ("ydotool", ErrorKind::MissingTool, Remediation::InstallXdotool),
+    // CGEvent sources can't be created without the Accessibility grant
+    ("event source", ErrorKind::PermissionDenied, Remediation::GrantAccessibility),
+    ("accessibility", ErrorKind::PermissionDenied, Remediation::GrantAccessibility),
+    ("permission denied", ErrorKind::PermissionDenied, Remediation::GrantAccessibility),
+    ("operation not permitted", ErrorKind::PermissionDenied, Remediation::GrantAccessibility),
+    ("access is denied", ErrorKind::PermissionDenied, Remediation::GrantAccessibility),
+    ("how do you want to open", ErrorKind::ProtocolHandlerAmbiguous, Remediation::RepairProtocolHandler),
+    ("no application", ErrorKind::Unknown, Remediation::RepairProtocolHandler),
+    ("failed to open whatsapp", ErrorKind::Unknown, Remediation::RepairProtocolHandler),
+    ("logged out", ErrorKind::SessionDisconnected, Remediation::RePairSession),
+    ("disconnected", ErrorKind::SessionDisconnected, Remediation::RePairSession),
+    ("foreground", ErrorKind::WrongWindowFocused, Remediation::FocusWhatsapp),
+    ("focus", ErrorKind::WrongWindowFocused, Remediation::FocusWhatsapp),
+    ("timed out", ErrorKind::Timeout, Remediation::RestartWhatsapp),
+    ("timeout", ErrorKind::Timeout, Remediation::RestartWhatsapp),
+];
+
+/// Maps a raw failure (process output, OS error, ...) to a kind and a hint.
+pub fn classify(raw: &str) -> (ErrorKind, Option<Remediation>) {
+    let raw = raw.to_lowercase();
+    CLASSIFICATION_RULES
+        .iter()
+        .find(|(symptom, _, _)| raw.contains(symptom))
+        .map_or((ErrorKind::Unknown, None), |&(_, kind, remediation)| (kind, Some(remediation)))
+}
+
+#[derive(Debug, Clone)]
+pub struct SendError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub remediation: Option<Remediation>,
+}
+
+impl ErrorKind {
+    /// Failures that every following send would hit too, so the run
+    /// pauses on the first one.
+    pub fn pauses_campaign(self) -> bool {
+        matches!(self, ErrorKind::ProtocolHandlerAmbiguous)
+    }
+}
+
+impl SendError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            remediation: Remediation::for_kind(kind),
+        }
+    }
+
+    /// Wraps a raw failure from the input or desktop layer.
+    pub fn classified(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let (kind, remediation) = classify(&message);
+        Self {
+            kind,
+            message,
+            remediation,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Raw failures as the input and desktop layers report them, with the
+    /// kind and hint the operator should see.
+    const CASES: &[(&str, ErrorKind, Option<Remediation>)] = &[
+        (
+            "Invalid phone number: 12345",
+            ErrorKind::InvalidPhone,
+            Some(Remediation::CheckPhoneNumber),
+        ),
+        (
+            "Failed to run xdotool: No such file or directory (os error 2)",
+            ErrorKind::MissingTool,
+            Some(Remediation::InstallXdotool),
+        ),
+        (
+            "Failed to run ydotool: No such file or directory (os error 2)",
+            ErrorKind::MissingTool,
+            Some(Remediation::InstallXdotool),
+        ),
+        (
+            "Failed to create CGEvent source",
+            ErrorKind::PermissionDenied,
+            Some(Remediation::GrantAccessibility),
+        ),
+        (
+            "osascript is not allowed assistive access (Accessibility)",
+            ErrorKind::PermissionDenied,
+            Some(Remediation::GrantAccessibility),
+        ),
+        (
+            "Failed to open /dev/uinput: Permission denied (os error 13)",
+            ErrorKind::PermissionDenied,
+            Some(Remediation::GrantAccessibility),
+        ),
+        (
+            "Failed to send key press: Operation not permitted (os error 1)",
+            ErrorKind::PermissionDenied,
+            Some(Remediation::GrantAccessibility),
+        ),
+        (
+            "Failed to send key press: Access is denied. (os error 5)",
+            ErrorKind::PermissionDenied,
+            Some(Remediation::GrantAccessibility),
+        ),
+        (
+            "Window 'How do you want to open this?' is in front",
+            ErrorKind::ProtocolHandlerAmbiguous,
+            Some(Remediation::RepairProtocolHandler),
+        ),
+        (
+            "No application knows how to open URL whatsapp://send",
+            ErrorKind::Unknown,
+            Some(Remediation::RepairProtocolHandler),
+        ),
+        (
+            "Failed to open WhatsApp: exit status 1",
+            ErrorKind::Unknown,
+            Some(Remediation::RepairProtocolHandler),
+        ),
+        (
+            "WhatsApp is logged out",
+            ErrorKind::SessionDisconnected,
+            Some(Remediation::RePairSession),
+        ),
+        (
+            "WhatsApp Desktop disconnected",
+            ErrorKind::SessionDisconnected,
+            Some(Remediation::RePairSession),
+        ),
+        (
+            "Notepad is in the foreground",
+            ErrorKind::WrongWindowFocused,
+            Some(Remediation::FocusWhatsapp),
+        ),
+        (
+            "WhatsApp lost focus before Enter",
+            ErrorKind::WrongWindowFocused,
+            Some(Remediation::FocusWhatsapp),
+        ),
+        (
+            "Chat timed out loading",
+            ErrorKind::Timeout,
+            Some(Remediation::RestartWhatsapp),
+        ),
+        (
+            "Send timeout after 30s",
+            ErrorKind::Timeout,
+            Some(Remediation::RestartWhatsapp),
+        ),
+        ("Failed: exit status 1", ErrorKind::Unknown, None),
+        ("", ErrorKind::Unknown, None),
+    ];
+
+    #[test]
+    fn raw_failures_map_to_kind_and_remediation() {
+        for &(raw, kind, remediation) in CASES {
+            assert_eq!(classify(raw), (kind, remediation), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn classification_ignores_case() {
+        assert_eq!(
+            classify("PERMISSION DENIED"),
+            (ErrorKind::PermissionDenied, Some(Remediation::GrantAccessibility))
+        );
+    }
+
+    #[test]
+    fn narrower_symptoms_win_over_broader_ones() {
+        // A missing helper mentioned alongside a timeout is still a missing helper
+        assert_eq!(
+            classify("xdotool timed out"),
+            (ErrorKind::MissingTool, Some(Remediation::InstallXdotool))
+        );
+        assert_eq!(
+            classify("Invalid phone; focus lost"),
+            (ErrorKind::InvalidPhone, Some(Remediation::CheckPhoneNumber))
+        );
+    }
+
+    #[test]
+    fn classified_errors_keep_the_raw_message() {
+        let error = SendError::classified("Failed to run xdotool: not found");
+        assert_eq!(error.kind, ErrorKind::MissingTool);
+        assert_eq!(error.remediation, Some(Remediation::InstallXdotool));
+        assert_eq!(error.to_string(), "Failed to run xdotool: not found");
+    }
+
+    #[test]
+    fn every_kind_but_unknown_has_a_hint() {
+        let kinds = [
+            ErrorKind::InvalidPhone,
+            ErrorKind::Timeout,
+            ErrorKind::WrongWindowFocused,
+            ErrorKind::SessionDisconnected,
+            ErrorKind::MissingTool,
+            ErrorKind::PermissionDenied,
+            ErrorKind::ProtocolHandlerAmbiguous,
+        ];
+        for kind in kinds {
+            let remediation = Remediation::for_kind(kind).unwrap_or_else(|| panic!("{:?} has no hint", kind));
+            assert!(!remediation.explanation().is_empty());
+        }
+        assert_eq!(Remediation::for_kind(ErrorKind::Unknown), None);
+    }
+}
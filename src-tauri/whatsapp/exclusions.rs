@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::StudentMessage;
+use crate::phone::normalize_to_e164;
+
+const EXCLUSION_LISTS_FILE: &str = "exclusion_lists.json";
+
+/// Who a campaign must skip, resolved when it is created. Entries are
+/// student ids or phone numbers; anything that normalizes to a phone is
+/// matched as both.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Exclusions {
+    #[serde(serialize_with = "crate::privacy::serialize_entries")]
+    pub student_ids: HashSet<String>,
+    #[serde(serialize_with = "crate::privacy::serialize_entries")]
+    pub phones: HashSet<String>,
+}
+
+impl Exclusions {
+    pub fn resolve<'a>(entries: impl IntoIterator<Item = &'a String>, default_country: &str) -> Self {
+        let mut exclusions = Self::default();
+        for entry in entries {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            if let Some(phone) = normalize_to_e164(entry, default_country) {
+                exclusions.phones.insert(phone);
+            }
+            exclusions.student_ids.insert(entry.to_string());
+        }
+        exclusions
+    }
+
+    pub fn excludes(&self, student: &StudentMessage, default_country: &str) -> bool {
+        self.student_ids.contains(&student.student_id)
+            || normalize_to_e164(&student.phone, default_country).is_some_and(|phone| self.phones.contains(&phone))
+    }
+}
+
+/// Named exclusion lists kept for reuse across campaigns, in the app data
+/// dir.
+pub struct ExclusionListStore {
+    path: PathBuf,
+}
+
+impl ExclusionListStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            path: data_dir.join(EXCLUSION_LISTS_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn list(&self) -> BTreeMap<String, Vec<String>> {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, name: &str) -> Result<Vec<String>, String> {
+        self.list()
+            .remove(name)
+            .ok_or_else(|| format!("Exclusion list \"{}\" not found", name))
+    }
+
+    /// Creates or replaces the list called `name`.
+    pub fn save(&self, name: &str, members: Vec<String>) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Exclusion list name is required".to_string());
+        }
+
+        let mut lists = self.list();
+        lists.insert(name.to_string(), members);
+        self.write(&lists)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let mut lists = self.list();
+        if lists.remove(name).is_none() {
+            return Err(format!("Exclusion list \"{}\" not found", name));
+        }
+        self.write(&lists)
+    }
+
+    fn write(&self, lists: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let contents = serde_json::to_string_pretty(lists).map_err(|e| e.to_string())?;
+        fs::write(&self.path, contents).map_err(|e| format!("Failed to save exclusion lists: {}", e))
+    }
+}
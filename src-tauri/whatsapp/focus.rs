@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::desktop;
+use crate::input;
+
+/// How often `whatsapp-waiting-for-focus` is repeated while a send is held.
+pub const FOCUS_NOTICE_INTERVAL: Duration = Duration::from_secs(5);
+pub const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitingForFocus {
+    pub campaign_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub waited_seconds: u64,
+    pub refocus_attempted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtaUpdate {
+    pub campaign_id: String,
+    pub remaining: usize,
+    pub estimated_seconds_remaining: u64,
+    pub estimated_finish_at: u64,
+    /// Total time sends were held for focus so far in this run.
+    pub focus_hold_ms: u64,
+}
+
+impl EtaUpdate {
+    /// Estimate before the first send, from the expected time of each
+    /// message; `project` takes over once there is a pace to go by.
+    pub fn estimate(campaign_id: &str, total: usize, message_time: Duration, now_millis: u64) -> Self {
+        let estimated_seconds_remaining = (message_time.as_secs_f64() * total as f64).round() as u64;
+
+        Self {
+            campaign_id: campaign_id.to_string(),
+            remaining: total,
+            estimated_seconds_remaining,
+            estimated_finish_at: now_millis + estimated_seconds_remaining * 1000,
+            focus_hold_ms: 0,
+        }
+    }
+
+    /// Projects the rest of the run from the pace so far, leaving out time
+    /// spent holding for focus so one long alt-tab doesn't skew it.
+    pub fn project(
+        campaign_id: &str,
+        processed: usize,
+        total: usize,
+        active: Duration,
+        focus_hold: Duration,
+        now_millis: u64,
+    ) -> Self {
+        let remaining = total.saturating_sub(processed);
+        let per_message = active.as_secs_f64() / processed.max(1) as f64;
+        let estimated_seconds_remaining = (per_message * remaining as f64).round() as u64;
+
+        Self {
+            campaign_id: campaign_id.to_string(),
+            remaining,
+            estimated_seconds_remaining,
+            estimated_finish_at: now_millis + estimated_seconds_remaining * 1000,
+            focus_hold_ms: focus_hold.as_millis() as u64,
+        }
+    }
+}
+
+/// Brings WhatsApp to the front; a bare deeplink does that without opening
+/// a chat.
+pub fn focus_whatsapp_window() -> Result<(), String> {
+    let scheme = desktop::active_variant().scheme();
+    input::simulator().open_url(&format!("{}://", scheme))
+}
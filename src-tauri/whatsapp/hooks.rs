@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use tokio::time::{sleep, Duration, Instant};
+
+use super::campaign::now_millis;
+
+/// Kept per run so a chatty script can't bloat the campaign record.
+const MAX_HOOK_STDERR_BYTES: usize = 4096;
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    BeforeCampaign,
+    AfterMessage,
+    AfterCampaign,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_seconds: u64,
+}
+
+pub(super) fn default_hook_timeout() -> u64 {
+    30
+}
+
+/// User scripts run around bulk sends. They execute arbitrary programs,
+/// so they stay off until explicitly enabled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    pub enabled: bool,
+    pub before_campaign: Option<HookCommand>,
+    pub after_message: Option<HookCommand>,
+    pub after_campaign: Option<HookCommand>,
+}
+
+impl HookSettings {
+    pub fn command_for(&self, event: HookEvent) -> Option<&HookCommand> {
+        if !self.enabled {
+            return None;
+        }
+
+        match event {
+            HookEvent::BeforeCampaign => self.before_campaign.as_ref(),
+            HookEvent::AfterMessage => self.after_message.as_ref(),
+            HookEvent::AfterCampaign => self.after_campaign.as_ref(),
+        }
+    }
+}
+
+/// One hook execution, journaled on the campaign record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookRun {
+    pub event: HookEvent,
+    pub program: String,
+    pub student_id: Option<String>,
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stderr: String,
+    /// Set when the program couldn't be started at all.
+    pub error: Option<String>,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Why a blocking hook stopped the campaign.
+    pub fn failure_reason(&self) -> String {
+        if let Some(error) = &self.error {
+            format!("Hook {} could not be started: {}", self.program, error)
+        } else if self.timed_out {
+            format!("Hook {} timed out", self.program)
+        } else {
+            match self.exit_code {
+                Some(code) => format!("Hook {} exited with code {}", self.program, code),
+                None => format!("Hook {} was terminated", self.program),
+            }
+        }
+    }
+}
+
+/// Runs `command` with `payload` as JSON on stdin, killing it after its
+/// timeout.
+pub async fn run_hook(
+    command: &HookCommand,
+    event: HookEvent,
+    payload: &Value,
+    student_id: Option<&str>,
+) -> HookRun {
+    let started_at = now_millis();
+    let started = Instant::now();
+    let mut run = HookRun {
+        event,
+        program: command.program.clone(),
+        student_id: student_id.map(str::to_string),
+        started_at,
+        duration_ms: 0,
+        exit_code: None,
+        timed_out: false,
+        stderr: String::new(),
+        error: None,
+    };
+
+    let spawned = Command::new(&command.program)
+        .args(&command.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            run.error = Some(e.to_string());
+            return run;
+        }
+    };
+
+    // A script that never reads stdin must not block us, nor one that
+    // fills the stderr pipe
+    if let Some(mut stdin) = child.stdin.take() {
+        let payload = crate::privacy::unmasked(|| serde_json::to_vec(payload)).unwrap_or_default();
+        thread::spawn(move || stdin.write_all(&payload));
+    }
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = (&mut stderr).take(MAX_HOOK_STDERR_BYTES as u64).read_to_end(&mut buffer);
+            let _ = io::copy(&mut stderr, &mut io::sink());
+            String::from_utf8_lossy(&buffer).into_owned()
+        })
+    });
+
+    // 0 would kill the hook before it starts
+    let timeout = match command.timeout_seconds {
+        0 => Duration::from_secs(default_hook_timeout()),
+        seconds => Duration::from_secs(seconds),
+    };
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                run.exit_code = status.code();
+                break;
+            }
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                run.timed_out = true;
+                break;
+            }
+            Ok(None) => sleep(HOOK_POLL_INTERVAL).await,
+            Err(e) => {
+                run.error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+
+    if let Some(reader) = stderr_reader {
+        run.stderr = reader.join().unwrap_or_default();
+    }
+    run.duration_ms = started.elapsed().as_millis() as u64;
+    run
+}
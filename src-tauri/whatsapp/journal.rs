@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::campaign::now_millis;
+use super::message_log::{self, MessageLogExport};
+use crate::privacy::mask_phone;
+use crate::tasks::TaskHandle;
+
+const JOURNAL_DIR: &str = "journal";
+const JOURNAL_FILE: &str = "events.log";
+/// The live file is rotated to `events.1.log` once it reaches this size.
+const MAX_JOURNAL_BYTES: u64 = 5 * 1024 * 1024;
+const ROTATED_FILES: usize = 3;
+/// Entries waiting for the writer; beyond this they are dropped and counted
+/// rather than holding up the send loop.
+const CHANNEL_CAPACITY: usize = 1024;
+/// Entries written per fsync.
+const MAX_BATCH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    /// Something emitted to the webview.
+    Event,
+    /// A backend decision the webview never sees, e.g. a retry.
+    Transition,
+}
+
+/// One line of the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: u64,
+    pub kind: EntryKind,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub student_id: Option<String>,
+    /// Payload with every `phone` masked.
+    pub data: Value,
+}
+
+/// Append-only record of what the backend emitted and decided, for
+/// working out after the fact why a message did or didn't go out.
+pub struct EventJournal {
+    dir: PathBuf,
+    sender: SyncSender<JournalEntry>,
+    dropped: Arc<AtomicU64>,
+    /// Entries sent to the writer and not yet on disk.
+    queued: Arc<AtomicU64>,
+}
+
+impl EventJournal {
+    /// Starts the writer thread for the journal under `data_dir`.
+    pub fn open(data_dir: &Path) -> Self {
+        let dir = data_dir.join(JOURNAL_DIR);
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let queued = Arc::new(AtomicU64::new(0));
+
+        let writer = JournalWriter {
+            dir: dir.clone(),
+            file: None,
+            size: 0,
+        };
+        let writer_dropped = dropped.clone();
+        let writer_queued = queued.clone();
+        thread::spawn(move || writer.run(receiver, writer_dropped, writer_queued));
+
+        Self { dir, sender, dropped, queued }
+    }
+
+    pub fn event<S: Serialize + ?Sized>(&self, name: &str, campaign_id: Option<&str>, payload: &S) {
+        let data = serde_json::to_value(payload).unwrap_or(Value::Null);
+        self.record(EntryKind::Event, name, campaign_id, data);
+    }
+
+    pub fn transition(&self, name: &str, campaign_id: Option<&str>, data: Value) {
+        self.record(EntryKind::Transition, name, campaign_id, data);
+    }
+
+    /// Never blocks: when the writer has fallen behind the entry is dropped
+    /// and counted, and the count is journaled once it catches up.
+    fn record(&self, kind: EntryKind, name: &str, campaign_id: Option<&str>, mut data: Value) {
+        mask_phones(&mut data);
+        let entry = JournalEntry {
+            at: now_millis(),
+            kind,
+            name: name.to_string(),
+            campaign_id: campaign_id
+                .map(str::to_string)
+                .or_else(|| data.get("campaign_id").and_then(Value::as_str).map(str::to_string)),
+            student_id: data.get("student_id").and_then(Value::as_str).map(str::to_string),
+            data,
+        };
+
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        if self.sender.try_send(entry).is_err() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Waits up to `timeout` for queued entries to reach the disk, e.g.
+    /// before the app quits. Returns whether they all did.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while self.queued.load(Ordering::SeqCst) > 0 {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        true
+    }
+
+    /// Copies one campaign's entries, oldest first, to `destination` and
+    /// returns how many there were. Entries still queued for the writer
+    /// are not included. A failed or cancelled export removes the file.
+    pub fn export(&self, campaign_id: &str, destination: &Path, task: &TaskHandle) -> Result<usize, String> {
+        let result = self.export_to(campaign_id, destination, task);
+        if result.is_err() {
+            let _ = fs::remove_file(destination);
+        }
+        result
+    }
+
+    /// Rows an `export_message_log` could write at most; see
+    /// `message_log::estimate_rows`.
+    pub fn estimate_message_log_rows(&self) -> usize {
+        message_log::estimate_rows(&journal_files(&self.dir))
+    }
+
+    /// Writes the message outcomes `export` asks for; see
+    /// `message_log::export`.
+    pub fn export_message_log(&self, export: &MessageLogExport, task: &TaskHandle) -> Result<usize, String> {
+        message_log::export(&journal_files(&self.dir), export, task)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Bytes on disk across the live and rotated files; an upper bound
+    /// for what an export writes.
+    pub fn size(&self) -> u64 {
+        journal_files(&self.dir)
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// `events.1.log` and up, oldest first; the live file isn't among them.
+    pub fn rotated_files(&self) -> Vec<PathBuf> {
+        (1..=ROTATED_FILES).rev().map(|index| rotated_path(&self.dir, index)).collect()
+    }
+
+    fn export_to(&self, campaign_id: &str, destination: &Path, task: &TaskHandle) -> Result<usize, String> {
+        let mut output = File::create(destination)
+            .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+
+        let mut count = 0;
+        let files = journal_files(&self.dir);
+        for (index, path) in files.iter().enumerate() {
+            task.check_cancelled()?;
+            task.progress(
+                "scanning",
+                (index * 100 / files.len()) as u8,
+                Some(format!("{} entries so far", count)),
+            );
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                // A crash can leave the last line half written
+                let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+                    continue;
+                };
+                if entry.campaign_id.as_deref() == Some(campaign_id) {
+                    writeln!(output, "{}", line).map_err(|e| format!("Failed to export journal: {}", e))?;
+                    count += 1;
+                }
+            }
+        }
+
+        output.sync_all().map_err(|e| format!("Failed to export journal: {}", e))?;
+        Ok(count)
+    }
+}
+
+struct JournalWriter {
+    dir: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl JournalWriter {
+    fn run(mut self, receiver: Receiver<JournalEntry>, dropped: Arc<AtomicU64>, queued: Arc<AtomicU64>) {
+        while let Ok(first) = receiver.recv() {
+            let mut batch = vec![first];
+            while batch.len() < MAX_BATCH {
+                match receiver.try_recv() {
+                    Ok(entry) => batch.push(entry),
+                    Err(_) => break,
+                }
+            }
+            let received = batch.len() as u64;
+
+            let lost = dropped.swap(0, Ordering::Relaxed);
+            if lost > 0 {
+                batch.push(JournalEntry {
+                    at: now_millis(),
+                    kind: EntryKind::Transition,
+                    name: "journal_overflow".to_string(),
+                    campaign_id: None,
+                    student_id: None,
+                    data: serde_json::json!({ "dropped": lost }),
+                });
+            }
+
+            if let Err(e) = self.write_batch(&batch) {
+                eprintln!("Failed to write event journal: {}", e);
+                // Reopen on the next batch
+                self.file = None;
+            }
+            queued.fetch_sub(received, Ordering::SeqCst);
+        }
+    }
+
+    fn write_batch(&mut self, batch: &[JournalEntry]) -> Result<(), String> {
+        if self.size >= MAX_JOURNAL_BYTES {
+            self.rotate()?;
+        }
+        let size = &mut self.size;
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                fs::create_dir_all(&self.dir).map_err(|e| format!("Failed to create journal directory: {}", e))?;
+                let path = self.dir.join(JOURNAL_FILE);
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+                *size = file.metadata().map(|m| m.len()).unwrap_or(0);
+                self.file.insert(file)
+            }
+        };
+
+        let mut lines = String::new();
+        for entry in batch {
+            if let Ok(line) = serde_json::to_string(entry) {
+                lines.push_str(&line);
+                lines.push('\n');
+            }
+        }
+        file.write_all(lines.as_bytes()).map_err(|e| e.to_string())?;
+        file.sync_data().map_err(|e| e.to_string())?;
+        *size += lines.len() as u64;
+        Ok(())
+    }
+
+    /// `events.log` becomes `events.1.log`, `events.1.log` becomes
+    /// `events.2.log` and so on; the oldest is discarded.
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file = None;
+        self.size = 0;
+        for index in (1..ROTATED_FILES).rev() {
+            let _ = fs::rename(rotated_path(&self.dir, index), rotated_path(&self.dir, index + 1));
+        }
+        fs::rename(self.dir.join(JOURNAL_FILE), rotated_path(&self.dir, 1))
+            .map_err(|e| format!("Failed to rotate event journal: {}", e))
+    }
+}
+
+fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("events.{}.log", index))
+}
+
+/// Oldest first.
+fn journal_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = (1..=ROTATED_FILES).rev().map(|index| rotated_path(dir, index)).collect();
+    files.push(dir.join(JOURNAL_FILE));
+    files
+}
+
+/// Masks every `phone` field, whatever the masking setting, so a journal
+/// can be attached to a support ticket as is.
+fn mask_phones(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                match field {
+                    // Already masked when the setting is on
+                    Value::String(phone) if key == "phone" && !phone.contains('X') => {
+                        *phone = mask_phone(phone);
+                    }
+                    _ => mask_phones(field),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask_phones),
+        _ => {}
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::campaign::ResendAttempt;
+use super::completion::csv_field;
+use super::journal::JournalEntry;
+use crate::privacy::mask_phone;
+use crate::tasks::TaskHandle;
+
+/// Exports estimated above this many rows need confirming first.
+pub const LARGE_EXPORT_ROWS: usize = 100_000;
+/// The only channel messages go out on so far.
+const CHANNEL: &str = "whatsapp";
+/// Progress statuses that aren't the outcome of a message: a retry still
+/// to come, or a dry run that sent nothing.
+const NOT_OUTCOMES: [&str; 3] = ["retrying", "previewed", "would_fail"];
+
+/// Which messages `export_message_log` writes; every field narrows it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageLogFilter {
+    pub student_id: Option<String>,
+    /// Matched against the masked numbers the journal keeps, so two
+    /// numbers that mask alike both match.
+    pub phone: Option<String>,
+    pub campaign_id: Option<String>,
+    /// Epoch ms, inclusive.
+    pub from: Option<u64>,
+    /// Epoch ms, exclusive.
+    pub to: Option<u64>,
+    /// e.g. `sent`, `failed`, `failed_after_retries`.
+    pub status: Option<String>,
+    pub channel: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageLogFormat {
+    Csv,
+    Jsonl,
+}
+
+/// What `export_message_log` writes and where.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageLogExport {
+    #[serde(default)]
+    pub filter: MessageLogFilter,
+    pub format: MessageLogFormat,
+    pub destination: String,
+    /// Adds the rendered text; off for exports that must leave content out.
+    #[serde(default)]
+    pub include_message: bool,
+}
+
+/// One message outcome as exported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageLogRow {
+    pub at: u64,
+    pub campaign_id: String,
+    pub student_id: String,
+    pub name: String,
+    /// Masked, as the journal keeps it.
+    pub phone: String,
+    pub channel: String,
+    pub status: String,
+    pub attempt: u32,
+    pub error: Option<String>,
+    pub demo_mode: bool,
+    /// Only with `include_message`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Upper bound of the rows an export could write: every message outcome
+/// and re-send in the journal, before filtering. Counted without parsing.
+pub fn estimate_rows(files: &[PathBuf]) -> usize {
+    files
+        .iter()
+        .filter_map(|path| File::open(path).ok())
+        .flat_map(|file| BufReader::new(file).lines().map_while(Result::ok))
+        .filter(|line| line.contains("\"whatsapp-message-progress\"") || line.contains("\"message_resent\""))
+        .count()
+}
+
+/// Streams the matching rows of the journal `files`, oldest first, to
+/// the export's destination; returns how many there were. A failed or
+/// cancelled export removes the file.
+pub fn export(files: &[PathBuf], export: &MessageLogExport, task: &TaskHandle) -> Result<usize, String> {
+    let destination = Path::new(&export.destination);
+    let result = export_to(files, export, destination, task);
+    if result.is_err() {
+        let _ = std::fs::remove_file(destination);
+    }
+    result
+}
+
+fn export_to(files: &[PathBuf], export: &MessageLogExport, destination: &Path, task: &TaskHandle) -> Result<usize, String> {
+    let MessageLogExport { filter, format, include_message, .. } = export;
+    let (format, include_message) = (*format, *include_message);
+    let file = File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let mut output = BufWriter::new(file);
+    if format == MessageLogFormat::Csv {
+        let mut header = "at,campaign_id,student_id,name,phone,channel,status,attempt,error,demo_mode".to_string();
+        if include_message {
+            header.push_str(",message");
+        }
+        writeln!(output, "{}", header).map_err(|e| format!("Failed to export message log: {}", e))?;
+    }
+
+    let phone = filter.phone.as_deref().map(mask_phone);
+    let mut count = 0;
+    for (index, path) in files.iter().enumerate() {
+        task.check_cancelled()?;
+        task.progress(
+            "exporting",
+            (index * 100 / files.len()) as u8,
+            Some(format!("{} messages so far", count)),
+        );
+        let Ok(file) = File::open(path) else {
+            continue;
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            // A crash can leave the last line half written
+            let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+                continue;
+            };
+            for mut row in rows(entry) {
+                if !matches(filter, phone.as_deref(), &row) {
+                    continue;
+                }
+                if !include_message {
+                    row.message = None;
+                }
+                write_row(&mut output, format, include_message, &row)?;
+                count += 1;
+            }
+        }
+    }
+
+    let file = output.into_inner().map_err(|e| format!("Failed to export message log: {}", e))?;
+    file.sync_all().map_err(|e| format!("Failed to export message log: {}", e))?;
+    Ok(count)
+}
+
+/// The message outcomes a journal entry records: one per progress event,
+/// one per student a re-send covered.
+fn rows(entry: JournalEntry) -> Vec<MessageLogRow> {
+    let field = |data: &Value, name: &str| data.get(name).and_then(Value::as_str).map(str::to_string);
+    match entry.name.as_str() {
+        "whatsapp-message-progress" => {
+            let data = &entry.data;
+            let Some(status) = field(data, "status").filter(|status| !NOT_OUTCOMES.contains(&status.as_str())) else {
+                return Vec::new();
+            };
+            vec![MessageLogRow {
+                at: entry.at,
+                campaign_id: field(data, "campaign_id").unwrap_or_default(),
+                student_id: field(data, "student_id").unwrap_or_default(),
+                name: field(data, "name").unwrap_or_default(),
+                phone: field(data, "phone").unwrap_or_default(),
+                channel: CHANNEL.to_string(),
+                status,
+                attempt: data.get("attempt").and_then(Value::as_u64).unwrap_or(0) as u32,
+                error: field(data, "error"),
+                demo_mode: data.get("demo_mode").and_then(Value::as_bool).unwrap_or(false),
+                message: field(data, "rendered_message"),
+            }]
+        }
+        "message_resent" => {
+            let Ok(resend) = serde_json::from_value::<ResendAttempt>(entry.data) else {
+                return Vec::new();
+            };
+            resend
+                .student_ids
+                .iter()
+                .map(|student_id| MessageLogRow {
+                    at: entry.at,
+                    campaign_id: entry.campaign_id.clone().unwrap_or_default(),
+                    student_id: student_id.clone(),
+                    name: String::new(),
+                    phone: String::new(),
+                    channel: CHANNEL.to_string(),
+                    status: resend.status.clone(),
+                    attempt: resend.attempt,
+                    error: resend.error.clone(),
+                    demo_mode: false,
+                    message: None,
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn matches(filter: &MessageLogFilter, masked_phone: Option<&str>, row: &MessageLogRow) -> bool {
+    let equals = |wanted: &Option<String>, actual: &str| wanted.as_deref().is_none_or(|wanted| wanted == actual);
+    equals(&filter.student_id, &row.student_id)
+        && equals(&filter.campaign_id, &row.campaign_id)
+        && equals(&filter.status, &row.status)
+        && equals(&filter.channel, &row.channel)
+        && masked_phone.is_none_or(|phone| phone == row.phone)
+        && filter.from.is_none_or(|from| row.at >= from)
+        && filter.to.is_none_or(|to| row.at < to)
+}
+
+fn write_row(output: &mut impl Write, format: MessageLogFormat, include_message: bool, row: &MessageLogRow) -> Result<(), String> {
+    let line = match format {
+        MessageLogFormat::Jsonl => serde_json::to_string(row).map_err(|e| e.to_string())?,
+        MessageLogFormat::Csv => {
+            let mut fields = vec![
+                row.at.to_string(),
+                row.campaign_id.clone(),
+                row.student_id.clone(),
+                row.name.clone(),
+                row.phone.clone(),
+                row.channel.clone(),
+                row.status.clone(),
+                row.attempt.to_string(),
+                row.error.clone().unwrap_or_default(),
+                row.demo_mode.to_string(),
+            ];
+            if include_message {
+                fields.push(row.message.clone().unwrap_or_default());
+            }
+            fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",")
+        }
+    };
+    writeln!(output, "{}", line).map_err(|e| format!("Failed to export message log: {}", e))
+}
@@ -1,34 +1,284 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use serde::{Deserialize, Serialize};
-use tauri::{Window, Emitter};
-use tokio::time::{sleep, Duration};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use tokio::time::{sleep, Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+mod attachments;
+mod auto_pause;
+mod benchmark;
+mod campaign;
+mod cleanup;
+mod completion;
+mod consent;
+mod control;
+mod csv_import;
+mod deeplink;
+mod drafts;
+mod dry_run;
+mod dynamic;
+mod errors;
+mod exclusions;
+mod focus;
+mod hooks;
+mod pause;
+mod receipts;
+mod journal;
+mod message_log;
+mod ordering;
+mod render;
+mod resume;
+mod retry;
+mod status_text;
+mod summary;
+mod tokens;
+mod trace;
+mod warmup;
+mod watchdog;
+pub use attachments::Attachment;
+use auto_pause::FailureStats;
+use cleanup::{StorageCategory, StoredFile};
+use completion::{CampaignEnd, CompletionActionKind, CompletionActionRun, CompletionNotice, CompletionOutcome, FailedMessage};
+pub use cleanup::{Artifact, MaintenanceReport, RetentionSettings, StorageUsage};
+pub use completion::CompletionAction;
+use control::BulkSendControl;
+use dynamic::SendClock;
+use hooks::{HookEvent, HookRun};
+use focus::{EtaUpdate, WaitingForFocus, FOCUS_NOTICE_INTERVAL, FOCUS_POLL_INTERVAL};
+use receipts::{LazyReceipts, LookAhead, ReceiptSpec, RECEIPT_LOOKAHEAD};
+use retry::{RetryDecision, RetryJournalEntry};
+use summary::{CampaignSummary, SummaryOutcome, SupervisorNotified};
+pub use summary::{BulkSendSummary, DEFAULT_SUMMARY_TEMPLATE};
+use trace::CampaignTrace;
+use tokens::{DeprecatedTokensFound, TemplateChange, TemplateSource};
+pub use tokens::{deprecated_tokens, token_rename_dir, DeprecatedToken, TokenRename};
+pub use trace::{trace_dir, TraceReplay};
+
+pub use benchmark::{machine_id, run_benchmark, BenchmarkResult, BenchmarkStore};
+pub use campaign::{now_millis, CampaignRecord, CampaignSource, CampaignStatus, CampaignStore, IncompleteCampaign, MergedMessage, ResendAttempt};
+use campaign::StudentProgress;
+pub use consent::{CampaignKind, ConsentLevel};
+pub use csv_import::{build_campaign_from_csv, CsvColumnMapping};
+pub use deeplink::{build_send_url, check_open_with_dialog, EncodedMessage};
+pub use drafts::{CampaignDraft, DraftRetention, DraftStore, DraftSummary};
+pub use errors::{ErrorKind, Remediation, SendError};
+pub use exclusions::ExclusionListStore;
+use exclusions::Exclusions;
+pub use focus::focus_whatsapp_window;
+pub use hooks::{HookCommand, HookSettings};
+pub use pause::PauseReason;
+use pause::PauseReasonsChanged;
+pub use journal::EventJournal;
+pub use message_log::{MessageLogExport, LARGE_EXPORT_ROWS};
+pub use status_text::{set_verbosity as set_status_verbosity, StatusVerbosity};
+pub use ordering::OrderingStrategy;
+pub use retry::RetryPolicy;
+pub use warmup::WarmupSettings;
+use warmup::WarmupStarted;
+use watchdog::SendWatchdog;
+pub use watchdog::{check_heartbeat, heartbeat_path};
+use crate::input::{self, Key};
+use crate::metrics::{self, Stage};
+use crate::settings::AppSettings;
+use resume::{SystemResumeNotice, DEFAULT_RESUME_SETTLE_SECONDS, MAX_RESUME_CHECKS};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BulkMessageRequest {
     pub students: Vec<StudentMessage>,
+    #[serde(flatten)]
+    pub options: CampaignOptions,
+}
+
+const CLONE_CHUNK_SIZE: usize = 500;
+/// Time WhatsApp takes to open a chat from a deeplink.
+const CHAT_LOAD_DELAY: Duration = Duration::from_secs(3);
+/// Recent events kept for a webview that was hidden or reloaded.
+const BUFFERED_EVENTS: usize = 1000;
+/// How long a completion `Shutdown` waits for the journal before quitting.
+const SHUTDOWN_JOURNAL_FLUSH: Duration = Duration::from_secs(5);
+
+type MessageBatches = Box<dyn Iterator<Item = Result<Vec<StudentMessage>, String>> + Send>;
+
+/// Everything about a bulk run except its recipients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignOptions {
     pub message_template: String,
     pub attach_receipt: bool,
     pub interval_seconds: u64,
+    #[serde(default)]
+    pub resume_settle_seconds: Option<u64>,
+    #[serde(default)]
+    pub abort_on_failure_rate: Option<f32>,
+    #[serde(default)]
+    pub abort_after_consecutive_failures: Option<u32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub campaign_kind: CampaignKind,
+    /// Sent to every student after their text, before their own receipt.
+    #[serde(default)]
+    pub common_attachments: Vec<Attachment>,
+    /// Send one message per phone to students who share it, rendering
+    /// `{{#each students}}...{{/each}}` once per student.
+    #[serde(default)]
+    pub merge_shared_phone: bool,
+    #[serde(default)]
+    pub source: Option<CampaignSource>,
+    /// The draft this campaign was started from; it is deleted once the
+    /// campaign starts sending.
+    #[serde(default)]
+    pub draft_id: Option<String>,
+    /// Student ids or phones this campaign skips.
+    #[serde(default)]
+    pub exclusions: Vec<String>,
+    /// Saved exclusion lists to skip as well, resolved when the campaign
+    /// is created.
+    #[serde(default)]
+    pub exclusion_lists: Vec<String>,
+    /// Message `supervisor_number` with the outcome when the campaign
+    /// finishes, is auto-paused or is cancelled.
+    #[serde(default)]
+    pub notify_supervisor: bool,
+    /// Run in order once the campaign has ended.
+    #[serde(default)]
+    pub completion_actions: Vec<CompletionAction>,
+    /// Send order; input order when unset.
+    #[serde(default)]
+    pub ordering: Option<OrderingStrategy>,
+    /// Retries of a failed message, in place of `retry_policies` for the
+    /// errors that are retried at all.
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// Wait before the first retry, doubling after each up to 15 minutes.
+    /// Retries never come sooner than `interval_seconds`.
+    #[serde(default)]
+    pub retry_backoff_seconds: Option<u64>,
+    /// Render and check every message without sending any or waiting
+    /// between them; statuses are `previewed` or `would_fail`.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// With `attach_receipt`, generate each receipt from the student's
+    /// `receipt_spec` with `receipt_generator` just before it's sent,
+    /// instead of attaching `receipt_path`.
+    #[serde(default)]
+    pub lazy_receipts: bool,
+}
+
+/// What may differ from the original when cloning a campaign.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloneOverrides {
+    pub name: Option<String>,
+    pub message_template: Option<String>,
+    pub interval_seconds: Option<u64>,
+    /// E.g. `false` to send a campaign that was only a dry run.
+    pub dry_run: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StudentMessage {
     pub student_id: String,
     pub name: String,
+    #[serde(serialize_with = "crate::privacy::serialize_phone", deserialize_with = "crate::privacy::deserialize_phone")]
     pub phone: String,
     pub receipt_path: Option<String>,
+    /// Used in place of `receipt_path` by campaigns with `lazy_receipts`.
+    #[serde(default)]
+    pub receipt_spec: Option<ReceiptSpec>,
     pub personalization_tokens: HashMap<String, String>,
+    #[serde(default)]
+    pub consent: Option<ConsentLevel>,
+    /// ISO date the fee falls due; `{days_overdue}` is worked out from it
+    /// when the message is sent.
+    #[serde(default)]
+    pub due_date: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MessageProgress {
     pub student_id: String,
     pub name: String,
+    #[serde(serialize_with = "crate::privacy::serialize_phone")]
     pub phone: String,
     pub status: String,
+    /// `status` as a sentence for screen readers, counts spelled out.
+    pub status_text: String,
     pub error: Option<String>,
+    pub error_kind: Option<ErrorKind>,
+    /// Fix-it hint for a failure; see `explain_error`.
+    pub remediation: Option<Remediation>,
+    /// 1-based send attempt; for `retrying`, the one that just failed, so
+    /// also the retry about to be made. 0 when skipped or in a dry run.
+    pub attempt: u32,
     pub processed: usize,
     pub total: usize,
+    pub campaign_id: String,
+    pub demo_mode: bool,
+    /// The text actually sent, dynamic tokens resolved; `None` when the
+    /// student was skipped.
+    pub rendered_message: Option<String>,
+}
+
+/// A message as it would be sent, for pre-flight review.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessagePreview {
+    pub student_ids: Vec<String>,
+    pub name: String,
+    #[serde(serialize_with = "crate::privacy::serialize_phone")]
+    pub phone: String,
+    pub message: String,
+    /// Dynamic tokens the template uses, as they resolve right now; the
+    /// run resolves them again for each message.
+    pub dynamic_values: HashMap<String, String>,
+    pub as_of: u64,
+}
+
+/// Payload of `whatsapp-campaign-pending`: nothing is sent before
+/// `starts_at`, and `abort_pending_campaign` cancels the start.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CampaignPending {
+    pub campaign_id: String,
+    pub delay_seconds: u64,
+    pub starts_at: u64,
+    pub status_text: String,
+}
+
+/// Payload of `whatsapp-bulk-paused`, emitted once the run has actually
+/// stopped; counts are of messages, like `MessageProgress`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkPaused {
+    pub campaign_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub reasons: Vec<PauseReason>,
+    pub status_text: String,
+}
+
+/// Payload of `whatsapp-bulk-cancelled`; counts are of messages, like
+/// `MessageProgress`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkCancelled {
+    pub campaign_id: String,
+    pub processed: usize,
+    pub remaining: usize,
+    pub total: usize,
+    pub status_text: String,
+}
+
+/// An emitted event as kept for `get_missed_events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferedEvent {
+    pub sequence: u64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub at: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,16 +288,46 @@ pub struct WhatsAppSession {
     pub qr_code: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct WhatsAppManager {
     session: Option<String>,
-    is_connected: bool,
+    /// Shared with the clone a run works on, so it sees a disconnect.
+    is_connected: Arc<AtomicBool>,
+    bulk_control: Arc<BulkSendControl>,
+    campaigns: Arc<CampaignStore>,
+    drafts: Arc<DraftStore>,
+    exclusion_lists: Arc<ExclusionListStore>,
+    heartbeat_path: PathBuf,
+    journal: Arc<EventJournal>,
+    trace_dir: PathBuf,
+    token_rename_dir: PathBuf,
+    recent_events: Arc<Mutex<VecDeque<BufferedEvent>>>,
+    next_sequence: Arc<AtomicU64>,
 }
 
 impl WhatsAppManager {
-    pub fn new() -> Self {
+    pub fn new(
+        campaigns: CampaignStore,
+        drafts: DraftStore,
+        exclusion_lists: ExclusionListStore,
+        heartbeat_path: PathBuf,
+        journal: EventJournal,
+        trace_dir: PathBuf,
+        token_rename_dir: PathBuf,
+    ) -> Self {
         Self {
             session: None,
-            is_connected: false,
+            is_connected: Arc::new(AtomicBool::new(false)),
+            bulk_control: Arc::new(BulkSendControl::default()),
+            campaigns: Arc::new(campaigns),
+            drafts: Arc::new(drafts),
+            exclusion_lists: Arc::new(exclusion_lists),
+            heartbeat_path,
+            journal: Arc::new(journal),
+            trace_dir,
+            token_rename_dir,
+            recent_events: Arc::new(Mutex::new(VecDeque::new())),
+            next_sequence: Arc::new(AtomicU64::new(1)),
         }
     }
 
@@ -55,7 +335,7 @@ impl WhatsAppManager {
         // Simulate WhatsApp Web authentication
         // In a real implementation, this would use puppeteer or similar
         
-        if self.is_connected {
+        if self.is_connected() {
             return Ok(WhatsAppSession {
                 is_connected: true,
                 session_id: self.session.clone(),
@@ -67,15 +347,15 @@ impl WhatsAppManager {
         let qr_code = "https://web.whatsapp.com/qr/MOCK_QR_CODE".to_string();
         
         // Emit QR code to frontend
-        window.emit("whatsapp-qr-code", &qr_code).map_err(|e| e.to_string())?;
+        self.emit(window, "whatsapp-qr-code", None, &qr_code)?;
         
         // Simulate waiting for QR scan (in real implementation, this would wait for actual scan)
         sleep(Duration::from_secs(3)).await;
         
         self.session = Some(uuid::Uuid::new_v4().to_string());
-        self.is_connected = true;
+        self.is_connected.store(true, Ordering::SeqCst);
         
-        window.emit("whatsapp-connected", &()).map_err(|e| e.to_string())?;
+        self.emit(window, "whatsapp-connected", None, &())?;
         
         Ok(WhatsAppSession {
             is_connected: true,
@@ -84,94 +364,1974 @@ impl WhatsAppManager {
         })
     }
 
+    /// One-shot form of the streamed flow below.
     pub async fn send_bulk_messages(
         &self,
         request: BulkMessageRequest,
+        settings: &AppSettings,
         window: &Window,
-    ) -> Result<(), String> {
-        if !self.is_connected {
+    ) -> Result<BulkSendSummary, String> {
+        if !self.is_connected() && !request.options.dry_run {
             return Err("WhatsApp session not connected".to_string());
         }
+        if self.bulk_control.is_running() {
+            return Err("A bulk send is already in progress".to_string());
+        }
 
-        let total = request.students.len();
-        
-        for (index, student) in request.students.iter().enumerate() {
-            // Personalize message
-            let mut personalized_message = request.message_template.clone();
-            for (token, value) in &student.personalization_tokens {
-                personalized_message = personalized_message.replace(&format!("{{{}}}", token), value);
+        let campaign_id = self.start_streamed_campaign(request.options, &settings.default_country)?;
+        self.append_campaign_students(&campaign_id, &request.students, &settings.default_country)?;
+        self.finalize_streamed_campaign(&campaign_id, settings, window).await
+    }
+
+    /// Creates a campaign whose recipients are appended in chunks; nothing
+    /// is sent until it is finalized.
+    pub fn start_streamed_campaign(&self, mut options: CampaignOptions, default_country: &str) -> Result<String, String> {
+        if let Some(rate) = options.abort_on_failure_rate {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err("abort_on_failure_rate must be between 0 and 1".to_string());
             }
+        }
 
-            // Simulate sending message
-            let result = self.send_individual_message(
-                &student.phone,
-                &personalized_message,
+        // Named lists are copied in now, so later edits don't change who
+        // an existing campaign skips
+        let mut entries = options.exclusions.clone();
+        for name in &options.exclusion_lists {
+            entries.extend(self.exclusion_lists.get(name)?);
+        }
+
+        // Saved with the options, so resuming or cloning shuffles the same way
+        if let Some(ordering) = &mut options.ordering {
+            ordering.pin_seed(campaign::now_millis());
+        }
+
+        let mut record = CampaignRecord::new(options);
+        record.exclusions = Exclusions::resolve(&entries, default_country);
+        self.campaigns.save(&record)?;
+        self.campaigns.append_students(&record.campaign_id, &[])?;
+        Ok(record.campaign_id)
+    }
+
+    /// Writes a chunk of recipients straight to the campaign store and
+    /// returns how many the campaign has so far. Excluded students are
+    /// left out and only noted on the record.
+    pub fn append_campaign_students(
+        &self,
+        campaign_id: &str,
+        chunk: &[StudentMessage],
+        default_country: &str,
+    ) -> Result<usize, String> {
+        let mut record = self.campaigns.load(campaign_id)?;
+        if record.status != CampaignStatus::Building {
+            return Err("Campaign has already been finalized".to_string());
+        }
+
+        let (excluded, included): (Vec<&StudentMessage>, Vec<&StudentMessage>) = chunk
+            .iter()
+            .partition(|student| record.exclusions.excludes(student, default_country));
+        record.excluded.extend(excluded.into_iter().map(|student| student.student_id.clone()));
+
+        self.campaigns.append_students(campaign_id, included.iter().copied())?;
+        record.total += included.len();
+        self.campaigns.save(&record)?;
+        Ok(record.total)
+    }
+
+    /// Pre-flight checks, then sends to every appended student, reading
+    /// them back from disk one at a time.
+    pub async fn finalize_streamed_campaign(
+        &self,
+        campaign_id: &str,
+        settings: &AppSettings,
+        window: &Window,
+    ) -> Result<BulkSendSummary, String> {
+        self.send_campaign(campaign_id, false, settings, window).await
+    }
+
+    /// Picks a campaign left sending by a crash or reboot back up, skipping
+    /// the students it already reached; the latest one when no id is given.
+    pub async fn resume_pending_bulk_send(
+        &self,
+        campaign_id: Option<&str>,
+        settings: &AppSettings,
+        window: &Window,
+    ) -> Result<BulkSendSummary, String> {
+        let campaign_id = match campaign_id {
+            Some(campaign_id) => campaign_id.to_string(),
+            None => self
+                .list_incomplete_jobs()?
+                .into_iter()
+                .next()
+                .map(|job| job.campaign_id)
+                .ok_or_else(|| "No interrupted bulk send to resume".to_string())?,
+        };
+        self.send_campaign(&campaign_id, true, settings, window).await
+    }
+
+    /// Campaigns still marked sending that no run is sending, newest first.
+    pub fn list_incomplete_jobs(&self) -> Result<Vec<IncompleteCampaign>, String> {
+        let running = self.sending_campaign();
+        self.campaigns
+            .list(None, None)?
+            .into_iter()
+            .filter(|record| record.status == CampaignStatus::Sending)
+            .filter(|record| running.as_deref() != Some(record.campaign_id.as_str()))
+            .map(|record| {
+                let progress = self.campaigns.progress(&record.campaign_id)?;
+                Ok(IncompleteCampaign {
+                    sent: progress.values().filter(|status| *status == "sent").count(),
+                    campaign_id: record.campaign_id,
+                    name: record.name,
+                    started_at: record.started_at,
+                    total: record.total,
+                })
+            })
+            .collect()
+    }
+
+    /// `resume` continues an interrupted run instead of starting a new one.
+    async fn send_campaign(
+        &self,
+        campaign_id: &str,
+        resume: bool,
+        settings: &AppSettings,
+        window: &Window,
+    ) -> Result<BulkSendSummary, String> {
+        let mut record = self.campaigns.load(campaign_id)?;
+        if resume && record.status != CampaignStatus::Sending {
+            return Err(format!("Campaign {} was not interrupted while sending", campaign_id));
+        }
+        // A countdown cut short by a crash leaves the record pending
+        if !resume && !matches!(record.status, CampaignStatus::Building | CampaignStatus::PendingStart) {
+            return Err("Campaign has already been finalized".to_string());
+        }
+        if record.total == 0 {
+            return Err("Campaign has no students".to_string());
+        }
+        let mut options = record.options.clone()
+            .ok_or_else(|| format!("Campaign {} has no send options", campaign_id))?;
+        attachments::validate(&options.common_attachments)?;
+
+        // Renamed tokens still resolve for now, with a warning
+        let deprecated = tokens::deprecated_tokens(&options.message_template, &settings.token_aliases);
+        if !deprecated.is_empty() {
+            let found = DeprecatedTokensFound {
+                campaign_id: record.campaign_id.clone(),
+                tokens: deprecated,
+            };
+            self.emit(window, "whatsapp-deprecated-tokens", None, &found)?;
+        }
+        options.message_template = tokens::resolve_aliases(&options.message_template, &settings.token_aliases);
+
+        if options.dry_run {
+            return self.dry_run_campaign(record, &options, settings, window);
+        }
+        let receipt_generator = match options.attach_receipt && options.lazy_receipts {
+            true => Some(
+                settings
+                    .receipt_generator
+                    .clone()
+                    .ok_or_else(|| "lazy_receipts needs a receipt_generator in settings".to_string())?,
+            ),
+            false => None,
+        };
+        if !self.is_connected() {
+            return Err("WhatsApp session not connected".to_string());
+        }
+
+        let _run = self.bulk_control.try_start()
+            .ok_or_else(|| "A bulk send is already in progress".to_string())?;
+
+        // Last chance to abort before anything is dispatched. A campaign
+        // that got through once (e.g. then blocked by a hook) isn't held again
+        let delay = settings.send_confirmation_delay_seconds;
+        if delay > 0 && record.start_confirmed_at.is_none() && !resume {
+            self.bulk_control.set_campaign_id(&record.campaign_id);
+            record.status = CampaignStatus::PendingStart;
+            self.campaigns.save(&record)?;
+            self.journal_status(&record);
+            let pending = CampaignPending {
+                campaign_id: record.campaign_id.clone(),
+                delay_seconds: delay,
+                starts_at: campaign::now_millis() + delay * 1000,
+                status_text: status_text::campaign_pending(delay),
+            };
+            self.emit(window, "whatsapp-campaign-pending", None, &pending)?;
+
+            let aborted = self.bulk_control.wait_pending(Duration::from_secs(delay)).await;
+            record.status = CampaignStatus::Building;
+            if aborted {
+                self.campaigns.save(&record)?;
+                self.emit(window, "whatsapp-campaign-pending-aborted", Some(&record.campaign_id), &record.campaign_id)?;
+                return Err("Campaign was aborted before it started".to_string());
+            }
+            record.start_confirmed_at = Some(campaign::now_millis());
+            self.campaigns.save(&record)?;
+            self.journal.transition("start_confirmed", Some(&record.campaign_id), serde_json::Value::Null);
+        }
+
+        // A failing before_campaign hook blocks the start; the campaign stays
+        // in building state so it can be finalized again. A resumed run
+        // already got through it
+        if !resume {
+            let payload = serde_json::json!({
+                "event": "before_campaign",
+                "campaign_id": record.campaign_id,
+                "name": record.name,
+                "total": record.total,
+            });
+            if let Some(run) = self.run_campaign_hook(settings, HookEvent::BeforeCampaign, payload, None, &mut record).await? {
+                if !run.succeeded() {
+                    return Err(run.failure_reason());
+                }
+            }
+        }
+
+        // Demo runs never touch the real WhatsApp window
+        if settings.warmup.enabled && !settings.demo_mode {
+            let was_running = crate::desktop::is_whatsapp_running();
+            self.emit(window, "whatsapp-warmup-started", Some(&record.campaign_id), &WarmupStarted { was_running })?;
+            let timings = warmup::warm_up(&settings.warmup, was_running).await?;
+            self.emit(window, "whatsapp-warmup-complete", Some(&record.campaign_id), &timings)?;
+        }
+
+        let mut failures = FailureStats::default();
+        record.status = CampaignStatus::Sending;
+        if !resume {
+            record.started_at = campaign::now_millis();
+        }
+        record.demo_mode = settings.demo_mode;
+        self.campaigns.save(&record)?;
+        self.journal_status(&record);
+        let already_sent: HashSet<String> = match resume {
+            true => self
+                .campaigns
+                .progress(&record.campaign_id)?
+                .into_iter()
+                .filter(|(_, status)| status == "sent")
+                .map(|(student_id, _)| student_id)
+                .collect(),
+            false => HashSet::new(),
+        };
+        if resume {
+            self.journal.transition(
+                "resumed_after_restart",
+                Some(&record.campaign_id),
+                serde_json::json!({ "already_sent": already_sent.len() }),
+            );
+        }
+        self.bulk_control.set_campaign_id(&record.campaign_id);
+
+        // The draft has served its purpose; the campaign record links it
+        if let Some(draft_id) = &options.draft_id {
+            let _ = self.drafts.delete(draft_id);
+        }
+        let run_started = Instant::now();
+        let mut focus_hold = Duration::ZERO;
+        let mut summary = CampaignSummary {
+            campaign_id: record.campaign_id.clone(),
+            name: record.name.clone(),
+            outcome: SummaryOutcome::Finished,
+            sent: 0,
+            failed: 0,
+            skipped: record.excluded.len(),
+            duration_seconds: 0,
+        };
+
+        let mut failed_messages = Vec::new();
+
+        let mut trace = if settings.record_campaign_trace {
+            let students = self.campaigns.students(&record.campaign_id)?;
+            Some(CampaignTrace::capture(&record, &options, settings, students)?)
+        } else {
+            None
+        };
+        let (batches, total) = self.message_batches(&record, &options, &settings.default_country)?;
+        // Receipts may be missing for some students; estimate with them
+        let attachment_count = options.common_attachments.len() + usize::from(options.attach_receipt);
+        let message_time = watchdog::expected_message_time(options.interval_seconds, attachment_count);
+        let watchdog = SendWatchdog::new(&record.campaign_id, total, message_time);
+        let _heartbeat = watchdog::spawn(
+            watchdog.clone(),
+            self.heartbeat_path.clone(),
+            self.bulk_control.clone(),
+            self.journal.clone(),
+            window.clone(),
+        );
+        let eta = EtaUpdate::estimate(&record.campaign_id, total, message_time, campaign::now_millis());
+        self.emit(window, "whatsapp-eta-updated", None, &eta)?;
+        let mut receipts = receipt_generator.map(|command| LazyReceipts::new(command, &record.campaign_id));
+        let mut batches = LookAhead::new(batches, if receipts.is_some() { RECEIPT_LOOKAHEAD } else { 0 });
+        while let Some((index, batch)) = batches.next() {
+            // A pause during the countdown, warm-up or a hook holds the
+            // first send too
+            self.wait_out_pause(&mut record, index, total, window).await?;
+            if self.bulk_control.is_cancelled() {
+                let finished_at = campaign::now_millis();
+                record.status = CampaignStatus::Cancelled;
+                record.finished_at = Some(finished_at);
+                let bulk_summary = BulkSendSummary::new(
+                    &summary,
+                    record.total + record.excluded.len(),
+                    true,
+                    &failed_messages,
+                    (record.started_at, finished_at),
+                    run_started.elapsed(),
+                    status_text::campaign_cancelled(index, total),
+                );
+                record.summary = Some(bulk_summary.clone());
+                self.campaigns.save(&record)?;
+                self.journal_status(&record);
+
+                let shutdown = self.run_completion_actions(
+                    &mut record,
+                    &options.completion_actions,
+                    CampaignEnd::Cancelled,
+                    &failed_messages,
+                    settings,
+                    window,
+                )?;
+                let cancelled = BulkCancelled {
+                    campaign_id: record.campaign_id.clone(),
+                    processed: index,
+                    remaining: total - index,
+                    total,
+                    status_text: status_text::campaign_cancelled(index, total),
+                };
+                self.emit(window, "whatsapp-bulk-cancelled", None, &cancelled)?;
+                // The same summary a finished run reports, with `cancelled` set
+                self.emit(window, "whatsapp-bulk-complete", None, &bulk_summary)?;
+                self.campaigns.remove_progress(&record.campaign_id)?;
+                if options.notify_supervisor {
+                    summary.outcome = SummaryOutcome::Cancelled;
+                    summary.duration_seconds = run_started.elapsed().as_secs();
+                    self.notify_supervisor(&summary, settings, window).await;
+                }
+                crate::background::show_admin_window(window.app_handle());
+                if shutdown {
+                    self.shut_down(window.app_handle());
+                }
+                return Ok(bulk_summary);
+            }
+
+            // Reached before the restart; counted, never sent again
+            let batch = batch?;
+            // The receipt goes with the first student the consent check lets through
+            if let Some(receipts) = &mut receipts {
+                receipts.prefetch(
+                    batches
+                        .upcoming()
+                        .filter_map(|upcoming| upcoming.iter().find(|student| options.campaign_kind.permits(student.consent)))
+                        .filter(|student| !already_sent.contains(&student.student_id)),
+                );
+            }
+            if !already_sent.is_empty() && batch.iter().all(|student| already_sent.contains(&student.student_id)) {
+                summary.sent += batch.len();
+                watchdog.progress(index + 1, batch.len(), 0);
+                continue;
+            }
+
+            let (students, refused): (Vec<StudentMessage>, Vec<StudentMessage>) = batch
+                .into_iter()
+                .partition(|student| options.campaign_kind.permits(student.consent));
+            if let Some(receipts) = &mut receipts {
+                refused.iter().for_each(|student| receipts.discard(&student.student_id));
+            }
+
+            summary.skipped += refused.len();
+            let refused_ids: Vec<String> = refused.iter().map(|s| s.student_id.clone()).collect();
+            for student in refused {
+                let progress = MessageProgress {
+                    status_text: status_text::message_progress(&student.name, "skipped_no_consent", None, index + 1, total),
+                    student_id: student.student_id,
+                    name: student.name,
+                    phone: student.phone,
+                    status: "skipped_no_consent".to_string(),
+                    error: None,
+                    error_kind: None,
+                    remediation: None,
+                    attempt: 0,
+                    processed: index + 1,
+                    total,
+                    campaign_id: record.campaign_id.clone(),
+                    demo_mode: record.demo_mode,
+                    rendered_message: None,
+                };
+                self.emit(window, "whatsapp-message-progress", None, &progress)?;
+            }
+            self.campaigns.append_progress(
+                &record.campaign_id,
+                refused_ids.iter().map(|student_id| StudentProgress {
+                    student_id: student_id.clone(),
+                    status: "skipped_no_consent".to_string(),
+                }),
+            )?;
+
+            // The first student of a shared phone carries the number and receipt
+            let Some(student) = students.first() else {
+                if let Some(trace) = &mut trace {
+                    trace.record_message(&students, refused_ids, None, None);
+                }
+                watchdog.progress(index + 1, 0, 0);
+                continue;
+            };
+            // Resolved here rather than at build time so `{today}` and
+            // `{days_overdue}` are right for a campaign sent days later
+            let clock = SendClock::now(settings.utc_offset_minutes);
+            let personalized_message = render::render_message(&options.message_template, &students, &clock);
+            if let Some(trace) = &mut trace {
+                trace.record_message(&students, refused_ids, Some(&clock), Some(&personalized_message));
+            }
+            if students.len() > 1 {
+                record.merged_messages.push(MergedMessage {
+                    student_ids: students.iter().map(|s| s.student_id.clone()).collect(),
+                });
+            }
+
+            let attachments = attachments::for_student(
+                &options.common_attachments,
                 student.receipt_path.as_ref(),
-            ).await;
+                options.attach_receipt && !options.lazy_receipts,
+            );
+            let campaign_id = record.campaign_id.clone();
+            let demo_mode = record.demo_mode;
+            let retrying = |attempt: u32, error: &SendError, delay: Duration| -> Result<(), String> {
+                watchdog.extend(delay);
+                for covered in &students {
+                    let progress = MessageProgress {
+                        student_id: covered.student_id.clone(),
+                        name: covered.name.clone(),
+                        phone: covered.phone.clone(),
+                        status: "retrying".to_string(),
+                        status_text: status_text::message_progress(&covered.name, "retrying", Some(error.kind), index, total),
+                        error: Some(error.message.clone()),
+                        error_kind: Some(error.kind),
+                        remediation: error.remediation,
+                        attempt,
+                        processed: index,
+                        total,
+                        campaign_id: campaign_id.clone(),
+                        demo_mode,
+                        rendered_message: None,
+                    };
+                    self.emit(window, "whatsapp-message-progress", None, &progress)?;
+                }
+                Ok(())
+            };
+            let (result, attempts) = self.send_with_retries(
+                student,
+                &personalized_message,
+                &attachments,
+                receipts.as_mut(),
+                settings,
+                &mut record,
+                (index, total),
+                window,
+                retrying,
+            ).await?;
 
-            let progress = MessageProgress {
-                student_id: student.student_id.clone(),
-                name: student.name.clone(),
-                phone: student.phone.clone(),
-                status: if result.is_ok() { "sent".to_string() } else { "failed".to_string() },
-                error: result.err(),
-                processed: index + 1,
-                total,
+            match &result {
+                Ok(()) => failures.record_success(),
+                Err(error) => failures.record_failure(error.kind),
+            }
+
+            let (error, error_kind, remediation) = match result {
+                Ok(()) => (None, None, None),
+                Err(error) => (Some(error.message), Some(error.kind), error.remediation),
             };
 
-            // Emit progress to frontend
-            window.emit("whatsapp-message-progress", &progress).map_err(|e| e.to_string())?;
+            // Emit progress to frontend, once for every student the message covered
+            let status = match (&error, attempts) {
+                (None, _) => "sent",
+                (Some(_), 1) => "failed",
+                (Some(_), _) => "failed_after_retries",
+            };
+            for covered in &students {
+                let progress = MessageProgress {
+                    student_id: covered.student_id.clone(),
+                    name: covered.name.clone(),
+                    phone: covered.phone.clone(),
+                    status: status.to_string(),
+                    status_text: status_text::message_progress(&covered.name, status, error_kind, index + 1, total),
+                    error: error.clone(),
+                    error_kind,
+                    remediation,
+                    attempt: attempts,
+                    processed: index + 1,
+                    total,
+                    campaign_id: record.campaign_id.clone(),
+                    demo_mode: record.demo_mode,
+                    rendered_message: Some(personalized_message.clone()),
+                };
+                self.emit(window, "whatsapp-message-progress", None, &progress)?;
+                if let Some(error) = &error {
+                    failed_messages.push(FailedMessage {
+                        student_id: covered.student_id.clone(),
+                        name: covered.name.clone(),
+                        phone: covered.phone.clone(),
+                        error_kind,
+                        error: error.clone(),
+                    });
+                }
+            }
+            self.campaigns.append_progress(
+                &record.campaign_id,
+                students.iter().map(|covered| StudentProgress {
+                    student_id: covered.student_id.clone(),
+                    status: status.to_string(),
+                }),
+            )?;
+            match error {
+                None => {
+                    summary.sent += students.len();
+                    watchdog.progress(index + 1, students.len(), 0);
+                }
+                Some(_) => {
+                    summary.failed += students.len();
+                    watchdog.progress(index + 1, 0, students.len());
+                }
+            }
+
+            // after_message failures are journaled but never fail the send
+            if error.is_none() {
+                let payload = serde_json::json!({
+                    "event": "after_message",
+                    "campaign_id": record.campaign_id,
+                    "student_id": student.student_id,
+                    "student_ids": students.iter().map(|s| s.student_id.as_str()).collect::<Vec<_>>(),
+                    "name": student.name,
+                    "phone": student.phone,
+                    "message": personalized_message,
+                });
+                self.run_campaign_hook(settings, HookEvent::AfterMessage, payload, Some(&student.student_id), &mut record).await?;
+            }
+
+            // Stop burning through the list when something is systematically wrong,
+            // e.g. WhatsApp logged out; the operator fixes it and resumes
+            let breach = failures.breached(
+                options.abort_on_failure_rate,
+                options.abort_after_consecutive_failures,
+            );
+            if let Some(reason) = breach {
+                if self.bulk_control.pause_for(failures.pause_reason()) {
+                    self.announce_pause_reasons(window.app_handle())?;
+                }
+                let notice = failures.notice(reason, total);
+                self.emit(window, "whatsapp-campaign-auto-paused", Some(&record.campaign_id), &notice)?;
+                crate::background::show_admin_window(window.app_handle());
+                if options.notify_supervisor {
+                    summary.outcome = SummaryOutcome::AutoPaused;
+                    summary.duration_seconds = run_started.elapsed().as_secs();
+                    self.notify_supervisor(&summary, settings, window).await;
+                }
+                self.wait_out_pause(&mut record, index + 1, total, window).await?;
+                failures.resumed();
+            }
+
+            // The watchdog, maintenance or the operator may have paused the run
+            if self.bulk_control.is_paused() {
+                self.wait_out_pause(&mut record, index + 1, total, window).await?;
+            }
 
             // Wait between messages to avoid rate limiting
             if index < total - 1 {
-                sleep(Duration::from_secs(request.interval_seconds)).await;
+                let interval = Duration::from_secs(options.interval_seconds);
+                let wait_started = SystemTime::now();
+                let paused = self.sleep_through_pauses(interval, &mut record, index + 1, total, window).await?;
+                if self.bulk_control.is_cancelled() {
+                    continue;
+                }
+
+                // A pause accounts for the overshoot itself
+                let suspended = match paused {
+                    true => None,
+                    false => resume::detect_suspend(wait_started, interval),
+                };
+                if let Some(suspended) = suspended {
+                    // A lid close during the wait makes the sleep return right
+                    // after wake-up, while WhatsApp is still reconnecting
+                    let settle = Duration::from_secs(
+                        options.resume_settle_seconds.unwrap_or(DEFAULT_RESUME_SETTLE_SECONDS),
+                    );
+                    watchdog.extend(settle * MAX_RESUME_CHECKS);
+                    self.settle_after_resume(suspended, settle, index + 1, total, window).await?;
+                    self.wait_out_pause(&mut record, index + 1, total, window).await?;
+                }
+
+                // The operator may have alt-tabbed away during the wait
+                let held = self.wait_for_focus(settings, &record.campaign_id, index + 1, total, window, &watchdog).await?;
+                if !held.is_zero() {
+                    focus_hold += held;
+                    let eta = EtaUpdate::project(
+                        &record.campaign_id,
+                        index + 1,
+                        total,
+                        run_started.elapsed().saturating_sub(focus_hold),
+                        focus_hold,
+                        campaign::now_millis(),
+                    );
+                    self.emit(window, "whatsapp-eta-updated", None, &eta)?;
+                }
+            }
+        }
+
+        let finished_at = campaign::now_millis();
+        let bulk_summary = BulkSendSummary::new(
+            &summary,
+            record.total + record.excluded.len(),
+            false,
+            &failed_messages,
+            (record.started_at, finished_at),
+            run_started.elapsed(),
+            status_text::campaign_complete(summary.sent, summary.failed, summary.skipped),
+        );
+        record.status = CampaignStatus::Finished;
+        record.finished_at = Some(finished_at);
+        record.summary = Some(bulk_summary.clone());
+        self.campaigns.save(&record)?;
+        self.journal_status(&record);
+        if let Some(trace) = &trace {
+            match trace.save(&self.trace_dir) {
+                Ok(path) => self.journal.transition(
+                    "trace_saved",
+                    Some(&record.campaign_id),
+                    serde_json::json!({ "path": path }),
+                ),
+                Err(e) => self.journal.transition(
+                    "trace_save_failed",
+                    Some(&record.campaign_id),
+                    serde_json::json!({ "error": e }),
+                ),
+            }
+        }
+
+        let payload = serde_json::json!({
+            "event": "after_campaign",
+            "campaign_id": record.campaign_id,
+            "total": record.total,
+            "finished_at": record.finished_at,
+        });
+        self.run_campaign_hook(settings, HookEvent::AfterCampaign, payload, None, &mut record).await?;
+
+        if options.notify_supervisor {
+            summary.outcome = SummaryOutcome::Finished;
+            summary.duration_seconds = run_started.elapsed().as_secs();
+            self.notify_supervisor(&summary, settings, window).await;
+        }
+
+        let shutdown = self.run_completion_actions(
+            &mut record,
+            &options.completion_actions,
+            CampaignEnd::Finished,
+            &failed_messages,
+            settings,
+            window,
+        )?;
+
+        self.emit(window, "whatsapp-bulk-complete", None, &bulk_summary)?;
+        self.campaigns.remove_progress(&record.campaign_id)?;
+        crate::background::show_admin_window(window.app_handle());
+        if shutdown {
+            self.shut_down(window.app_handle());
+        }
+        Ok(bulk_summary)
+    }
+
+    /// Renders every message and checks what would make it fail, with no
+    /// countdown, hooks, sending or waits; the campaign is finished with
+    /// the preview as its summary.
+    fn dry_run_campaign(
+        &self,
+        mut record: CampaignRecord,
+        options: &CampaignOptions,
+        settings: &AppSettings,
+        window: &Window,
+    ) -> Result<BulkSendSummary, String> {
+        // Never alongside a real send, whose pause and cancel it would share
+        let _run = self.bulk_control.try_start()
+            .ok_or_else(|| "A bulk send is already in progress".to_string())?;
+        record.started_at = campaign::now_millis();
+        record.demo_mode = settings.demo_mode;
+        let run_started = Instant::now();
+        let mut summary = CampaignSummary {
+            campaign_id: record.campaign_id.clone(),
+            name: record.name.clone(),
+            outcome: SummaryOutcome::Finished,
+            sent: 0,
+            failed: 0,
+            skipped: record.excluded.len(),
+            duration_seconds: 0,
+        };
+        let mut failed_messages = Vec::new();
+
+        let clock = SendClock::now(settings.utc_offset_minutes);
+        let (batches, total) = self.message_batches(&record, options, &settings.default_country)?;
+        for (index, batch) in batches.enumerate() {
+            let (students, refused): (Vec<StudentMessage>, Vec<StudentMessage>) = batch?
+                .into_iter()
+                .partition(|student| options.campaign_kind.permits(student.consent));
+
+            summary.skipped += refused.len();
+            for student in refused {
+                let progress = MessageProgress {
+                    status_text: status_text::message_progress(&student.name, "skipped_no_consent", None, index + 1, total),
+                    student_id: student.student_id,
+                    name: student.name,
+                    phone: student.phone,
+                    status: "skipped_no_consent".to_string(),
+                    error: None,
+                    error_kind: None,
+                    remediation: None,
+                    attempt: 0,
+                    processed: index + 1,
+                    total,
+                    campaign_id: record.campaign_id.clone(),
+                    demo_mode: record.demo_mode,
+                    rendered_message: None,
+                };
+                self.emit(window, "whatsapp-message-progress", None, &progress)?;
+            }
+            if students.is_empty() {
+                continue;
+            }
+
+            let rendered = render::render_message(&options.message_template, &students, &clock);
+            let problems = dry_run::problems(&rendered, &students, options, settings);
+            let error = (!problems.is_empty()).then(|| problems.join("; "));
+            let status = if error.is_none() { "previewed" } else { "would_fail" };
+            for covered in &students {
+                let progress = MessageProgress {
+                    student_id: covered.student_id.clone(),
+                    name: covered.name.clone(),
+                    phone: covered.phone.clone(),
+                    status: status.to_string(),
+                    status_text: status_text::message_progress(&covered.name, status, None, index + 1, total),
+                    error: error.clone(),
+                    error_kind: None,
+                    remediation: None,
+                    attempt: 0,
+                    processed: index + 1,
+                    total,
+                    campaign_id: record.campaign_id.clone(),
+                    demo_mode: record.demo_mode,
+                    rendered_message: Some(rendered.clone()),
+                };
+                self.emit(window, "whatsapp-message-progress", None, &progress)?;
+                if let Some(error) = &error {
+                    failed_messages.push(FailedMessage {
+                        student_id: covered.student_id.clone(),
+                        name: covered.name.clone(),
+                        phone: covered.phone.clone(),
+                        error_kind: None,
+                        error: error.clone(),
+                    });
+                }
+            }
+            match error {
+                None => summary.sent += students.len(),
+                Some(_) => summary.failed += students.len(),
+            }
+        }
+
+        let finished_at = campaign::now_millis();
+        let mut bulk_summary = BulkSendSummary::new(
+            &summary,
+            record.total + record.excluded.len(),
+            false,
+            &failed_messages,
+            (record.started_at, finished_at),
+            run_started.elapsed(),
+            status_text::dry_run_complete(summary.sent, summary.failed, summary.skipped),
+        );
+        bulk_summary.dry_run = true;
+        record.status = CampaignStatus::Finished;
+        record.finished_at = Some(finished_at);
+        record.summary = Some(bulk_summary.clone());
+        self.campaigns.save(&record)?;
+        self.journal_status(&record);
+        self.emit(window, "whatsapp-bulk-complete", None, &bulk_summary)?;
+        Ok(bulk_summary)
+    }
+
+    /// Runs the completion actions for how the campaign ended, in order,
+    /// recording each outcome. Returns whether one asked to quit the app,
+    /// which the caller does last.
+    fn run_completion_actions(
+        &self,
+        record: &mut CampaignRecord,
+        actions: &[CompletionAction],
+        end: CampaignEnd,
+        failures: &[FailedMessage],
+        settings: &AppSettings,
+        window: &Window,
+    ) -> Result<bool, String> {
+        let mut shutdown = false;
+        for action in actions.iter().filter(|action| action.runs_after(end)) {
+            let result = match &action.kind {
+                CompletionActionKind::ExportFailuresCsv { path } => {
+                    completion::export_failures_csv(Path::new(path), failures)
+                        .map(|detail| (CompletionOutcome::Succeeded, detail))
+                }
+                CompletionActionKind::ZipReceipts { path } => self
+                    .campaign_receipts(&record.campaign_id)
+                    .and_then(|receipts| completion::zip_receipts(Path::new(path), &receipts))
+                    .map(|detail| (CompletionOutcome::Succeeded, detail)),
+                CompletionActionKind::Notify { message } => {
+                    let notice = CompletionNotice {
+                        campaign_id: record.campaign_id.clone(),
+                        message: message.clone(),
+                    };
+                    self.emit(window, "whatsapp-completion-notice", None, &notice)?;
+                    crate::background::show_admin_window(window.app_handle());
+                    Ok((CompletionOutcome::Succeeded, "Shown to the operator".to_string()))
+                }
+                CompletionActionKind::Shutdown { if_no_failures } => {
+                    if *if_no_failures && !failures.is_empty() {
+                        Ok((CompletionOutcome::Skipped, format!("{} messages failed", failures.len())))
+                    } else if settings.demo_mode {
+                        Ok((CompletionOutcome::Skipped, "Demo mode is on".to_string()))
+                    } else {
+                        shutdown = true;
+                        Ok((CompletionOutcome::Succeeded, "App quits once the campaign is saved".to_string()))
+                    }
+                }
+            };
+
+            let run = CompletionActionRun::new(&action.kind, result);
+            self.journal.transition(
+                "completion_action",
+                Some(&record.campaign_id),
+                serde_json::to_value(&run).unwrap_or_default(),
+            );
+            record.completion_runs.push(run);
+            self.campaigns.save(record)?;
+        }
+        Ok(shutdown)
+    }
+
+    fn campaign_receipts(&self, campaign_id: &str) -> Result<Vec<(String, PathBuf)>, String> {
+        let mut receipts = Vec::new();
+        for student in self.campaigns.students(campaign_id)? {
+            let student = student?;
+            if let Some(path) = student.receipt_path {
+                receipts.push((student.student_id, PathBuf::from(path)));
             }
         }
+        Ok(receipts)
+    }
+
+    /// Quits the app for a completion `Shutdown`. Campaign records are
+    /// saved as they change, so only the journal queue needs draining.
+    fn shut_down(&self, app: &AppHandle) {
+        self.journal.transition("shutdown", None, serde_json::Value::Null);
+        if !self.journal.flush(SHUTDOWN_JOURNAL_FLUSH) {
+            // Written only if the writer catches up before the exit
+            self.journal.transition("shutdown_flush_timed_out", None, serde_json::Value::Null);
+        }
+        app.exit(0);
+    }
 
-        window.emit("whatsapp-bulk-complete", &()).map_err(|e| e.to_string())?;
+    /// Quits mid-campaign from the close prompt. The run loop won't reach
+    /// its cancel branch before the app exits, so the record is marked
+    /// `Cancelled` here, the way that branch leaves it.
+    pub fn cancel_and_quit(&self, app: &AppHandle) -> Result<(), String> {
+        let campaign_id = self.sending_campaign();
+        self.cancel_bulk_send()?;
+        if let Some(campaign_id) = campaign_id {
+            let mut record = self.campaigns.load(&campaign_id)?;
+            record.status = CampaignStatus::Cancelled;
+            record.finished_at = Some(campaign::now_millis());
+            self.campaigns.save(&record)?;
+            self.journal_status(&record);
+            self.campaigns.remove_progress(&campaign_id)?;
+        }
+        self.shut_down(app);
         Ok(())
     }
 
+    /// Renders the first `limit` messages of a campaign the way the run
+    /// will send them, shared phones merged.
+    pub fn preview_campaign(
+        &self,
+        campaign_id: &str,
+        limit: usize,
+        settings: &AppSettings,
+    ) -> Result<Vec<MessagePreview>, String> {
+        let record = self.campaigns.load(campaign_id)?;
+        let mut options = record.options.clone()
+            .ok_or_else(|| format!("Campaign {} has no send options", campaign_id))?;
+        options.message_template = tokens::resolve_aliases(&options.message_template, &settings.token_aliases);
+        let (batches, _) = self.message_batches(&record, &options, &settings.default_country)?;
+
+        let clock = SendClock::now(settings.utc_offset_minutes);
+        let mut previews = Vec::new();
+        for batch in batches.take(limit) {
+            let students: Vec<StudentMessage> = batch?
+                .into_iter()
+                .filter(|student| options.campaign_kind.permits(student.consent))
+                .collect();
+            let Some(first) = students.first() else {
+                continue;
+            };
+
+            let mut dynamic_values = clock.values_for(first);
+            dynamic_values.retain(|token, _| dynamic::is_used_in(&options.message_template, token));
+            previews.push(MessagePreview {
+                student_ids: students.iter().map(|s| s.student_id.clone()).collect(),
+                name: first.name.clone(),
+                phone: first.phone.clone(),
+                message: render::render_message(&options.message_template, &students, &clock),
+                dynamic_values,
+                as_of: clock.as_of,
+            });
+        }
+        Ok(previews)
+    }
+
+    /// Renames `{old}` to `{new}` in every draft and unsent campaign; only
+    /// reports what would change unless `apply`. Applying writes a backup
+    /// of the original bodies first and puts them back if a write fails.
+    pub fn rename_token(&self, old: &str, new: &str, apply: bool) -> Result<TokenRename, String> {
+        tokens::validate_name(old)?;
+        tokens::validate_name(new)?;
+        if old == new {
+            return Err("The new token name is the same as the old one".to_string());
+        }
+
+        let mut changes = Vec::new();
+        let mut drafts = Vec::new();
+        for draft in self.drafts.load_all() {
+            if let Some(after) = tokens::rename_in(&draft.options.message_template, old, new) {
+                changes.push(TemplateChange {
+                    source: TemplateSource::Draft,
+                    id: draft.draft_id.clone().unwrap_or_default(),
+                    name: draft.options.name.clone(),
+                    before: draft.options.message_template.clone(),
+                    after,
+                });
+                drafts.push(draft);
+            }
+        }
+        let mut records = Vec::new();
+        for record in self.campaigns.list(None, None)? {
+            if !matches!(record.status, CampaignStatus::Building | CampaignStatus::PendingStart) {
+                continue;
+            }
+            let Some(options) = &record.options else {
+                continue;
+            };
+            if let Some(after) = tokens::rename_in(&options.message_template, old, new) {
+                changes.push(TemplateChange {
+                    source: TemplateSource::Campaign,
+                    id: record.campaign_id.clone(),
+                    name: record.name.clone(),
+                    before: options.message_template.clone(),
+                    after,
+                });
+                records.push(record);
+            }
+        }
+
+        let mut rename = TokenRename {
+            old: old.to_string(),
+            new: new.to_string(),
+            applied: false,
+            changes,
+            backup_path: None,
+        };
+        if !apply {
+            return Ok(rename);
+        }
+
+        if !rename.changes.is_empty() {
+            rename.backup_path = Some(tokens::write_backup(&self.token_rename_dir, &rename)?);
+        }
+        for (index, change) in rename.changes.iter().enumerate() {
+            if let Err(e) = self.write_template(&mut drafts, &mut records, index, &change.after) {
+                for (undo, change) in rename.changes.iter().enumerate().take(index) {
+                    let _ = self.write_template(&mut drafts, &mut records, undo, &change.before);
+                }
+                return Err(format!("Failed to rename token, templates left as they were: {}", e));
+            }
+        }
+        rename.applied = true;
+        self.journal.transition(
+            "token_renamed",
+            None,
+            serde_json::json!({ "old": old, "new": new, "changed": rename.changes.len() }),
+        );
+        Ok(rename)
+    }
+
+    /// Saves `template` on the `index`th template found by `rename_token`:
+    /// drafts first, then campaigns.
+    fn write_template(
+        &self,
+        drafts: &mut [CampaignDraft],
+        records: &mut [CampaignRecord],
+        index: usize,
+        template: &str,
+    ) -> Result<(), String> {
+        let draft_count = drafts.len();
+        match drafts.get_mut(index) {
+            Some(draft) => {
+                draft.options.message_template = template.to_string();
+                self.drafts.replace(draft)
+            }
+            None => {
+                let record = &mut records[index - draft_count];
+                if let Some(options) = &mut record.options {
+                    options.message_template = template.to_string();
+                }
+                self.campaigns.save(record)
+            }
+        }
+    }
+
+    pub fn get_campaign_detail(&self, campaign_id: &str) -> Result<CampaignRecord, String> {
+        self.campaigns.load(campaign_id)
+    }
+
+    /// The full number of a campaign recipient, whatever the masking; every
+    /// reveal is journaled.
+    pub fn reveal_phone(&self, campaign_id: &str, student_id: &str) -> Result<String, String> {
+        let student = self
+            .campaigns
+            .students(campaign_id)?
+            .filter_map(Result::ok)
+            .find(|student| student.student_id == student_id)
+            .ok_or_else(|| format!("Student {} is not in campaign {}", student_id, campaign_id))?;
+        self.journal.transition("phone_revealed", Some(campaign_id), serde_json::json!({ "student_id": student_id }));
+        Ok(student.phone)
+    }
+
+    pub fn list_campaigns(&self, label: Option<&str>, search: Option<&str>) -> Result<Vec<CampaignRecord>, String> {
+        metrics::time(Stage::ListCampaigns, || self.campaigns.list(label, search))
+    }
+
+    pub fn save_campaign_draft(&self, draft: CampaignDraft, retention: DraftRetention) -> Result<CampaignDraft, String> {
+        self.drafts.save(draft, retention)
+    }
+
+    pub fn list_campaign_drafts(&self, retention: DraftRetention) -> Result<Vec<DraftSummary>, String> {
+        metrics::time(Stage::ListDrafts, || self.drafts.list(retention))
+    }
+
+    pub fn load_campaign_draft(&self, draft_id: &str) -> Result<CampaignDraft, String> {
+        self.drafts.load(draft_id)
+    }
+
+    pub fn delete_campaign_draft(&self, draft_id: &str) -> Result<(), String> {
+        self.drafts.delete(draft_id)
+    }
+
+    /// Copies a campaign's options and recipients into a new campaign that
+    /// is left building, for pre-flight and finalizing like any other.
+    /// Outcomes of the original run are not carried over.
+    pub fn clone_campaign(&self, campaign_id: &str, overrides: CloneOverrides) -> Result<CampaignRecord, String> {
+        let original = self.campaigns.load(campaign_id)?;
+        let mut options = original.options.clone()
+            .ok_or_else(|| format!("Campaign {} has no send options to clone", campaign_id))?;
+        options.name = overrides.name.or(original.name);
+        options.label = original.label;
+        options.notes = original.notes;
+        options.draft_id = None;
+        if let Some(template) = overrides.message_template {
+            options.message_template = template;
+        }
+        if let Some(interval) = overrides.interval_seconds {
+            options.interval_seconds = interval;
+        }
+        if let Some(dry_run) = overrides.dry_run {
+            options.dry_run = dry_run;
+        }
+
+        let mut record = CampaignRecord::new(options);
+        record.exclusions = original.exclusions;
+        self.campaigns.save(&record)?;
+        self.campaigns.append_students(&record.campaign_id, &[])?;
+
+        let mut chunk = Vec::with_capacity(CLONE_CHUNK_SIZE);
+        for student in self.campaigns.students(campaign_id)? {
+            chunk.push(student?);
+            if chunk.len() == CLONE_CHUNK_SIZE {
+                self.campaigns.append_students(&record.campaign_id, &chunk)?;
+                record.total += chunk.len();
+                chunk.clear();
+            }
+        }
+        self.campaigns.append_students(&record.campaign_id, &chunk)?;
+        record.total += chunk.len();
+        self.campaigns.save(&record)?;
+        Ok(record)
+    }
+
+    pub fn update_campaign_meta(
+        &self,
+        campaign_id: &str,
+        name: Option<String>,
+        label: Option<String>,
+        notes: Option<String>,
+    ) -> Result<CampaignRecord, String> {
+        // The running send rewrites its record after every student
+        if self.bulk_control.campaign_id().as_deref() == Some(campaign_id) {
+            return Err("Campaign is still running; edit it once it has finished".to_string());
+        }
+
+        let mut record = self.campaigns.load(campaign_id)?;
+        record.name = name;
+        record.label = label;
+        record.notes = notes;
+        self.campaigns.save(&record)?;
+        Ok(record)
+    }
+
+    /// Sends a finished campaign's message to one student again, e.g. a
+    /// failed row picked in the detail view. The text that failed is sent
+    /// as it was, or rendered again for records that didn't keep it; a
+    /// message merged for a shared phone goes again to all its students.
+    /// One attempt, refused while a bulk send runs. The run's failures stay
+    /// in `retry_journal`; a successful re-send only moves the students
+    /// from failed to sent in the summary.
+    pub async fn resend_campaign_message(
+        &self,
+        campaign_id: &str,
+        student_id: &str,
+        settings: &AppSettings,
+    ) -> Result<ResendAttempt, String> {
+        let _run = self.bulk_control.try_start()
+            .ok_or_else(|| "A bulk send is in progress; re-send once it has finished".to_string())?;
+        self.bulk_control.set_campaign_id(campaign_id);
+
+        let mut record = self.campaigns.load(campaign_id)?;
+        if !matches!(record.status, CampaignStatus::Finished | CampaignStatus::Cancelled) {
+            return Err("Campaign hasn't finished; only messages of ended campaigns can be re-sent".to_string());
+        }
+        let options = record.options.clone()
+            .ok_or_else(|| format!("Campaign {} has no send options", campaign_id))?;
+
+        let covered_ids: Vec<String> = record
+            .merged_messages
+            .iter()
+            .find(|merged| merged.student_ids.iter().any(|id| id == student_id))
+            .map(|merged| merged.student_ids.clone())
+            .unwrap_or_else(|| vec![student_id.to_string()]);
+        let mut students: Vec<StudentMessage> = self
+            .campaigns
+            .students(campaign_id)?
+            .filter_map(Result::ok)
+            .filter(|student| covered_ids.contains(&student.student_id))
+            .collect();
+        students.sort_by_key(|student| covered_ids.iter().position(|id| *id == student.student_id));
+        let Some(student) = students.first() else {
+            return Err(format!("Student {} is not in campaign {}", student_id, campaign_id));
+        };
+
+        let message = match record
+            .retry_journal
+            .iter()
+            .rev()
+            .filter(|entry| covered_ids.contains(&entry.student_id))
+            .find_map(|entry| entry.message.clone())
+        {
+            Some(message) => message,
+            None => {
+                let template = tokens::resolve_aliases(&options.message_template, &settings.token_aliases);
+                render::render_message(&template, &students, &SendClock::now(settings.utc_offset_minutes))
+            }
+        };
+        let attachments = attachments::for_student(
+            &options.common_attachments,
+            student.receipt_path.as_ref(),
+            options.attach_receipt && !options.lazy_receipts,
+        );
+        let mut receipts = match options.attach_receipt && options.lazy_receipts {
+            true => Some(LazyReceipts::new(
+                settings
+                    .receipt_generator
+                    .clone()
+                    .ok_or_else(|| "lazy_receipts needs a receipt_generator in settings".to_string())?,
+                campaign_id,
+            )),
+            false => None,
+        };
+        let attempt = record
+            .retry_journal
+            .iter()
+            .filter(|entry| covered_ids.contains(&entry.student_id))
+            .map(|entry| entry.attempt)
+            .chain(
+                record
+                    .resends
+                    .iter()
+                    .filter(|resend| resend.student_ids.iter().any(|id| covered_ids.contains(id)))
+                    .map(|resend| resend.attempt),
+            )
+            .max()
+            .unwrap_or(1)
+            + 1;
+
+        let result = self.send_with_receipt(student, &message, &attachments, receipts.as_mut(), settings).await;
+        let at = campaign::now_millis();
+        if let Err(error) = &result {
+            record.retry_journal.push(RetryJournalEntry {
+                student_id: student.student_id.clone(),
+                attempt,
+                error_kind: error.kind,
+                error: error.message.clone(),
+                remediation: error.remediation,
+                decision: RetryDecision::GiveUp {
+                    reason: "Manual re-send".to_string(),
+                },
+                at,
+                message: Some(message.clone()),
+            });
+        } else if let Some(summary) = &mut record.summary {
+            let before = summary.failures.len();
+            summary.failures.retain(|failure| !covered_ids.contains(&failure.student_id));
+            let recovered = before - summary.failures.len();
+            summary.failed -= recovered;
+            summary.sent += recovered;
+        }
+
+        let resend = ResendAttempt {
+            student_ids: covered_ids,
+            attempt,
+            status: if result.is_ok() { "sent" } else { "failed" }.to_string(),
+            error: result.err().map(|error| error.message),
+            at,
+        };
+        record.resends.push(resend.clone());
+        self.campaigns.save(&record)?;
+        self.journal.transition(
+            "message_resent",
+            Some(campaign_id),
+            serde_json::to_value(&resend).unwrap_or_default(),
+        );
+        Ok(resend)
+    }
+
+    /// Sends one message, retrying according to the policy for the kind of
+    /// failure. Every failed attempt is journaled on the campaign record.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_with_retries(
+        &self,
+        student: &StudentMessage,
+        message: &str,
+        attachments: &[Attachment],
+        mut receipts: Option<&mut LazyReceipts>,
+        settings: &AppSettings,
+        record: &mut CampaignRecord,
+        (processed, total): (usize, usize),
+        window: &Window,
+        retrying: impl Fn(u32, &SendError, Duration) -> Result<(), String>,
+    ) -> Result<(Result<(), SendError>, u32), String> {
+        let mut attempt = 1;
+        loop {
+            let error = match self.send_with_receipt(
+                student,
+                message,
+                attachments,
+                receipts.as_deref_mut(),
+                settings,
+            ).await {
+                Ok(()) => return Ok((Ok(()), attempt)),
+                Err(error) => error,
+            };
+
+            let mut policy = retry::policy_for(error.kind, &settings.retry_policies);
+            if let Some(options) = &record.options {
+                policy = policy.for_campaign(options);
+            }
+            let decision = policy.decide(attempt);
+            let delay = decision.delay();
+            record.retry_journal.push(RetryJournalEntry {
+                student_id: student.student_id.clone(),
+                attempt,
+                error_kind: error.kind,
+                error: error.message.clone(),
+                remediation: error.remediation,
+                decision,
+                at: campaign::now_millis(),
+                message: Some(message.to_string()),
+            });
+            self.campaigns.save(record)?;
+            if let Some(entry) = record.retry_journal.last() {
+                self.journal.transition(
+                    "send_attempt_failed",
+                    Some(&record.campaign_id),
+                    serde_json::to_value(entry).unwrap_or_default(),
+                );
+            }
+
+            let Some(delay) = delay else {
+                return Ok((Err(error), attempt));
+            };
+            retrying(attempt, &error, delay)?;
+            self.sleep_through_pauses(delay, record, processed, total, window).await?;
+            if self.bulk_control.is_cancelled() {
+                return Ok((Err(error), attempt));
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Sends one message with the student's lazily generated receipt after
+    /// `attachments`, when the campaign has them.
+    async fn send_with_receipt(
+        &self,
+        student: &StudentMessage,
+        message: &str,
+        attachments: &[Attachment],
+        receipts: Option<&mut LazyReceipts>,
+        settings: &AppSettings,
+    ) -> Result<(), SendError> {
+        let Some(receipts) = receipts else {
+            return self.send_individual_message(&student.phone, message, attachments, settings).await;
+        };
+        let receipt = receipts.take(student).await?;
+        let mut attachments = attachments.to_vec();
+        attachments.push(Attachment {
+            path: receipt.to_string_lossy().into_owned(),
+            caption: None,
+        });
+        self.send_individual_message(&student.phone, message, &attachments, settings).await
+    }
+
+    /// Sends the outcome of a campaign to `supervisor_number`. One attempt,
+    /// outside the campaign's retries, hooks and auto-pause, so a failed
+    /// summary never leads to another summary; the outcome is emitted as
+    /// `whatsapp-supervisor-notified`. Never fails the campaign.
+    async fn notify_supervisor(&self, summary: &CampaignSummary, settings: &AppSettings, window: &Window) {
+        let phone = settings
+            .supervisor_number
+            .as_deref()
+            .and_then(|number| crate::phone::normalize_to_e164(number, &settings.default_country));
+
+        let error = match phone {
+            None => Some("No valid supervisor_number configured".to_string()),
+            Some(phone) => {
+                let clock = SendClock::now(settings.utc_offset_minutes);
+                let message = summary.render(&settings.supervisor_summary_template, &phone, &clock);
+                self.send_individual_message(&phone, &message, &[], settings)
+                    .await
+                    .err()
+                    .map(|error| error.message)
+            }
+        };
+
+        let notified = SupervisorNotified {
+            campaign_id: summary.campaign_id.clone(),
+            outcome: summary.outcome,
+            error,
+        };
+        if let Err(e) = self.emit(window, "whatsapp-supervisor-notified", None, &notified) {
+            self.journal.transition(
+                "supervisor_notice_undelivered",
+                Some(&summary.campaign_id),
+                serde_json::json!({ "error": e }),
+            );
+        }
+    }
+
+    /// Holds the run while WhatsApp reconnects after the machine slept. If
+    /// it doesn't come back the run pauses for the operator instead of
+    /// failing.
+    async fn settle_after_resume(
+        &self,
+        suspended: Duration,
+        settle: Duration,
+        processed: usize,
+        total: usize,
+        window: &Window,
+    ) -> Result<(), String> {
+        let notice = SystemResumeNotice {
+            suspended_seconds: suspended.as_secs(),
+            settle_seconds: settle.as_secs(),
+            processed,
+            total,
+        };
+        self.emit(window, "campaign-paused-system-resume", self.bulk_control.campaign_id().as_deref(), &notice)?;
+
+        self.set_hold(window, PauseReason::SystemResume, true)?;
+        match self.wait_for_whatsapp_after_resume(settle).await {
+            Ok(()) => self.set_hold(window, PauseReason::SystemResume, false),
+            Err(reason) => {
+                self.journal.transition(
+                    "system_resume_unsettled",
+                    self.bulk_control.campaign_id().as_deref(),
+                    serde_json::json!({ "reason": reason }),
+                );
+                self.bulk_control.pause_for(PauseReason::SystemResume);
+                self.announce_pause_reasons(window.app_handle())
+            }
+        }
+    }
+
+    /// Gives up early, as settled, when the run is paused or cancelled
+    /// meanwhile; the caller handles that.
+    async fn wait_for_whatsapp_after_resume(&self, settle: Duration) -> Result<(), String> {
+        for _ in 0..MAX_RESUME_CHECKS {
+            self.bulk_control.sleep_interruptibly(settle).await;
+            if self.bulk_control.is_paused() || self.bulk_control.is_cancelled() {
+                return Ok(());
+            }
+
+            if !self.is_connected() {
+                return Err("WhatsApp session disconnected during system sleep".to_string());
+            }
+
+            if crate::check_whatsapp_desktop().await.unwrap_or(false) {
+                return Ok(());
+            }
+        }
+
+        Err("WhatsApp did not become available after system resume".to_string())
+    }
+
+    /// The messages of a campaign and how many there are. With
+    /// `merge_shared_phone`, students sharing a phone become one message and
+    /// the list is grouped in memory; otherwise students are streamed from
+    /// disk one per message.
+    fn message_batches(
+        &self,
+        record: &CampaignRecord,
+        options: &CampaignOptions,
+        default_country: &str,
+    ) -> Result<(MessageBatches, usize), String> {
+        let students = self.campaigns.students(&record.campaign_id)?;
+        let ordering = options.ordering.clone().unwrap_or_default();
+        if !options.merge_shared_phone && ordering == OrderingStrategy::InputOrder {
+            return Ok((Box::new(students.map(|student| student.map(|s| vec![s]))), record.total));
+        }
+
+        // Reordering needs every message in memory, as merging does
+        let students = students.collect::<Result<Vec<_>, String>>()?;
+        let groups = if options.merge_shared_phone {
+            render::group_by_phone(students, default_country)
+        } else {
+            students.into_iter().map(|student| vec![student]).collect()
+        };
+        let groups = ordering.apply(groups);
+        let total = groups.len();
+        Ok((Box::new(groups.into_iter().map(Ok)), total))
+    }
+
+    /// Runs the user's hook for `event`, if one is configured, and journals
+    /// the run on the campaign record. Demo runs never execute hooks.
+    async fn run_campaign_hook(
+        &self,
+        settings: &AppSettings,
+        event: HookEvent,
+        payload: serde_json::Value,
+        student_id: Option<&str>,
+        record: &mut CampaignRecord,
+    ) -> Result<Option<HookRun>, String> {
+        if settings.demo_mode {
+            return Ok(None);
+        }
+        let command = match settings.hooks.command_for(event) {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+
+        let run = hooks::run_hook(command, event, &payload, student_id).await;
+        record.hook_runs.push(run.clone());
+        self.campaigns.save(record)?;
+        Ok(Some(run))
+    }
+
+    /// Holds the next send while WhatsApp isn't the foreground window and
+    /// returns how long it held.
+    async fn wait_for_focus(
+        &self,
+        settings: &AppSettings,
+        campaign_id: &str,
+        processed: usize,
+        total: usize,
+        window: &Window,
+        watchdog: &SendWatchdog,
+    ) -> Result<Duration, String> {
+        if settings.demo_mode || !settings.hold_until_focused
+            || crate::desktop::is_whatsapp_foreground() != Some(false)
+        {
+            return Ok(Duration::ZERO);
+        }
+
+        self.set_hold(window, PauseReason::WrongFocus, true)?;
+        let held = self.hold_until_focused(settings, campaign_id, processed, total, window, watchdog).await;
+        self.set_hold(window, PauseReason::WrongFocus, false)?;
+        if let Ok(held) = &held {
+            metrics::record(Stage::FocusWait, *held);
+        }
+        held
+    }
+
+    async fn hold_until_focused(
+        &self,
+        settings: &AppSettings,
+        campaign_id: &str,
+        processed: usize,
+        total: usize,
+        window: &Window,
+        watchdog: &SendWatchdog,
+    ) -> Result<Duration, String> {
+        let started = Instant::now();
+        let refocus_after = settings.auto_refocus_after_seconds.map(Duration::from_secs);
+        let mut refocus_attempted = false;
+        let mut next_notice = Duration::ZERO;
+
+        loop {
+            let waited = started.elapsed();
+            if waited >= next_notice {
+                let notice = WaitingForFocus {
+                    campaign_id: campaign_id.to_string(),
+                    processed,
+                    total,
+                    waited_seconds: waited.as_secs(),
+                    refocus_attempted,
+                };
+                self.emit(window, "whatsapp-waiting-for-focus", None, &notice)?;
+                next_notice += FOCUS_NOTICE_INTERVAL;
+            }
+
+            if !refocus_attempted && refocus_after.is_some_and(|after| waited >= after) {
+                focus::focus_whatsapp_window()?;
+                refocus_attempted = true;
+            }
+
+            // Holding for focus is deliberate, not a stall
+            watchdog.extend(FOCUS_POLL_INTERVAL);
+            sleep(FOCUS_POLL_INTERVAL).await;
+            if self.bulk_control.is_cancelled() || crate::desktop::is_whatsapp_foreground() != Some(false) {
+                return Ok(started.elapsed());
+            }
+        }
+    }
+
+    /// Opens the chat with the text filled in and presses Enter, then
+    /// pastes and sends each attachment with its caption. Everything goes
+    /// through the input simulator, so demo mode reaches nothing outside
+    /// the app.
     async fn send_individual_message(
         &self,
         phone: &str,
         message: &str,
-        receipt_path: Option<&String>,
+        attachments: &[Attachment],
+        settings: &AppSettings,
+    ) -> Result<(), SendError> {
+        // Deeplinks take the number as digits, country code first
+        let phone = check_phone(phone, &settings.default_country)?;
+        let url = build_send_url(crate::desktop::active_variant(), phone.trim_start_matches('+'), message)
+            .map_err(SendError::classified)?
+            .url;
+        let simulator = input::simulator();
+        simulator.open_url(&url).map_err(SendError::classified)?;
+
+        // Enter on Windows' app picker would change the default app
+        if !settings.demo_mode {
+            sleep(CHAT_LOAD_DELAY).await;
+            deeplink::check_open_with_dialog(settings.close_open_with_dialog)?;
+        }
+        simulator.press_key(Key::Enter).map_err(SendError::classified)?;
+
+        for attachment in attachments {
+            simulator.copy_file(&attachment.path).map_err(SendError::classified)?;
+            simulator.press_key(Key::Paste).map_err(SendError::classified)?;
+            if !settings.demo_mode {
+                sleep(attachments::PREVIEW_DELAY).await;
+            }
+            if let Some(caption) = attachment.caption.as_deref().filter(|caption| !caption.is_empty()) {
+                simulator.type_text(caption).map_err(SendError::classified)?;
+            }
+            simulator.press_key(Key::Enter).map_err(SendError::classified)?;
+            if !settings.demo_mode {
+                sleep(attachments::UPLOAD_DELAY).await;
+            }
+        }
+        if let Some(layout) = input::layout_change() {
+            self.journal.transition(
+                "keyboard_layout_changed",
+                self.bulk_control.campaign_id().as_deref(),
+                serde_json::json!({ "layout": layout }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Stops the running send before its next message, or a campaign still
+    /// in its countdown.
+    pub fn cancel_bulk_send(&self) -> Result<(), String> {
+        if self.bulk_control.abort_pending() {
+            return Ok(());
+        }
+        if !self.bulk_control.cancel() {
+            return Err("No bulk send to cancel".to_string());
+        }
+        self.journal.transition("cancel_requested", self.bulk_control.campaign_id().as_deref(), serde_json::Value::Null);
+        Ok(())
+    }
+
+    /// Resumes a paused run. Refused while WhatsApp is still unreachable
+    /// or maintenance still on, if either paused it, unless `force`.
+    pub fn resume_bulk_send(&self, force: bool, app: &AppHandle) -> Result<(), String> {
+        if !self.bulk_control.is_running() || !self.bulk_control.is_paused() {
+            return Err("No paused bulk send to resume".to_string());
+        }
+
+        let holding: Vec<PauseReason> = self
+            .bulk_control
+            .pause_reasons()
+            .into_iter()
+            .filter(|reason| reason.is_blocking() && self.still_holds(*reason))
+            .collect();
+        if !holding.is_empty() && !force {
+            return Err(format!("Campaign is still blocked by {:?}; pass force to resume anyway", holding));
+        }
+
+        self.bulk_control.resume();
+        self.journal.transition(
+            "resumed",
+            self.bulk_control.campaign_id().as_deref(),
+            serde_json::json!({ "forced": force, "overridden": holding }),
+        );
+        self.announce_pause_reasons(app)
+    }
+
+    fn still_holds(&self, reason: PauseReason) -> bool {
+        match reason {
+            PauseReason::Maintenance => crate::maintenance::state().active,
+            PauseReason::Disconnected => !self.is_connected() || !crate::desktop::is_whatsapp_running(),
+            _ => false,
+        }
+    }
+
+    /// The campaign being sent, with what is pausing it right now.
+    pub fn get_active_campaign(&self) -> Result<Option<CampaignRecord>, String> {
+        let Some(campaign_id) = self.sending_campaign() else {
+            return Ok(None);
+        };
+        let mut record = self.campaigns.load(&campaign_id)?;
+        record.pause_reasons = self.bulk_control.pause_reasons();
+        Ok(Some(record))
+    }
+
+    pub fn list_exclusion_lists(&self) -> BTreeMap<String, Vec<String>> {
+        self.exclusion_lists.list()
+    }
+
+    pub fn save_exclusion_list(&self, name: &str, members: Vec<String>) -> Result<(), String> {
+        self.exclusion_lists.save(name, members)
+    }
+
+    pub fn delete_exclusion_list(&self, name: &str) -> Result<(), String> {
+        self.exclusion_lists.delete(name)
+    }
+
+    /// Cancels a campaign during its confirmation countdown; it goes back
+    /// to building.
+    pub fn abort_pending_campaign(&self, campaign_id: &str) -> Result<(), String> {
+        if self.bulk_control.campaign_id().as_deref() != Some(campaign_id) || !self.bulk_control.abort_pending() {
+            return Err(format!("Campaign {} is not waiting to start", campaign_id));
+        }
+        self.journal.transition("abort_requested", Some(campaign_id), serde_json::Value::Null);
+        Ok(())
+    }
+
+    pub fn pause_for_maintenance(&self, app: &AppHandle) -> Result<bool, String> {
+        let paused = self.bulk_control.pause_for_maintenance();
+        if paused {
+            self.journal.transition("paused_for_maintenance", self.bulk_control.campaign_id().as_deref(), serde_json::Value::Null);
+            self.announce_pause_reasons(app)?;
+        }
+        Ok(paused)
+    }
+
+    pub fn resume_after_maintenance(&self, app: &AppHandle) -> Result<(), String> {
+        if self.bulk_control.resume_after_maintenance() {
+            self.journal.transition("resumed_after_maintenance", self.bulk_control.campaign_id().as_deref(), serde_json::Value::Null);
+            self.announce_pause_reasons(app)?;
+        }
+        Ok(())
+    }
+
+    /// Replays a trace recorded with `record_campaign_trace`; see
+    /// `CampaignTrace::replay`.
+    pub fn replay_campaign_trace(&self, path: &std::path::Path) -> Result<TraceReplay, String> {
+        Ok(CampaignTrace::load(path)?.replay())
+    }
+
+    /// Writes one campaign's journal entries to `destination`, for a
+    /// support ticket; returns how many were written.
+    pub fn export_event_journal(
+        &self,
+        campaign_id: &str,
+        destination: &std::path::Path,
+        task: &crate::tasks::TaskHandle,
+    ) -> Result<usize, String> {
+        self.journal.export(campaign_id, destination, task)
+    }
+
+    pub fn estimate_message_log_rows(&self) -> usize {
+        self.journal.estimate_message_log_rows()
+    }
+
+    pub fn export_message_log(&self, export: &MessageLogExport, task: &crate::tasks::TaskHandle) -> Result<usize, String> {
+        self.journal.export_message_log(export, task)
+    }
+
+    /// Bytes the journal takes up, for sizing an export before it starts.
+    pub fn journal_bytes(&self) -> u64 {
+        self.journal.size()
+    }
+
+    /// How much of the app data each category takes up, and what's left on
+    /// its volume.
+    pub fn storage_usage(&self) -> StorageUsage {
+        let categories = [
+            (StorageCategory::Campaigns, self.campaigns.dir()),
+            (StorageCategory::Journal, self.journal.dir()),
+            (StorageCategory::Traces, self.trace_dir.as_path()),
+            (StorageCategory::TokenRenames, self.token_rename_dir.as_path()),
+            (StorageCategory::Drafts, self.drafts.dir()),
+            (StorageCategory::Heartbeat, self.heartbeat_path.as_path()),
+            (StorageCategory::ExclusionLists, self.exclusion_lists.path()),
+        ];
+        let available = crate::resources::available_space(self.campaigns.dir());
+        StorageUsage::measure(&categories, available)
+    }
+
+    /// Deletes the generated files `settings.retention` and the draft
+    /// limits no longer keep, journaling each one, and emits
+    /// `maintenance-report`; `only` limits it to one artifact. Traces of
+    /// campaigns still in the message log are kept, and so is the
+    /// heartbeat of a running campaign.
+    pub fn run_maintenance(
+        &self,
+        app: &AppHandle,
+        settings: &AppSettings,
+        only: Option<Artifact>,
+    ) -> Result<MaintenanceReport, String> {
+        let retention = &settings.retention;
+        let cleans = |artifact: Artifact| only.is_none_or(|only| only == artifact);
+        let now = now_millis();
+        let mut report = MaintenanceReport::new(now);
+
+        if cleans(Artifact::Journal) {
+            let journals = self.journal.rotated_files().iter().filter_map(|path| StoredFile::at(path)).collect();
+            report.remove(Artifact::Journal, cleanup::expired(retention.journals, journals, now));
+        }
+
+        if cleans(Artifact::Trace) {
+            let cutoff = retention.message_log_cutoff(now);
+            let traces = cleanup::expired(retention.traces, cleanup::json_files(&self.trace_dir), now);
+            let (referenced, traces): (Vec<_>, Vec<_>) = traces.into_iter().partition(|trace| {
+                let campaign_id = trace.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+                self.campaigns
+                    .load(campaign_id)
+                    .is_ok_and(|record| record.finished_at.is_none_or(|finished_at| finished_at >= cutoff))
+            });
+            report.kept_referenced = referenced.len();
+            report.remove(Artifact::Trace, traces);
+        }
+
+        if cleans(Artifact::TokenRename) {
+            let renames = cleanup::json_files(&self.token_rename_dir);
+            report.remove(Artifact::TokenRename, cleanup::expired(retention.token_renames, renames, now));
+        }
+
+        if cleans(Artifact::Heartbeat) && !self.bulk_control.is_running() {
+            let heartbeat = StoredFile::at(&self.heartbeat_path).into_iter().collect();
+            report.remove(Artifact::Heartbeat, cleanup::expired(retention.heartbeat, heartbeat, now));
+        }
+
+        if cleans(Artifact::Draft) {
+            match self.drafts.prune(settings.draft_retention()) {
+                Ok(pruned) => pruned.into_iter().for_each(|(file, bytes)| report.record(Artifact::Draft, file, bytes)),
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        for deleted in &report.deleted {
+            self.journal.transition(
+                "artifact_deleted",
+                deleted.campaign_id.as_deref(),
+                serde_json::to_value(deleted).unwrap_or(serde_json::Value::Null),
+            );
+        }
+        self.emit_app(app, "maintenance-report", None, &report)?;
+        Ok(report)
+    }
+
+    /// Emits `event` to the admin window and journals it. `campaign_id` is
+    /// only needed when the payload doesn't carry one. Goes through the app
+    /// rather than `window`, so a run outlives the window that started it.
+    fn emit<S: Serialize>(
+        &self,
+        window: &Window,
+        event: &str,
+        campaign_id: Option<&str>,
+        payload: &S,
     ) -> Result<(), String> {
-        // Simulate message sending with 90% success rate
-        sleep(Duration::from_millis(500)).await;
-        
-        if rand::random::<f64>() < 0.9 {
-            Ok(())
-        } else {
-            Err("Failed to send message".to_string())
+        self.emit_app(window.app_handle(), event, campaign_id, payload)
+    }
+
+    fn emit_app<S: Serialize>(
+        &self,
+        app: &AppHandle,
+        event: &str,
+        campaign_id: Option<&str>,
+        payload: &S,
+    ) -> Result<(), String> {
+        metrics::time(Stage::EventEmit, || {
+            self.journal.event(event, campaign_id, payload);
+            self.buffer_event(event, payload);
+            app.emit_to(crate::kiosk::ADMIN_WINDOW, event, payload).map_err(|e| e.to_string())
+        })
+    }
+
+    fn announce_pause_reasons(&self, app: &AppHandle) -> Result<(), String> {
+        let changed = PauseReasonsChanged::new(self.bulk_control.campaign_id(), self.bulk_control.pause_reasons());
+        self.emit_app(app, "campaign-pause-reason-changed", None, &changed)
+    }
+
+    /// Notes or clears a reason that holds the run without pausing it.
+    fn set_hold(&self, window: &Window, reason: PauseReason, active: bool) -> Result<(), String> {
+        let changed = match active {
+            true => self.bulk_control.hold(reason),
+            false => self.bulk_control.release(reason),
+        };
+        if changed {
+            self.announce_pause_reasons(window.app_handle())?;
+        }
+        Ok(())
+    }
+
+    /// Waits for `resume_bulk_send`, with the pause reasons recorded on the
+    /// campaign meanwhile. `processed` messages are done; the next one goes
+    /// out after resuming.
+    async fn wait_out_pause(
+        &self,
+        record: &mut CampaignRecord,
+        processed: usize,
+        total: usize,
+        window: &Window,
+    ) -> Result<(), String> {
+        if !self.bulk_control.is_paused() {
+            return Ok(());
+        }
+        record.pause_reasons = self.bulk_control.pause_reasons();
+        self.campaigns.save(record)?;
+        let paused = BulkPaused {
+            campaign_id: record.campaign_id.clone(),
+            processed,
+            total,
+            reasons: record.pause_reasons.clone(),
+            status_text: status_text::bulk_paused(processed, total),
+        };
+        self.emit(window, "whatsapp-bulk-paused", None, &paused)?;
+
+        self.bulk_control.wait_while_paused().await;
+        record.pause_reasons = self.bulk_control.pause_reasons();
+        self.campaigns.save(record)
+    }
+
+    /// Sleeps for `duration`, stopping for any pause on the way and
+    /// sleeping out the rest once resumed, so a pause never shortens the
+    /// wait. Returns early on cancel; `true` if the run was paused.
+    async fn sleep_through_pauses(
+        &self,
+        duration: Duration,
+        record: &mut CampaignRecord,
+        processed: usize,
+        total: usize,
+        window: &Window,
+    ) -> Result<bool, String> {
+        let mut remaining = duration;
+        let mut paused = false;
+        loop {
+            let started = Instant::now();
+            self.bulk_control.sleep_interruptibly(remaining).await;
+            remaining = remaining.saturating_sub(started.elapsed());
+            if self.bulk_control.is_cancelled() || !self.bulk_control.is_paused() {
+                return Ok(paused);
+            }
+            paused = true;
+            self.wait_out_pause(record, processed, total, window).await?;
+        }
+    }
+
+    fn buffer_event<S: Serialize>(&self, event: &str, payload: &S) {
+        let Ok(mut events) = self.recent_events.lock() else {
+            return;
+        };
+        if events.len() == BUFFERED_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(BufferedEvent {
+            sequence: self.next_sequence.fetch_add(1, Ordering::SeqCst),
+            event: event.to_string(),
+            payload: serde_json::to_value(payload).unwrap_or_default(),
+            at: campaign::now_millis(),
+        });
+    }
+
+    /// Events emitted after `sequence`, oldest first, for a webview that
+    /// was hidden or reloaded while a campaign ran. Older ones than the
+    /// buffer holds are in the event journal.
+    pub fn events_since(&self, sequence: u64) -> Vec<BufferedEvent> {
+        self.recent_events
+            .lock()
+            .map(|events| events.iter().filter(|e| e.sequence > sequence).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The campaign being sent, if a send is running.
+    pub fn sending_campaign(&self) -> Option<String> {
+        self.bulk_control.campaign_id().filter(|_| self.bulk_control.is_running())
+    }
+
+    /// Pauses the running send before its next message; the wait between
+    /// messages is cut short.
+    pub fn pause_bulk_send(&self, app: &AppHandle) -> Result<(), String> {
+        if self.bulk_control.pause_for(PauseReason::Operator) {
+            self.journal.transition("pause_requested", self.bulk_control.campaign_id().as_deref(), serde_json::Value::Null);
+            self.announce_pause_reasons(app)?;
         }
+        Ok(())
+    }
+
+    pub fn note_close_choice(&self, choice: crate::background::CloseChoice) {
+        self.journal.transition(
+            "window_close_choice",
+            self.sending_campaign().as_deref(),
+            serde_json::to_value(choice).unwrap_or_default(),
+        );
+    }
+
+    fn journal_status(&self, record: &CampaignRecord) {
+        self.journal.transition(
+            "campaign_status",
+            Some(&record.campaign_id),
+            serde_json::json!({ "status": record.status, "total": record.total }),
+        );
     }
 
     pub fn disconnect(&mut self) {
         self.session = None;
-        self.is_connected = false;
+        self.is_connected.store(false, Ordering::SeqCst);
     }
 
     pub fn is_connected(&self) -> bool {
-        self.is_connected
+        self.is_connected.load(Ordering::SeqCst)
     }
 }
 
-// Mock random function since we can't use rand crate
-mod rand {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    pub fn random<T>() -> T 
-    where 
-        T: From<f64>
-    {
-        let mut hasher = DefaultHasher::new();
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-        now.hash(&mut hasher);
-        let hash = hasher.finish();
-        T::from((hash as f64) / (u64::MAX as f64))
-    }
-}
\ No newline at end of file
+/// `phone` in E.164, the number a send actually goes to; the same check
+/// for real sends and dry runs, so a preview never passes a number the run
+/// would refuse or send elsewhere.
+fn check_phone(phone: &str, default_country: &str) -> Result<String, SendError> {
+    match crate::phone::normalize_to_e164(phone, default_country) {
+        Some(e164) => Ok(e164),
+        None => Err(SendError::new(ErrorKind::InvalidPhone, format!("Invalid phone number: {}", phone))),
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use super::StudentMessage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Order in which a campaign's messages go out. Applied to whole messages,
+/// after students sharing a phone are merged.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum OrderingStrategy {
+    #[default]
+    InputOrder,
+    /// Random but reproducible order. A missing seed is picked when the
+    /// campaign is created and saved with it.
+    Shuffle {
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    /// Takes one message per value of `token` in turn, e.g. interleaving
+    /// shifts so no shift gets all its messages at once.
+    RoundRobinBy { token: String },
+    /// Sorts by `token`, numerically when both values are numbers. Ties and
+    /// students without the token keep their input order, the latter last.
+    SortBy {
+        token: String,
+        #[serde(default)]
+        direction: SortDirection,
+    },
+}
+
+impl OrderingStrategy {
+    /// Fills in a shuffle seed so resuming or re-running gives the same order.
+    pub fn pin_seed(&mut self, fallback: u64) {
+        if let OrderingStrategy::Shuffle { seed: seed @ None } = self {
+            *seed = Some(fallback);
+        }
+    }
+
+    pub fn apply(&self, mut messages: Vec<Vec<StudentMessage>>) -> Vec<Vec<StudentMessage>> {
+        match self {
+            OrderingStrategy::InputOrder => messages,
+            OrderingStrategy::Shuffle { seed } => {
+                let mut rng = SplitMix64(seed.unwrap_or(0));
+                // Fisher-Yates
+                for index in (1..messages.len()).rev() {
+                    let other = (rng.next() % (index as u64 + 1)) as usize;
+                    messages.swap(index, other);
+                }
+                messages
+            }
+            OrderingStrategy::RoundRobinBy { token } => {
+                let mut buckets: Vec<VecDeque<Vec<StudentMessage>>> = Vec::new();
+                let mut bucket_by_value: HashMap<String, usize> = HashMap::new();
+                for message in messages {
+                    let value = message.first().and_then(|s| token_value(s, token)).unwrap_or_default();
+                    let bucket = *bucket_by_value.entry(value).or_insert_with(|| {
+                        buckets.push(Default::default());
+                        buckets.len() - 1
+                    });
+                    buckets[bucket].push_back(message);
+                }
+
+                let mut ordered = Vec::new();
+                while buckets.iter().any(|bucket| !bucket.is_empty()) {
+                    for bucket in &mut buckets {
+                        ordered.extend(bucket.pop_front());
+                    }
+                }
+                ordered
+            }
+            OrderingStrategy::SortBy { token, direction } => {
+                messages.sort_by(|a, b| {
+                    let a = a.first().and_then(|s| token_value(s, token));
+                    let b = b.first().and_then(|s| token_value(s, token));
+                    match (a, b) {
+                        (Some(a), Some(b)) => match direction {
+                            SortDirection::Ascending => compare_values(&a, &b),
+                            SortDirection::Descending => compare_values(&b, &a),
+                        },
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    }
+                });
+                messages
+            }
+        }
+    }
+}
+
+fn token_value(student: &StudentMessage, token: &str) -> Option<String> {
+    match token {
+        "name" => Some(student.name.clone()),
+        "student_id" => Some(student.student_id.clone()),
+        "due_date" => student.due_date.clone(),
+        _ => student.personalization_tokens.get(token).cloned(),
+    }
+}
+
+fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// Small seeded generator; the same seed gives the same order on every
+/// build and platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::errors::ErrorKind;
+use super::status_text;
+
+/// Why a run is paused or held. Several can be active at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    /// The campaign's failure rate or consecutive-failure limit was hit.
+    FailureThreshold,
+    /// WhatsApp isn't reachable: the session dropped or sends fail as
+    /// disconnected.
+    Disconnected,
+    /// A whatsapp:// link opened Windows' app picker.
+    AppPicker,
+    /// The watchdog saw no progress for too long.
+    Stalled,
+    /// Waiting for WhatsApp to come back after the machine slept.
+    SystemResume,
+    /// Holding until WhatsApp is the foreground window.
+    WrongFocus,
+    Maintenance,
+    /// Paused by the operator, e.g. when closing the window.
+    Operator,
+}
+
+impl PauseReason {
+    /// Resuming is refused while one of these still holds, unless forced.
+    pub fn is_blocking(self) -> bool {
+        matches!(self, PauseReason::Disconnected | PauseReason::Maintenance)
+    }
+
+    /// Cleared by the run itself once the condition passes; the others
+    /// wait for `resume_bulk_send`.
+    pub fn clears_itself(self) -> bool {
+        matches!(self, PauseReason::SystemResume | PauseReason::WrongFocus)
+    }
+
+    /// Reason for an auto-pause whose latest failure was `kind`.
+    pub fn for_failure(kind: Option<ErrorKind>) -> Self {
+        match kind {
+            Some(ErrorKind::SessionDisconnected) => PauseReason::Disconnected,
+            Some(ErrorKind::ProtocolHandlerAmbiguous) => PauseReason::AppPicker,
+            _ => PauseReason::FailureThreshold,
+        }
+    }
+}
+
+/// Payload of `campaign-pause-reason-changed`: everything holding the run
+/// right now; empty once it is sending again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PauseReasonsChanged {
+    pub campaign_id: Option<String>,
+    pub reasons: Vec<PauseReason>,
+    pub blocking: Vec<PauseReason>,
+    pub clears_itself: Vec<PauseReason>,
+    pub status_text: String,
+}
+
+impl PauseReasonsChanged {
+    pub fn new(campaign_id: Option<String>, reasons: Vec<PauseReason>) -> Self {
+        Self {
+            campaign_id,
+            blocking: reasons.iter().copied().filter(|r| r.is_blocking()).collect(),
+            clears_itself: reasons.iter().copied().filter(|r| r.clears_itself()).collect(),
+            status_text: status_text::pause_reasons(&reasons),
+            reasons,
+        }
+    }
+}
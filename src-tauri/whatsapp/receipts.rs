@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::errors::{ErrorKind, SendError};
+use super::hooks::{self, HookCommand};
+use super::{MessageBatches, StudentMessage};
+
+/// Students whose receipts are generated ahead of their turn, so the work
+/// overlaps the send and the interval wait before them.
+pub const RECEIPT_LOOKAHEAD: usize = 4;
+const GENERATOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What `receipt_generator` makes a student's receipt from; carried
+/// instead of `receipt_path` by campaigns with `lazy_receipts`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptSpec {
+    pub payment_id: String,
+    pub template: String,
+}
+
+/// Why a lazy receipt couldn't be generated, found without running the
+/// generator; for dry runs.
+pub fn spec_problem(spec: Option<&ReceiptSpec>, generator: Option<&HookCommand>) -> Option<String> {
+    if generator.is_none() {
+        return Some("No receipt_generator configured for lazy receipts".to_string());
+    }
+    match spec {
+        None => Some("No receipt_spec to generate the receipt from".to_string()),
+        Some(spec) if spec.payment_id.trim().is_empty() => Some("Receipt spec has no payment_id".to_string()),
+        Some(spec) if spec.template.trim().is_empty() => Some("Receipt spec has no template".to_string()),
+        Some(_) => None,
+    }
+}
+
+/// Receipts of a `lazy_receipts` run, generated just in time into a
+/// scratch folder that goes away with the run.
+pub struct LazyReceipts {
+    command: HookCommand,
+    dir: PathBuf,
+    /// Generation started, by student id.
+    pending: HashMap<String, JoinHandle<Result<PathBuf, String>>>,
+}
+
+impl LazyReceipts {
+    pub fn new(command: HookCommand, campaign_id: &str) -> Self {
+        Self {
+            command,
+            dir: std::env::temp_dir().join(format!("patch-receipts-{}", campaign_id)),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Starts on the receipts of the next `RECEIPT_LOOKAHEAD` students.
+    pub fn prefetch<'a>(&mut self, upcoming: impl Iterator<Item = &'a StudentMessage>) {
+        for student in upcoming.take(RECEIPT_LOOKAHEAD) {
+            self.start(student);
+        }
+    }
+
+    fn start(&mut self, student: &StudentMessage) {
+        if self.pending.contains_key(&student.student_id) {
+            return;
+        }
+        let Some(spec) = student.receipt_spec.clone() else {
+            return;
+        };
+        let command = self.command.clone();
+        let student_id = student.student_id.clone();
+        let output = self.dir.join(format!("{}.pdf", uuid::Uuid::new_v4()));
+        let handle = thread::spawn(move || generate(&command, &student_id, &spec, &output));
+        self.pending.insert(student.student_id.clone(), handle);
+    }
+
+    /// The student's receipt, waiting for it if it's still being generated.
+    /// Failures are `AttachmentMissing`; a retry generates it afresh.
+    pub async fn take(&mut self, student: &StudentMessage) -> Result<PathBuf, SendError> {
+        self.start(student);
+        let Some(handle) = self.pending.remove(&student.student_id) else {
+            return Err(SendError::new(
+                ErrorKind::AttachmentMissing,
+                format!("No receipt_spec for student {}", student.student_id),
+            ));
+        };
+        while !handle.is_finished() {
+            tokio::time::sleep(GENERATOR_POLL_INTERVAL).await;
+        }
+        handle
+            .join()
+            .unwrap_or_else(|_| Err("Receipt generation crashed".to_string()))
+            .map_err(|error| SendError::new(ErrorKind::AttachmentMissing, error))
+    }
+
+    /// Drops the receipt of a student who won't be sent to, once it's done.
+    pub fn discard(&mut self, student_id: &str) {
+        if let Some(handle) = self.pending.remove(student_id) {
+            thread::spawn(move || {
+                if let Ok(Ok(path)) = handle.join() {
+                    let _ = fs::remove_file(path);
+                }
+            });
+        }
+    }
+}
+
+impl Drop for LazyReceipts {
+    /// Removes the scratch folder once generation still under way is done.
+    fn drop(&mut self) {
+        let pending: Vec<_> = self.pending.drain().map(|(_, handle)| handle).collect();
+        let dir = self.dir.clone();
+        thread::spawn(move || {
+            for handle in pending {
+                let _ = handle.join();
+            }
+            let _ = fs::remove_dir_all(dir);
+        });
+    }
+}
+
+/// Runs the generator with the spec and output path as JSON on stdin; it
+/// has to write the receipt to `output` before its timeout.
+fn generate(command: &HookCommand, student_id: &str, spec: &ReceiptSpec, output: &Path) -> Result<PathBuf, String> {
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create receipt folder: {}", e))?;
+    }
+    let mut child = Command::new(&command.program)
+        .args(&command.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Receipt generator {} could not be started: {}", command.program, e))?;
+    let payload = serde_json::json!({
+        "student_id": student_id,
+        "payment_id": spec.payment_id,
+        "template": spec.template,
+        "output": output,
+    });
+    // Dropping stdin closes it, so the generator sees the end of the spec
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(payload.to_string().as_bytes());
+    }
+
+    let timeout = Duration::from_secs(match command.timeout_seconds {
+        0 => hooks::default_hook_timeout(),
+        seconds => seconds,
+    });
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Receipt for payment {} timed out being generated", spec.payment_id));
+            }
+            Ok(None) => thread::sleep(GENERATOR_POLL_INTERVAL),
+            Err(e) => return Err(format!("Receipt generator failed: {}", e)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("Receipt generator failed for payment {}: {}", spec.payment_id, status));
+    }
+    if !output.is_file() {
+        return Err(format!("Receipt generator wrote no receipt for payment {}", spec.payment_id));
+    }
+    Ok(output.to_path_buf())
+}
+
+/// The send loop's batches with their indexes, read a few ahead so the
+/// receipts of upcoming students can be started early.
+pub struct LookAhead {
+    batches: MessageBatches,
+    buffered: VecDeque<Result<Vec<StudentMessage>, String>>,
+    ahead: usize,
+    next_index: usize,
+}
+
+impl LookAhead {
+    /// `ahead` of 0 reads nothing ahead.
+    pub fn new(batches: MessageBatches, ahead: usize) -> Self {
+        Self {
+            batches,
+            buffered: VecDeque::new(),
+            ahead,
+            next_index: 0,
+        }
+    }
+
+    /// Batches after the one last returned, as far as has been read.
+    pub fn upcoming(&self) -> impl Iterator<Item = &[StudentMessage]> {
+        self.buffered.iter().filter_map(|batch| batch.as_deref().ok())
+    }
+}
+
+impl Iterator for LookAhead {
+    type Item = (usize, Result<Vec<StudentMessage>, String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffered.len() <= self.ahead {
+            match self.batches.next() {
+                Some(batch) => self.buffered.push_back(batch),
+                None => break,
+            }
+        }
+        let batch = self.buffered.pop_front()?;
+        self.next_index += 1;
+        Some((self.next_index - 1, batch))
+    }
+}
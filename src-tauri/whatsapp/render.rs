@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use super::dynamic::SendClock;
+use super::StudentMessage;
+use crate::phone::normalize_to_e164;
+
+const EACH_OPEN: &str = "{{#each students}}";
+const EACH_CLOSE: &str = "{{/each}}";
+
+/// Dynamic tokens go first so a stale value of the same name baked into
+/// the student's tokens never wins.
+fn fill_tokens(text: &str, student: &StudentMessage, clock: &SendClock) -> String {
+    let mut filled = text.to_string();
+    for tokens in [&clock.values_for(student), &student.personalization_tokens] {
+        for (token, value) in tokens {
+            filled = filled.replace(&format!("{{{}}}", token), value);
+        }
+    }
+    filled
+}
+
+/// Renders one message for `students`, who all share a phone. Each
+/// `{{#each students}}...{{/each}}` section is repeated per student with
+/// that student's tokens; the rest of the template uses the first
+/// student's tokens. Dynamic tokens resolve against `clock`.
+pub fn render_message(template: &str, students: &[StudentMessage], clock: &SendClock) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find(EACH_OPEN) {
+        let body_start = open + EACH_OPEN.len();
+        let Some(close) = rest[body_start..].find(EACH_CLOSE) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + close];
+
+        expanded.push_str(&rest[..open]);
+        for student in students {
+            expanded.push_str(&fill_tokens(body, student, clock));
+        }
+        rest = &rest[body_start + close + EACH_CLOSE.len()..];
+    }
+    expanded.push_str(rest);
+
+    match students.first() {
+        Some(first) => fill_tokens(&expanded, first, clock),
+        None => expanded,
+    }
+}
+
+/// Groups students by normalized phone, keeping the order in which each
+/// phone first appears. Numbers that don't normalize stay on their own.
+pub fn group_by_phone(students: Vec<StudentMessage>, default_country: &str) -> Vec<Vec<StudentMessage>> {
+    group_by_key(students, |student| normalize_to_e164(&student.phone, default_country))
+}
+
+/// `group_by_phone` with the phone's key supplied by `key`; students
+/// without a key stay on their own.
+pub fn group_by_key(
+    students: Vec<StudentMessage>,
+    key: impl Fn(&StudentMessage) -> Option<String>,
+) -> Vec<Vec<StudentMessage>> {
+    let mut groups: Vec<Vec<StudentMessage>> = Vec::new();
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+
+    for student in students {
+        match key(&student) {
+            Some(key) => match index_by_key.get(&key) {
+                Some(&index) => groups[index].push(student),
+                None => {
+                    index_by_key.insert(key, groups.len());
+                    groups.push(vec![student]);
+                }
+            },
+            None => groups.push(vec![student]),
+        }
+    }
+
+    groups
+}
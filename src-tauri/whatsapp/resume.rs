@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// How far the wall clock may run past an expected wait before we assume
+/// the machine was suspended in between.
+const SUSPEND_DRIFT_THRESHOLD: Duration = Duration::from_secs(30);
+
+pub const DEFAULT_RESUME_SETTLE_SECONDS: u64 = 15;
+
+/// Number of settle periods to wait for WhatsApp to come back before giving up.
+pub const MAX_RESUME_CHECKS: u32 = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemResumeNotice {
+    pub suspended_seconds: u64,
+    pub settle_seconds: u64,
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Returns how long the system was (probably) asleep during a wait that
+/// started at `started` and should have taken `expected`.
+///
+/// The monotonic clock stops during suspend on some platforms and keeps
+/// running on others, so the check is done against the wall clock: any
+/// large overshoot of the expected wait is treated as a suspend.
+pub fn detect_suspend(started: SystemTime, expected: Duration) -> Option<Duration> {
+    let elapsed = SystemTime::now().duration_since(started).ok()?;
+    let overshoot = elapsed.checked_sub(expected)?;
+
+    if overshoot > SUSPEND_DRIFT_THRESHOLD {
+        Some(overshoot)
+    } else {
+        None
+    }
+}
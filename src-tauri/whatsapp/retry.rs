@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::time::Duration;
+
+use super::errors::{ErrorKind, Remediation};
+use super::CampaignOptions;
+
+/// A campaign's `retry_backoff_seconds` doubles up to this, or up to the
+/// backoff itself if that is longer.
+pub const MAX_CAMPAIGN_BACKOFF_SECONDS: u64 = 15 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_delay_seconds: u64,
+    pub max_delay_seconds: u64,
+}
+
+impl RetryPolicy {
+    const fn new(max_retries: u32, initial_delay_seconds: u64, max_delay_seconds: u64) -> Self {
+        Self {
+            max_retries,
+            initial_delay_seconds,
+            max_delay_seconds,
+        }
+    }
+
+    /// Built-in policy for each error kind; settings may override any of them.
+    pub fn default_for(kind: ErrorKind) -> Self {
+        match kind {
+            // Retrying won't make a bad number valid
+            ErrorKind::InvalidPhone => Self::new(0, 0, 0),
+            ErrorKind::Timeout => Self::new(3, 2, 30),
+            // Gives the operator a moment to bring WhatsApp back to the front
+            ErrorKind::WrongWindowFocused => Self::new(2, 5, 20),
+            // Wait long enough for the session to reconnect
+            ErrorKind::SessionDisconnected => Self::new(3, 30, 300),
+            // Needs the operator to install or grant something first
+            ErrorKind::MissingTool | ErrorKind::PermissionDenied | ErrorKind::ProtocolHandlerAmbiguous => {
+                Self::new(0, 0, 0)
+            }
+            // A generator that timed out or hit a locked file often gets
+            // there on a second go
+            ErrorKind::AttachmentMissing => Self::new(2, 5, 30),
+            ErrorKind::Unknown => Self::new(1, 10, 10),
+        }
+    }
+
+    /// This policy with a campaign's own `max_retries` and
+    /// `retry_backoff_seconds`, for errors worth retrying at all, and no
+    /// delay shorter than the campaign's interval.
+    pub fn for_campaign(mut self, options: &CampaignOptions) -> Self {
+        if self.max_retries > 0 {
+            if let Some(max_retries) = options.max_retries {
+                self.max_retries = max_retries;
+            }
+            if let Some(backoff) = options.retry_backoff_seconds {
+                self.initial_delay_seconds = backoff;
+                self.max_delay_seconds = backoff.max(MAX_CAMPAIGN_BACKOFF_SECONDS);
+            }
+        }
+        self.initial_delay_seconds = self.initial_delay_seconds.max(options.interval_seconds);
+        self.max_delay_seconds = self.max_delay_seconds.max(options.interval_seconds);
+        self
+    }
+
+    /// Decides what to do after `attempt` (1-based) failed.
+    pub fn decide(&self, attempt: u32) -> RetryDecision {
+        if attempt > self.max_retries {
+            return RetryDecision::GiveUp {
+                reason: if self.max_retries == 0 {
+                    "Not retryable".to_string()
+                } else {
+                    format!("Gave up after {} retries", self.max_retries)
+                },
+            };
+        }
+
+        let factor = 2u64.saturating_pow(attempt - 1);
+        let delay = self.initial_delay_seconds.saturating_mul(factor).min(self.max_delay_seconds);
+        RetryDecision::Retry { delay_seconds: delay }
+    }
+}
+
+pub fn policy_for(kind: ErrorKind, overrides: &HashMap<ErrorKind, RetryPolicy>) -> RetryPolicy {
+    overrides
+        .get(&kind)
+        .cloned()
+        .unwrap_or_else(|| RetryPolicy::default_for(kind))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RetryDecision {
+    Retry { delay_seconds: u64 },
+    GiveUp { reason: String },
+}
+
+impl RetryDecision {
+    pub fn delay(&self) -> Option<Duration> {
+        match self {
+            RetryDecision::Retry { delay_seconds } => Some(Duration::from_secs(*delay_seconds)),
+            RetryDecision::GiveUp { .. } => None,
+        }
+    }
+}
+
+/// One failed attempt and what the policy decided to do about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryJournalEntry {
+    pub student_id: String,
+    pub attempt: u32,
+    pub error_kind: ErrorKind,
+    pub error: String,
+    pub remediation: Option<Remediation>,
+    pub decision: RetryDecision,
+    pub at: u64,
+    /// The text that failed, which `resend_campaign_message` sends again;
+    /// missing from records written before re-sends existed.
+    #[serde(default)]
+    pub message: Option<String>,
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::errors::ErrorKind;
+use super::pause::PauseReason;
+
+/// How much `status_text` says. Only English text exists for now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusVerbosity {
+    /// Full sentences, for screen readers.
+    #[default]
+    Verbose,
+    Terse,
+}
+
+static TERSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verbosity(verbosity: StatusVerbosity) {
+    TERSE.store(verbosity == StatusVerbosity::Terse, Ordering::Relaxed);
+}
+
+fn terse() -> bool {
+    TERSE.load(Ordering::Relaxed)
+}
+
+const ONES: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+    "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+/// `n` in words, e.g. "one hundred and twelve".
+pub fn spell_number(n: usize) -> String {
+    if n < 20 {
+        return ONES[n].to_string();
+    }
+    if n < 100 {
+        return match n % 10 {
+            0 => TENS[n / 10].to_string(),
+            ones => format!("{}-{}", TENS[n / 10], ONES[ones]),
+        };
+    }
+    if n < 1000 {
+        return match n % 100 {
+            0 => format!("{} hundred", ONES[n / 100]),
+            rest => format!("{} hundred and {}", ONES[n / 100], spell_number(rest)),
+        };
+    }
+
+    let (scale, name) = match n {
+        n if n < 1_000_000 => (1000, "thousand"),
+        n if n < 1_000_000_000 => (1_000_000, "million"),
+        _ => (1_000_000_000, "billion"),
+    };
+    let head = format!("{} {}", spell_number(n / scale), name);
+    match n % scale {
+        0 => head,
+        rest if rest < 100 => format!("{} and {}", head, spell_number(rest)),
+        rest => format!("{} {}", head, spell_number(rest)),
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    match n {
+        1 => format!("one {}", noun),
+        _ => format!("{} {}s", spell_number(n), noun),
+    }
+}
+
+/// "a, b and c".
+fn join(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+fn error_phrase(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::InvalidPhone => "the phone number is not valid",
+        ErrorKind::Timeout => "WhatsApp did not respond in time",
+        ErrorKind::WrongWindowFocused => "another window was in front of WhatsApp",
+        ErrorKind::SessionDisconnected => "WhatsApp is disconnected",
+        ErrorKind::MissingTool => "a helper program is not installed",
+        ErrorKind::PermissionDenied => "the system refused keyboard access",
+        ErrorKind::ProtocolHandlerAmbiguous => "Windows asked which app opens WhatsApp links",
+        ErrorKind::AttachmentMissing => "the receipt could not be generated",
+        ErrorKind::Unknown => "of an unknown error",
+    }
+}
+
+fn reason_phrase(reason: PauseReason) -> &'static str {
+    match reason {
+        PauseReason::FailureThreshold => "too many messages failed",
+        PauseReason::Disconnected => "WhatsApp is disconnected",
+        PauseReason::AppPicker => "Windows is asking which app opens WhatsApp links",
+        PauseReason::Stalled => "sending stopped making progress",
+        PauseReason::SystemResume => "the computer has just woken up",
+        PauseReason::WrongFocus => "WhatsApp is not the active window",
+        PauseReason::Maintenance => "maintenance mode is on",
+        PauseReason::Operator => "it was paused by hand",
+    }
+}
+
+/// For `MessageProgress`, e.g. "Message to Asha sent. One hundred and
+/// twelve of three hundred done."
+pub fn message_progress(name: &str, status: &str, error_kind: Option<ErrorKind>, processed: usize, total: usize) -> String {
+    let outcome = match status {
+        "sent" => "sent".to_string(),
+        "failed" => match error_kind {
+            Some(kind) => format!("not sent because {}", error_phrase(kind)),
+            None => "not sent".to_string(),
+        },
+        "failed_after_retries" => match error_kind {
+            Some(kind) => format!("not sent after retrying because {}", error_phrase(kind)),
+            None => "not sent after retrying".to_string(),
+        },
+        "previewed" => "previewed, not sent".to_string(),
+        "would_fail" => "would not be sent, check the details".to_string(),
+        "retrying" => match error_kind {
+            Some(kind) => format!("not sent yet because {}, trying again", error_phrase(kind)),
+            None => "not sent yet, trying again".to_string(),
+        },
+        "skipped_no_consent" => "skipped, no consent to message".to_string(),
+        other => other.replace('_', " "),
+    };
+    let progress = format!("{} of {}", spell_number(processed), spell_number(total));
+
+    if terse() {
+        format!("{}: {}, {}.", name, outcome, progress)
+    } else {
+        format!("Message to {} {}. {} done.", name, outcome, capitalize(&progress))
+    }
+}
+
+/// For `campaign-pause-reason-changed`.
+pub fn pause_reasons(reasons: &[PauseReason]) -> String {
+    if reasons.is_empty() {
+        return match terse() {
+            true => "Sending.".to_string(),
+            false => "The campaign is sending again.".to_string(),
+        };
+    }
+
+    let phrases: Vec<&str> = reasons.iter().map(|reason| reason_phrase(*reason)).collect();
+    if terse() {
+        return format!("Paused: {}.", join(&phrases));
+    }
+
+    let mut text = format!("The campaign is paused because {}.", join(&phrases));
+    if reasons.iter().any(|reason| reason.is_blocking()) {
+        text.push_str(" It can't be resumed until that is fixed.");
+    } else if reasons.iter().all(|reason| reason.clears_itself()) {
+        text.push_str(" It will continue on its own.");
+    }
+    text
+}
+
+pub fn campaign_pending(delay_seconds: u64) -> String {
+    let delay = count(delay_seconds as usize, "second");
+    match terse() {
+        true => format!("Starting in {}.", delay),
+        false => format!("The campaign starts in {} unless you abort it.", delay),
+    }
+}
+
+pub fn auto_paused(processed: usize, total: usize, failed: usize) -> String {
+    let failed = count(failed, "message");
+    match terse() {
+        true => format!("Auto-paused: {} failed.", failed),
+        false => format!(
+            "The campaign paused itself after {} of {}: {} failed. Fix the problem, then resume.",
+            spell_number(processed),
+            spell_number(total),
+            failed,
+        ),
+    }
+}
+
+pub fn stalled(idle_seconds: u64) -> String {
+    let idle = count(idle_seconds as usize, "second");
+    match terse() {
+        true => format!("Stalled for {}.", idle),
+        false => format!("Nothing has been sent for {}, so the campaign was paused.", idle),
+    }
+}
+
+pub fn bulk_paused(processed: usize, total: usize) -> String {
+    match terse() {
+        true => format!("Paused at {} of {}.", spell_number(processed), spell_number(total)),
+        false => format!(
+            "Sending is paused after {} of {}. It will continue from the next message when resumed.",
+            spell_number(processed),
+            spell_number(total),
+        ),
+    }
+}
+
+pub fn campaign_cancelled(processed: usize, total: usize) -> String {
+    let remaining = count(total.saturating_sub(processed), "message");
+    match terse() {
+        true => format!("Cancelled: {} not sent.", remaining),
+        false => format!(
+            "The campaign was cancelled after {} of {}; {} were not sent.",
+            spell_number(processed),
+            spell_number(total),
+            remaining,
+        ),
+    }
+}
+
+pub fn dry_run_complete(previewed: usize, would_fail: usize, skipped: usize) -> String {
+    let counts = format!(
+        "{} previewed, {} would fail, {} skipped",
+        spell_number(previewed),
+        spell_number(would_fail),
+        spell_number(skipped),
+    );
+    match terse() {
+        true => format!("Dry run: {}.", counts),
+        false => format!("The dry run has finished, nothing was sent: {}.", counts),
+    }
+}
+
+pub fn campaign_complete(sent: usize, failed: usize, skipped: usize) -> String {
+    let counts = format!("{} sent, {} failed, {} skipped", spell_number(sent), spell_number(failed), spell_number(skipped));
+    match terse() {
+        true => format!("Finished: {}.", counts),
+        false => format!("The campaign has finished: {}.", counts),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::completion::FailedMessage;
+use super::dynamic::SendClock;
+use super::{render, StudentMessage};
+
+pub const DEFAULT_SUMMARY_TEMPLATE: &str =
+    "{campaign}: {sent} sent, {failed} failed, {skipped} skipped, took {duration}.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryOutcome {
+    Finished,
+    AutoPaused,
+    Cancelled,
+}
+
+/// Counts for the supervisor's end-of-campaign message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignSummary {
+    pub campaign_id: String,
+    pub name: Option<String>,
+    pub outcome: SummaryOutcome,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedSend {
+    pub student_id: String,
+    pub error: String,
+}
+
+/// What a bulk send returns and `whatsapp-bulk-complete` carries; also
+/// saved on the campaign record. Counts are of students.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkSendSummary {
+    pub campaign_id: String,
+    pub total: usize,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Never reached because the run was cancelled.
+    pub remaining: usize,
+    pub cancelled: bool,
+    /// Nothing was sent: `sent` counts messages previewed and `failed`
+    /// those that would fail.
+    #[serde(default)]
+    pub dry_run: bool,
+    pub failures: Vec<FailedSend>,
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// Of this run; a resumed campaign's earlier run isn't included.
+    pub elapsed_seconds: u64,
+    pub status_text: String,
+}
+
+impl BulkSendSummary {
+    /// `total` counts excluded students too, as `summary.skipped` does.
+    /// `cancelled` is set by the cancel path; a run that finishes with
+    /// students unaccounted for wasn't cancelled.
+    pub fn new(
+        summary: &CampaignSummary,
+        total: usize,
+        cancelled: bool,
+        failures: &[FailedMessage],
+        (started_at, finished_at): (u64, u64),
+        elapsed: Duration,
+        status_text: String,
+    ) -> Self {
+        let reached = summary.sent + summary.failed + summary.skipped;
+        Self {
+            campaign_id: summary.campaign_id.clone(),
+            total,
+            sent: summary.sent,
+            failed: summary.failed,
+            skipped: summary.skipped,
+            remaining: total.saturating_sub(reached),
+            cancelled,
+            dry_run: false,
+            failures: failures
+                .iter()
+                .map(|failure| FailedSend {
+                    student_id: failure.student_id.clone(),
+                    error: failure.error.clone(),
+                })
+                .collect(),
+            started_at,
+            finished_at,
+            elapsed_seconds: elapsed.as_secs(),
+            status_text,
+        }
+    }
+}
+
+/// Payload of `whatsapp-supervisor-notified`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupervisorNotified {
+    pub campaign_id: String,
+    pub outcome: SummaryOutcome,
+    pub error: Option<String>,
+}
+
+impl CampaignSummary {
+    /// Renders `template` like a campaign message, with the counts as the
+    /// recipient's tokens: `{campaign}`, `{outcome}`, `{sent}`, `{failed}`,
+    /// `{skipped}` and `{duration}`.
+    pub fn render(&self, template: &str, supervisor_phone: &str, clock: &SendClock) -> String {
+        let outcome = match self.outcome {
+            SummaryOutcome::Finished => "finished",
+            SummaryOutcome::AutoPaused => "auto-paused",
+            SummaryOutcome::Cancelled => "cancelled",
+        };
+        let tokens: HashMap<String, String> = [
+            ("campaign", self.name.clone().unwrap_or_else(|| "Campaign".to_string())),
+            ("outcome", outcome.to_string()),
+            ("sent", self.sent.to_string()),
+            ("failed", self.failed.to_string()),
+            ("skipped", self.skipped.to_string()),
+            ("duration", format_duration(self.duration_seconds)),
+        ]
+        .into_iter()
+        .map(|(token, value)| (token.to_string(), value))
+        .collect();
+
+        let recipient = StudentMessage {
+            student_id: format!("supervisor-{}", self.campaign_id),
+            name: "Supervisor".to_string(),
+            phone: supervisor_phone.to_string(),
+            receipt_path: None,
+            receipt_spec: None,
+            personalization_tokens: tokens,
+            consent: None,
+            due_date: None,
+        };
+        render::render_message(template, &[recipient], clock)
+    }
+}
+
+/// `2h 10m`, `7m` or `45s`.
+fn format_duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
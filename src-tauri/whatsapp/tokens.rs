@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::campaign::now_millis;
+
+const TOKEN_RENAME_DIR: &str = "token_renames";
+
+/// Where `rename_token` keeps the templates it rewrote.
+pub fn token_rename_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(TOKEN_RENAME_DIR)
+}
+
+/// Where a template rewritten by `rename_token` is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    Draft,
+    /// A campaign that hasn't started sending; sent ones are history and
+    /// are left alone.
+    Campaign,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateChange {
+    pub source: TemplateSource,
+    pub id: String,
+    pub name: Option<String>,
+    pub before: String,
+    pub after: String,
+}
+
+/// What `rename_token` changed, or would change when not applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenRename {
+    pub old: String,
+    pub new: String,
+    pub applied: bool,
+    pub changes: Vec<TemplateChange>,
+    /// The original bodies, written before anything was changed.
+    pub backup_path: Option<PathBuf>,
+}
+
+/// A token in a template that only resolves through `token_aliases`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeprecatedToken {
+    pub token: String,
+    pub replacement: String,
+}
+
+/// Payload of `whatsapp-deprecated-tokens`, emitted before a campaign
+/// whose template still uses renamed tokens starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeprecatedTokensFound {
+    pub campaign_id: String,
+    pub tokens: Vec<DeprecatedToken>,
+}
+
+pub fn validate_name(token: &str) -> Result<(), String> {
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid token name: {}", token));
+    }
+    Ok(())
+}
+
+/// Openings after which a token name is a use of that token: `{fee}`,
+/// `{fee|upper}`, `{{#if fee}}` and `{{#unless fee}}`.
+const TOKEN_PREFIXES: [&str; 3] = ["{", "{{#if ", "{{#unless "];
+
+/// Byte offsets of every use of `token` in `template`. A longer name such
+/// as `{fee_paid}` is not a use of `fee`.
+fn uses_of(template: &str, token: &str) -> Vec<usize> {
+    template
+        .match_indices(token)
+        .map(|(at, _)| at)
+        .filter(|&at| {
+            let after = &template[at + token.len()..];
+            TOKEN_PREFIXES.iter().any(|prefix| template[..at].ends_with(prefix))
+                && (after.starts_with('}') || after.starts_with('|'))
+        })
+        .collect()
+}
+
+/// `template` with every use of `old` turned into `new`, keeping any
+/// formatter suffix, or `None` when it doesn't use `old`. `{{#each}}`
+/// sections are plain text to this.
+pub fn rename_in(template: &str, old: &str, new: &str) -> Option<String> {
+    let uses = uses_of(template, old);
+    if uses.is_empty() {
+        return None;
+    }
+
+    let mut renamed = String::with_capacity(template.len());
+    let mut copied = 0;
+    for at in uses {
+        renamed.push_str(&template[copied..at]);
+        renamed.push_str(new);
+        copied = at + old.len();
+    }
+    renamed.push_str(&template[copied..]);
+    Some(renamed)
+}
+
+/// Rewrites aliased tokens to their current names before rendering, so
+/// templates saved before a rename keep working.
+pub fn resolve_aliases(template: &str, aliases: &HashMap<String, String>) -> String {
+    let mut resolved = template.to_string();
+    for (old, new) in aliases {
+        if let Some(renamed) = rename_in(&resolved, old, new) {
+            resolved = renamed;
+        }
+    }
+    resolved
+}
+
+pub fn deprecated_tokens(template: &str, aliases: &HashMap<String, String>) -> Vec<DeprecatedToken> {
+    let mut deprecated: Vec<DeprecatedToken> = aliases
+        .iter()
+        .filter(|(old, _)| !uses_of(template, old).is_empty())
+        .map(|(old, new)| DeprecatedToken {
+            token: old.clone(),
+            replacement: new.clone(),
+        })
+        .collect();
+    deprecated.sort_by(|a, b| a.token.cmp(&b.token));
+    deprecated
+}
+
+/// Saves the original bodies of `rename` under `dir` before it is applied.
+pub fn write_backup(dir: &Path, rename: &TokenRename) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let path = dir.join(format!("rename-{}-{}.json", rename.old, now_millis()));
+    let contents = serde_json::to_string_pretty(rename).map_err(|e| e.to_string())?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write template backup: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases() -> HashMap<String, String> {
+        HashMap::from([("fee".to_string(), "due_amount".to_string())])
+    }
+
+    #[test]
+    fn renames_plain_tokens() {
+        assert_eq!(
+            rename_in("Pay {fee} by {due_date}", "fee", "due_amount").as_deref(),
+            Some("Pay {due_amount} by {due_date}")
+        );
+    }
+
+    #[test]
+    fn keeps_formatter_suffixes() {
+        assert_eq!(
+            rename_in("{name|upper}, {fee|currency} is due", "name", "student_name").as_deref(),
+            Some("{student_name|upper}, {fee|currency} is due")
+        );
+        assert_eq!(
+            rename_in("{fee|round|currency}", "fee", "due_amount").as_deref(),
+            Some("{due_amount|round|currency}")
+        );
+    }
+
+    #[test]
+    fn renames_inside_each_sections() {
+        let template = "Dues:{{#each students}}\n{name}: {fee}{{/each}}\nTotal {fee}";
+        assert_eq!(
+            rename_in(template, "fee", "due_amount").as_deref(),
+            Some("Dues:{{#each students}}\n{name}: {due_amount}{{/each}}\nTotal {due_amount}")
+        );
+    }
+
+    #[test]
+    fn renames_conditions_and_their_bodies() {
+        let template = "{{#if fee}}Pay {fee}{{/if}}{{#unless fee}}Nothing due{{/unless}}";
+        assert_eq!(
+            rename_in(template, "fee", "due_amount").as_deref(),
+            Some("{{#if due_amount}}Pay {due_amount}{{/if}}{{#unless due_amount}}Nothing due{{/unless}}")
+        );
+    }
+
+    #[test]
+    fn leaves_longer_names_and_plain_text_alone() {
+        assert_eq!(rename_in("{fee_paid} fee {late_fee}", "fee", "due_amount"), None);
+        assert_eq!(
+            rename_in("{fee_paid} {fee}", "fee", "due_amount").as_deref(),
+            Some("{fee_paid} {due_amount}")
+        );
+    }
+
+    #[test]
+    fn each_section_name_is_not_a_token() {
+        assert_eq!(rename_in("{{#each students}}{name}{{/each}}", "students", "members"), None);
+    }
+
+    #[test]
+    fn aliases_resolve_in_every_position() {
+        let template = "{{#if fee}}{fee|upper}{{/if}} {{#each students}}{fee}{{/each}}";
+        assert_eq!(
+            resolve_aliases(template, &aliases()),
+            "{{#if due_amount}}{due_amount|upper}{{/if}} {{#each students}}{due_amount}{{/each}}"
+        );
+        assert_eq!(resolve_aliases("{due_amount}", &aliases()), "{due_amount}");
+    }
+
+    #[test]
+    fn deprecated_tokens_are_found_behind_formatters() {
+        let deprecated = deprecated_tokens("Pay {fee|currency}", &aliases());
+        assert_eq!(deprecated.len(), 1);
+        assert_eq!(deprecated[0].token, "fee");
+        assert_eq!(deprecated[0].replacement, "due_amount");
+        assert!(deprecated_tokens("Pay {fee_paid}", &aliases()).is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::consent::ConsentLevel;
+use super::dynamic::SendClock;
+use super::{render, CampaignOptions, CampaignRecord, StudentMessage};
+use crate::phone::normalize_to_e164;
+use crate::privacy::mask_phone;
+use crate::settings::AppSettings;
+
+const TRACE_DIR: &str = "traces";
+const TRACE_VERSION: u32 = 1;
+
+pub fn trace_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(TRACE_DIR)
+}
+
+/// The settings that affect what a campaign sends and in which order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceSettings {
+    pub default_country: String,
+    pub utc_offset_minutes: i32,
+    pub demo_mode: bool,
+}
+
+/// A recipient with the phone masked and the receipt reduced to a hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceStudent {
+    pub student_id: String,
+    pub name: String,
+    pub phone: String,
+    /// Hash of the normalized phone, which is what merging groups by;
+    /// `None` when the number didn't normalize.
+    pub phone_key: Option<String>,
+    pub receipt_hash: Option<String>,
+    pub personalization_tokens: HashMap<String, String>,
+    pub consent: Option<ConsentLevel>,
+    pub due_date: Option<String>,
+}
+
+/// What the run decided for one message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceMessage {
+    pub student_ids: Vec<String>,
+    /// Refused for lack of consent.
+    pub skipped: Vec<String>,
+    /// When `{today}` and friends were resolved.
+    pub as_of: Option<u64>,
+    pub message_hash: Option<String>,
+}
+
+/// Everything needed to redo a campaign's ordering and rendering without
+/// its real phones or attachment contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignTrace {
+    pub version: u32,
+    pub campaign_id: String,
+    pub settings: TraceSettings,
+    pub options: CampaignOptions,
+    /// Hashes of the common attachments, in order.
+    pub attachment_hashes: Vec<Option<String>>,
+    /// In the order the campaign store returned them.
+    pub students: Vec<TraceStudent>,
+    pub messages: Vec<TraceMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceDivergence {
+    pub index: usize,
+    pub field: String,
+    pub recorded: String,
+    pub replayed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceReplay {
+    pub campaign_id: String,
+    pub messages_recorded: usize,
+    pub messages_replayed: usize,
+    pub divergences: Vec<TraceDivergence>,
+}
+
+impl CampaignTrace {
+    pub fn capture(
+        record: &CampaignRecord,
+        options: &CampaignOptions,
+        settings: &AppSettings,
+        students: impl Iterator<Item = Result<StudentMessage, String>>,
+    ) -> Result<Self, String> {
+        let mut options = options.clone();
+        options.exclusions = options.exclusions.iter().map(|entry| mask_phone(entry)).collect();
+        let attachment_hashes = options
+            .common_attachments
+            .iter()
+            .map(|attachment| file_hash(&attachment.path))
+            .collect();
+
+        let students = students
+            .map(|student| {
+                let student = student?;
+                Ok(TraceStudent {
+                    phone_key: normalize_to_e164(&student.phone, &settings.default_country)
+                        .map(|phone| fingerprint(phone.as_bytes())),
+                    phone: mask_phone(&student.phone),
+                    receipt_hash: student.receipt_path.as_deref().and_then(file_hash),
+                    student_id: student.student_id,
+                    name: student.name,
+                    personalization_tokens: student.personalization_tokens,
+                    consent: student.consent,
+                    due_date: student.due_date,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            version: TRACE_VERSION,
+            campaign_id: record.campaign_id.clone(),
+            settings: TraceSettings {
+                default_country: settings.default_country.clone(),
+                utc_offset_minutes: settings.utc_offset_minutes,
+                demo_mode: settings.demo_mode,
+            },
+            options,
+            attachment_hashes,
+            students,
+            messages: Vec::new(),
+        })
+    }
+
+    /// `clock` and `message` are `None` when every student of the message
+    /// was refused.
+    pub fn record_message(
+        &mut self,
+        students: &[StudentMessage],
+        skipped: Vec<String>,
+        clock: Option<&SendClock>,
+        message: Option<&str>,
+    ) {
+        self.messages.push(TraceMessage {
+            student_ids: students.iter().map(|s| s.student_id.clone()).collect(),
+            skipped,
+            as_of: clock.map(|clock| clock.as_of),
+            message_hash: message.map(|message| fingerprint(message.as_bytes())),
+        });
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create trace directory: {}", e))?;
+        let path = dir.join(format!("{}.json", self.campaign_id));
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&path, contents).map_err(|e| format!("Failed to save campaign trace: {}", e))?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let trace: Self = serde_json::from_str(&contents)
+            .map_err(|e| format!("Not a campaign trace: {}", e))?;
+        if trace.version > TRACE_VERSION {
+            return Err(format!("Campaign trace version {} is not supported", trace.version));
+        }
+        Ok(trace)
+    }
+
+    /// Redoes grouping, consent filtering and rendering from the recorded
+    /// inputs, with the recorded clock, and lists where the outcome differs.
+    /// Nothing is sent.
+    pub fn replay(&self) -> TraceReplay {
+        // The phone key stands in for the phone; merging only compares them
+        let students: Vec<StudentMessage> = self
+            .students
+            .iter()
+            .map(|student| StudentMessage {
+                student_id: student.student_id.clone(),
+                name: student.name.clone(),
+                phone: student.phone_key.clone().unwrap_or_default(),
+                receipt_path: None,
+                receipt_spec: None,
+                personalization_tokens: student.personalization_tokens.clone(),
+                consent: student.consent,
+                due_date: student.due_date.clone(),
+            })
+            .collect();
+        let batches = if self.options.merge_shared_phone {
+            render::group_by_key(students, |student| Some(student.phone.clone()).filter(|key| !key.is_empty()))
+        } else {
+            students.into_iter().map(|student| vec![student]).collect()
+        };
+        let batches = self.options.ordering.clone().unwrap_or_default().apply(batches);
+
+        let mut divergences = Vec::new();
+        let mut diverge = |index: usize, field: &str, recorded: String, replayed: String| {
+            if recorded != replayed {
+                divergences.push(TraceDivergence { index, field: field.to_string(), recorded, replayed });
+            }
+        };
+
+        let messages_replayed = batches.len();
+        for (index, (batch, recorded)) in batches.into_iter().zip(&self.messages).enumerate() {
+            let (students, refused): (Vec<StudentMessage>, Vec<StudentMessage>) = batch
+                .into_iter()
+                .partition(|student| self.options.campaign_kind.permits(student.consent));
+
+            let ids = |students: &[StudentMessage]| {
+                students.iter().map(|s| s.student_id.as_str()).collect::<Vec<_>>().join(",")
+            };
+            diverge(index, "student_ids", recorded.student_ids.join(","), ids(&students));
+            diverge(index, "skipped", recorded.skipped.join(","), ids(&refused));
+
+            let replayed_hash = match (recorded.as_of, students.is_empty()) {
+                (Some(as_of), false) => {
+                    let clock = SendClock::at(as_of, self.settings.utc_offset_minutes);
+                    let message = render::render_message(&self.options.message_template, &students, &clock);
+                    Some(fingerprint(message.as_bytes()))
+                }
+                _ => None,
+            };
+            diverge(
+                index,
+                "message_hash",
+                recorded.message_hash.clone().unwrap_or_default(),
+                replayed_hash.unwrap_or_default(),
+            );
+        }
+
+        if messages_replayed != self.messages.len() {
+            diverge(
+                messages_replayed.min(self.messages.len()),
+                "message_count",
+                self.messages.len().to_string(),
+                messages_replayed.to_string(),
+            );
+        }
+
+        TraceReplay {
+            campaign_id: self.campaign_id.clone(),
+            messages_recorded: self.messages.len(),
+            messages_replayed,
+            divergences,
+        }
+    }
+}
+
+fn file_hash(path: &str) -> Option<String> {
+    fs::read(path).ok().map(|contents| fingerprint(&contents))
+}
+
+/// 64-bit FNV-1a, in hex. Stable across builds and platforms, unlike
+/// `DefaultHasher`, so traces from a user's machine replay anywhere.
+fn fingerprint(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
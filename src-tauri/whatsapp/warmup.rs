@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use sysinfo::{System, MINIMUM_CPU_UPDATE_INTERVAL};
+use tokio::time::{sleep, Duration, Instant};
+
+use super::focus;
+use crate::desktop;
+use crate::input;
+
+/// Readiness heuristics for the warm-up before the first send. Low-end PCs
+/// need longer timeouts and a higher idle threshold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarmupSettings {
+    pub enabled: bool,
+    /// Start WhatsApp ourselves when it isn't running.
+    pub auto_launch: bool,
+    pub launch_timeout_seconds: u64,
+    /// WhatsApp counts as idle below this CPU usage (percent of one core).
+    pub idle_cpu_percent: f32,
+    /// Consecutive idle samples required before sending.
+    pub idle_samples: u32,
+    pub sample_interval_ms: u64,
+    /// Upper bound for waiting on focus and idleness; sending starts anyway after it.
+    pub max_wait_seconds: u64,
+}
+
+impl Default for WarmupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auto_launch: true,
+            launch_timeout_seconds: 30,
+            idle_cpu_percent: 15.0,
+            idle_samples: 2,
+            sample_interval_ms: 1000,
+            max_wait_seconds: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupStarted {
+    pub was_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupComplete {
+    pub launched: bool,
+    pub launch_ms: u64,
+    pub foreground: bool,
+    pub focus_ms: u64,
+    pub idle: bool,
+    pub idle_ms: u64,
+    pub total_ms: u64,
+}
+
+/// Makes sure WhatsApp is running, in front and done loading before the
+/// first deeplink of a run; a cold-starting app tends to drop that URL.
+pub async fn warm_up(
+    settings: &WarmupSettings,
+    was_running: bool,
+) -> Result<WarmupComplete, String> {
+    let started = Instant::now();
+    let max_wait = Duration::from_secs(settings.max_wait_seconds);
+
+    let mut launched = false;
+    if !was_running {
+        if !settings.auto_launch {
+            return Err("WhatsApp is not running. Start it and try again".to_string());
+        }
+
+        let scheme = desktop::active_variant().scheme();
+        input::simulator().open_url(&format!("{}://", scheme))?;
+        let deadline = Instant::now() + Duration::from_secs(settings.launch_timeout_seconds);
+        while !desktop::is_whatsapp_running() {
+            if Instant::now() >= deadline {
+                return Err("WhatsApp did not start in time".to_string());
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+        launched = true;
+    }
+    let launch_ms = started.elapsed().as_millis() as u64;
+
+    focus::focus_whatsapp_window()?;
+    let focus_started = Instant::now();
+    // Focus that can't be checked doesn't hold up the run
+    let mut foreground = desktop::is_whatsapp_foreground().unwrap_or(true);
+    while !foreground && focus_started.elapsed() < max_wait {
+        sleep(Duration::from_millis(500)).await;
+        foreground = desktop::is_whatsapp_foreground().unwrap_or(true);
+    }
+    let focus_ms = focus_started.elapsed().as_millis() as u64;
+
+    let idle_started = Instant::now();
+    let idle = wait_for_idle(settings, max_wait).await;
+    let idle_ms = idle_started.elapsed().as_millis() as u64;
+
+    Ok(WarmupComplete {
+        launched,
+        launch_ms,
+        foreground,
+        focus_ms,
+        idle,
+        idle_ms,
+        total_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+async fn wait_for_idle(settings: &WarmupSettings, max_wait: Duration) -> bool {
+    let interval = Duration::from_millis(settings.sample_interval_ms).max(MINIMUM_CPU_UPDATE_INTERVAL);
+    let started = Instant::now();
+    let mut system = System::new();
+    system.refresh_processes();
+
+    let mut idle_streak = 0;
+    while started.elapsed() < max_wait {
+        sleep(interval).await;
+        system.refresh_processes();
+
+        if desktop::whatsapp_cpu_usage(&system) < settings.idle_cpu_percent {
+            idle_streak += 1;
+            if idle_streak >= settings.idle_samples {
+                return true;
+            }
+        } else {
+            idle_streak = 0;
+        }
+    }
+
+    false
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager, Window};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+
+use super::attachments;
+use super::campaign::now_millis;
+use super::control::BulkSendControl;
+use super::journal::EventJournal;
+use super::pause::{PauseReason, PauseReasonsChanged};
+use super::status_text;
+
+const HEARTBEAT_FILE: &str = "heartbeat.json";
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// `--watchdog-check` fails once the heartbeat is this many intervals old.
+const STALE_AFTER_INTERVALS: u64 = 3;
+/// Rough cost of one send, without attachments, on top of the configured
+/// interval.
+const EXPECTED_SEND_SECONDS: u64 = 10;
+/// A run counts as stalled after this many expected message times
+/// without progress.
+const STALL_AFTER_MESSAGES: u32 = 3;
+
+/// Written to the app data dir while a campaign is sending, for external
+/// monitors such as Task Scheduler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub campaign_id: String,
+    pub written_at: u64,
+    pub last_progress_at: u64,
+    pub processed: usize,
+    pub total: usize,
+    pub sent: usize,
+    pub failed: usize,
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignStalled {
+    pub campaign_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub idle_seconds: u64,
+    pub status_text: String,
+}
+
+/// Expected time from one message to the next: the interval, the send and
+/// its `attachments`.
+pub fn expected_message_time(interval_seconds: u64, attachments: usize) -> Duration {
+    Duration::from_secs(interval_seconds + EXPECTED_SEND_SECONDS) + attachments::send_time(attachments)
+}
+
+pub fn heartbeat_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(HEARTBEAT_FILE)
+}
+
+/// Progress of the running send as seen by the heartbeat task.
+pub struct SendWatchdog {
+    heartbeat: Mutex<Heartbeat>,
+    /// Progress is expected by then; known waits such as retry delays
+    /// push it out.
+    deadline: AtomicU64,
+    stall_after: Duration,
+}
+
+impl SendWatchdog {
+    /// `message_time` is from `expected_message_time`.
+    pub fn new(campaign_id: &str, total: usize, message_time: Duration) -> Arc<Self> {
+        let now = now_millis();
+        let stall_after = message_time * STALL_AFTER_MESSAGES;
+        Arc::new(Self {
+            heartbeat: Mutex::new(Heartbeat {
+                campaign_id: campaign_id.to_string(),
+                written_at: now,
+                last_progress_at: now,
+                processed: 0,
+                total,
+                sent: 0,
+                failed: 0,
+                paused: false,
+            }),
+            deadline: AtomicU64::new(now + stall_after.as_millis() as u64),
+            stall_after,
+        })
+    }
+
+    /// Records a finished message (or skipped student).
+    pub fn progress(&self, processed: usize, sent: usize, failed: usize) {
+        let now = now_millis();
+        if let Ok(mut heartbeat) = self.heartbeat.lock() {
+            heartbeat.last_progress_at = now;
+            heartbeat.processed = processed;
+            heartbeat.sent += sent;
+            heartbeat.failed += failed;
+        }
+        self.extend(Duration::ZERO);
+    }
+
+    /// Allows for a deliberate wait of `wait` before the next progress.
+    pub fn extend(&self, wait: Duration) {
+        let deadline = now_millis() + (wait + self.stall_after).as_millis() as u64;
+        self.deadline.fetch_max(deadline, Ordering::SeqCst);
+    }
+
+    fn reset_deadline(&self) {
+        let deadline = now_millis() + self.stall_after.as_millis() as u64;
+        self.deadline.store(deadline, Ordering::SeqCst);
+    }
+
+    fn snapshot(&self, paused: bool) -> Option<Heartbeat> {
+        let mut heartbeat = self.heartbeat.lock().ok()?;
+        heartbeat.written_at = now_millis();
+        heartbeat.paused = paused;
+        Some(heartbeat.clone())
+    }
+}
+
+/// Writes the heartbeat until dropped, then removes the file so a
+/// finished run doesn't look hung.
+pub struct HeartbeatTask {
+    handle: JoinHandle<()>,
+    path: PathBuf,
+}
+
+impl Drop for HeartbeatTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Starts the heartbeat writer, which also pauses the run with a
+/// `campaign-stalled` event when no progress arrives in time.
+pub fn spawn(
+    watchdog: Arc<SendWatchdog>,
+    path: PathBuf,
+    control: Arc<BulkSendControl>,
+    journal: Arc<EventJournal>,
+    window: Window,
+) -> HeartbeatTask {
+    let task_path = path.clone();
+    let handle = tokio::spawn(async move {
+        let mut last_tick = now_millis();
+        loop {
+            let paused = control.is_paused();
+            if let Some(heartbeat) = watchdog.snapshot(paused) {
+                if let Ok(contents) = serde_json::to_string_pretty(&heartbeat) {
+                    let _ = fs::write(&task_path, contents);
+                }
+            }
+
+            sleep(HEARTBEAT_INTERVAL).await;
+
+            // Time spent paused or with the machine asleep isn't a stall
+            let now = now_millis();
+            let slept = now.saturating_sub(last_tick) > 2 * HEARTBEAT_INTERVAL.as_millis() as u64;
+            last_tick = now;
+            if control.is_paused() || slept {
+                watchdog.reset_deadline();
+                continue;
+            }
+
+            if now > watchdog.deadline.load(Ordering::SeqCst) {
+                let Some(heartbeat) = watchdog.snapshot(false) else {
+                    continue;
+                };
+                if control.pause_for(PauseReason::Stalled) {
+                    let changed = PauseReasonsChanged::new(control.campaign_id(), control.pause_reasons());
+                    journal.event("campaign-pause-reason-changed", None, &changed);
+                    let _ = window.app_handle().emit_to(crate::kiosk::ADMIN_WINDOW, "campaign-pause-reason-changed", &changed);
+                }
+                let idle_seconds = now.saturating_sub(heartbeat.last_progress_at) / 1000;
+                let stalled = CampaignStalled {
+                    campaign_id: heartbeat.campaign_id,
+                    processed: heartbeat.processed,
+                    total: heartbeat.total,
+                    idle_seconds,
+                    status_text: status_text::stalled(idle_seconds),
+                };
+                journal.event("campaign-stalled", None, &stalled);
+                let _ = window.app_handle().emit_to(crate::kiosk::ADMIN_WINDOW, "campaign-stalled", &stalled);
+                crate::background::show_admin_window(window.app_handle());
+            }
+        }
+    });
+
+    HeartbeatTask { handle, path }
+}
+
+/// Exit code for `--watchdog-check`: 0 when no campaign is running or its
+/// heartbeat is fresh, 1 when the heartbeat is stale, 2 when it can't be
+/// read.
+pub fn check_heartbeat(path: &Path) -> i32 {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            println!("No campaign running");
+            return 0;
+        }
+    };
+    let heartbeat: Heartbeat = match serde_json::from_str(&contents) {
+        Ok(heartbeat) => heartbeat,
+        Err(e) => {
+            eprintln!("Unreadable heartbeat {}: {}", path.display(), e);
+            return 2;
+        }
+    };
+
+    let age = now_millis().saturating_sub(heartbeat.written_at);
+    let stale_after = HEARTBEAT_INTERVAL.as_millis() as u64 * STALE_AFTER_INTERVALS;
+    if age > stale_after {
+        eprintln!(
+            "Campaign {} heartbeat is {}s old ({}/{} processed)",
+            heartbeat.campaign_id,
+            age / 1000,
+            heartbeat.processed,
+            heartbeat.total
+        );
+        return 1;
+    }
+
+    println!(
+        "Campaign {} alive: {}/{} processed, {} sent, {} failed{}",
+        heartbeat.campaign_id,
+        heartbeat.processed,
+        heartbeat.total,
+        heartbeat.sent,
+        heartbeat.failed,
+        if heartbeat.paused { ", paused" } else { "" }
+    );
+    0
+}
@@ -8,7 +8,12 @@ export enum SendStatus {
   SENDING = 'sending', 
   SENT = 'sent',
   FAILED = 'failed',
+  FAILED_AFTER_RETRIES = 'failed_after_retries',
+  RETRYING = 'retrying',
+  PREVIEWED = 'previewed',
+  WOULD_FAIL = 'would_fail',
   SKIPPED = 'skipped',
+  SKIPPED_NO_CONSENT = 'skipped_no_consent',
   CANCELLED = 'cancelled'
 }
 
@@ -97,19 +102,82 @@ export interface PersonalizationTokens {
   seatNumber: string;
 }
 
-export interface BulkMessageRequest {
+export interface BulkMessageRequest extends CampaignOptions {
   students: StudentMessage[];
+}
+
+// Everything about a bulk run except its recipients; also what
+// start_streamed_campaign takes
+export interface CampaignOptions {
   message_template: string;
   attach_receipt: boolean;
   interval_seconds: number;
+  resume_settle_seconds?: number;  // wait after the PC wakes from sleep mid-run
+  abort_on_failure_rate?: number;  // 0-1, auto-pauses the run when reached
+  abort_after_consecutive_failures?: number;
+  name?: string;
+  label?: string;
+  notes?: string;
+  campaign_kind?: CampaignKind;     // defaults to 'transactional'
+  common_attachments?: Attachment[]; // sent to everyone after the text, before the student's receipt
+  merge_shared_phone?: boolean;     // one message per phone; {{#each students}}...{{/each}} repeats per student
+  source?: CampaignSource;
+  draft_id?: string;                // deleted once the campaign starts sending
+  exclusions?: string[];            // student ids or phones to skip in this campaign
+  exclusion_lists?: string[];       // saved list names, copied in when the campaign is created
+  notify_supervisor?: boolean;      // message supervisor_number when it finishes, auto-pauses or is cancelled
+  completion_actions?: CompletionAction[];  // run in order once the campaign ends
+  ordering?: OrderingStrategy | null;       // input order when unset
+  max_retries?: number;             // overrides retry_policies for retryable errors
+  retry_backoff_seconds?: number;   // first retry delay, doubling up to 15 min; never below interval_seconds
+  dry_run?: boolean;                // render and check only: 'previewed' / 'would_fail', no waits
+  lazy_receipts?: boolean;          // with attach_receipt: receipt_generator makes each receipt from
+                                    // receipt_spec just before its send; dry runs only check the specs
+}
+
+export interface Attachment {
+  path: string;
+  caption?: string | null;
+}
+
+export type CampaignSource = { type: 'csv_import'; file_name: string };
+
+export interface CsvColumnMapping {
+  name: string;                     // CSV header of the name column
+  phone: string;
+  student_id?: string;
+  tokens?: Record<string, string>;  // header -> token name, other columns keep their header
+  due_date?: string;                // header of an ISO due-date column, for {days_overdue}
+}
+
+export interface CsvCampaignImport {
+  students: StudentMessage[];
+  rejected: { row: number; name: string; phone: string; reason: string }[];
+  issues: { row: number; message: string }[];
+  source: CampaignSource;
+}
+
+export type CampaignKind = 'transactional' | 'promotional';
+
+export type ConsentLevel = 'none' | 'transactional' | 'all';
+
+// Fields that may change when cloning a campaign; the rest is copied
+export interface CloneOverrides {
+  name?: string;
+  message_template?: string;
+  interval_seconds?: number;
+  dry_run?: boolean;          // false to send a campaign that was only a dry run
 }
 
 export interface StudentMessage {
   student_id: string;
   name: string;
-  phone: string;
+  phone: string;                    // masked ones the backend handed out can be sent back as is
   receipt_path?: string;
+  receipt_spec?: { payment_id: string; template: string };  // instead of receipt_path with lazy_receipts
   personalization_tokens: Record<string, string>;
+  consent?: ConsentLevel;           // promotional runs skip anything but 'all'
+  due_date?: string;                // ISO date; {days_overdue} is resolved at send time
 }
 
 export interface MessageProgress {
@@ -117,13 +185,646 @@ export interface MessageProgress {
   name: string;
   phone: string;
   status: SendStatus;
+  status_text: string;        // plain-language sentence for screen readers
   error?: string;
+  error_kind?: ErrorKind;
+  remediation?: Remediation | null;
+  attempt: number;            // 1-based; on 'retrying' the attempt that failed, 0 when skipped
+  processed: number;
+  total: number;
+  campaign_id: string;
+  demo_mode: boolean;
+  rendered_message: string | null;  // text actually sent; null when skipped
+}
+
+export type ErrorKind =
+  | 'invalid_phone'
+  | 'timeout'
+  | 'wrong_window_focused'
+  | 'session_disconnected'
+  | 'missing_tool'
+  | 'permission_denied'
+  | 'protocol_handler_ambiguous'     // Windows showed its app picker; the run pauses
+  | 'attachment_missing'             // a lazy receipt couldn't be generated
+  | 'unknown';
+
+// Fix-it hint key; the text comes from the explain_error command
+export type Remediation =
+  | 'install_xdotool'
+  | 'grant_accessibility'
+  | 'repair_protocol_handler'
+  | 're_pair_session'
+  | 'focus_whatsapp'
+  | 'restart_whatsapp'
+  | 'check_phone_number'
+  | 'check_receipt_generator';
+
+export interface RetryPolicy {
+  max_retries: number;
+  initial_delay_seconds: number;  // doubles on every retry
+  max_delay_seconds: number;
+}
+
+export interface RetryJournalEntry {
+  student_id: string;
+  attempt: number;
+  error_kind: ErrorKind;
+  error: string;
+  remediation: Remediation | null;
+  decision: { action: 'retry'; delay_seconds: number } | { action: 'give_up'; reason: string };
+  at: number;                 // epoch ms
+  message?: string | null;    // the text that failed
+}
+
+// Returned by resend_campaign_message(campaign_id, student_id) and kept in CampaignRecord.resends
+export interface ResendAttempt {
+  student_ids: string[];      // everyone the message covers, phone owner first
+  attempt: number;            // continues from the run's attempts
+  status: 'sent' | 'failed';
+  error: string | null;
+  at: number;                 // epoch ms
+}
+
+export type CampaignStatus = 'building' | 'pending_start' | 'sending' | 'finished' | 'cancelled';
+
+// Payload of `whatsapp-campaign-pending`; abort_pending_campaign cancels until starts_at
+export interface CampaignPending {
+  campaign_id: string;
+  delay_seconds: number;
+  starts_at: number;          // epoch ms
+  status_text: string;
+}
+
+export interface CampaignRecord {
+  campaign_id: string;
+  status: CampaignStatus;
+  name?: string;
+  label?: string;
+  notes?: string;
+  source?: CampaignSource;
+  options?: CampaignOptions;
+  started_at: number;         // epoch ms
+  finished_at?: number;
+  total: number;
+  retry_journal: RetryJournalEntry[];
+  hook_runs: HookRun[];
+  completion_runs?: CompletionActionRun[];
+  resends?: ResendAttempt[];        // manual re-sends after the campaign ended
+  merged_messages: { student_ids: string[] }[];
+  demo_mode: boolean;
+  draft_id?: string | null;
+  start_confirmed_at?: number | null;  // countdown passed; not held again
+  exclusions?: { student_ids: string[]; phones: string[] };
+  excluded?: string[];              // student ids reported as skipped_excluded
+  pause_reasons?: PauseReason[];    // live in get_active_campaign
+  summary?: BulkSendSummary | null; // once finished or cancelled
+}
+
+// A campaign in progress, saved so it survives an update or crash
+export interface CampaignDraft extends CampaignOptions {
+  draft_id?: string | null;         // omit when saving a new draft
+  saved_at?: number;                // epoch ms, set on save
+  students: StudentMessage[];
+  ui_state?: unknown;               // returned as saved
+}
+
+export interface DraftSummary {
+  draft_id: string;
+  name: string | null;
+  saved_at: number;
+  student_count: number;
+}
+
+export interface SystemResumeNotice {
+  suspended_seconds: number;
+  settle_seconds: number;
+  processed: number;
+  total: number;
+}
+
+// Payload of `campaign-stalled`: the run made no progress in time and was paused
+export interface CampaignStalled {
+  campaign_id: string;
   processed: number;
   total: number;
+  idle_seconds: number;
+  status_text: string;
+}
+
+export interface AutoPauseNotice {
+  reason: string;
+  processed: number;
+  total: number;
+  failed: number;
+  consecutive_failures: number;
+  failure_rate: number;
+  dominant_error_kind?: ErrorKind;
+  remediation?: Remediation | null;
+  status_text: string;
+}
+
+export interface AppSettings {
+  mask_phone_numbers: boolean;  // phones in backend payloads arrive as 98XXXXXX21; reveal_phone(campaign_id, student_id) for one in full
+  retry_policies: Partial<Record<ErrorKind, RetryPolicy>>;
+  default_country: string;        // e.g. 'IN'
+  warmup: WarmupSettings;
+  preferred_variant: WhatsAppVariant | null;
+  demo_mode: boolean;
+  hold_until_focused: boolean;
+  auto_refocus_after_seconds: number | null;
+  hooks: HookSettings;
+  receipt_generator: HookCommand | null;  // for lazy_receipts: gets {student_id, payment_id, template, output}
+                                          // on stdin and writes the PDF to output; never exported
+  library_profile: LibraryProfile | null;
+  message_footer: string;
+  utc_offset_minutes: number;       // for {today}/{send_time}; 330 = IST
+  close_open_with_dialog: boolean;  // close Windows' app picker when a send hits it
+  draft_retention_days: number;
+  max_campaign_drafts: number;
+  retention: RetentionSettings;   // enforced daily and by run_maintenance_now
+  send_confirmation_delay_seconds: number;  // abortable countdown before sending; 0 disables
+  supervisor_number: string | null;
+  supervisor_summary_template: string;  // tokens: {campaign} {outcome} {sent} {failed} {skipped} {duration}
+  record_campaign_trace: boolean;       // debugging: save traces for replay_campaign_trace
+  token_aliases: Record<string, string>;  // renamed tokens, old -> new, still resolving
+  status_verbosity: 'verbose' | 'terse';  // phrasing of status_text
+}
+
+// Returned by send_bulk_whatsapp_messages, finalize_streamed_campaign and
+// resume_pending_bulk_send; payload of 'whatsapp-bulk-complete', also sent after
+// 'whatsapp-bulk-cancelled' with cancelled set. Counts are students
+export interface BulkSendSummary {
+  campaign_id: string;
+  total: number;
+  sent: number;
+  failed: number;
+  skipped: number;
+  remaining: number;          // not reached because the run was cancelled
+  cancelled: boolean;
+  dry_run?: boolean;          // sent = previewed, failed = would fail
+  failures: { student_id: string; error: string }[];
+  started_at: number;         // epoch ms
+  finished_at: number;        // epoch ms
+  elapsed_seconds: number;    // this run only, when resumed
+  status_text: string;
+}
+
+// Returned by list_incomplete_jobs: campaigns left sending by a crash or reboot,
+// for resume_pending_bulk_send
+export interface IncompleteCampaign {
+  campaign_id: string;
+  name: string | null;
+  started_at: number;   // epoch ms
+  total: number;
+  sent: number;         // students already reached; skipped on resume
+}
+
+// Payload of 'whatsapp-bulk-paused', once the run has stopped; resuming sends message processed + 1
+export interface BulkPaused {
+  campaign_id: string;
+  processed: number;
+  total: number;
+  reasons: PauseReason[];
+  status_text: string;
+}
+
+// Payload of 'whatsapp-bulk-cancelled'; counts are messages, as in MessageProgress
+export interface BulkCancelled {
+  campaign_id: string;
+  processed: number;
+  remaining: number;
+  total: number;
+  status_text: string;
+}
+
+// export_settings writes this; import_settings reads it
+export interface SettingsArchive {
+  version: number;
+  exported_at: number;   // epoch ms
+  machine_id: string;
+  includes_secrets: boolean;     // always false now: export_settings refuses include_secrets, archives aren't encrypted
+  settings: Partial<AppSettings>;
+}
+
+export interface SettingChange {
+  field: keyof AppSettings;
+  current: unknown;
+  incoming: unknown;
+  device_scoped: boolean;  // kept unless import_settings gets include_device_fields
+  secret: boolean;         // kept unless import_settings gets include_secrets
+}
+
+// Returned by destructive commands (delete_exclusion_list, delete_campaign_draft,
+// import_settings, rename_token with apply): call again with
+// `confirmation: token` within a minute to go ahead
+export type Confirmable<T> =
+  | { status: 'confirmation_required'; token: string; impact: string; expires_at: number }
+  | { status: 'done'; result: T };
+
+export interface LibraryProfile {
+  name: string;
+  address?: string | null;
+  contact_phone?: string | null;
+}
+
+export type OnboardingStep =
+  | 'library_profile'
+  | 'default_country'
+  | 'whatsapp_diagnostics'
+  | 'first_student_import'
+  | 'message_footer';
+
+export interface OnboardingState {
+  complete: boolean;
+  steps: { step: OnboardingStep; completed_at: number | null }[];
+  restored_from_backup_at: number | null;
+}
+
+export interface InputResult {
+  message: string;
+  demo_mode: boolean;
+}
+
+export type RecordedInput =
+  | { action: 'open_url'; url: string; at: number }
+  | { action: 'press_key'; key: 'Enter' | 'SelectAll' | 'Copy' | 'Paste'; at: number }
+  | { action: 'type_text'; text: string; at: number }
+  | { action: 'copy_file'; path: string; at: number };   // an attachment put on the clipboard
+
+// From test_input_injection
+export interface InjectionTest {
+  expected: string;
+  actual: string;                    // read back through the clipboard
+  matched: boolean;
+  keyboard_layout: string | null;    // Windows language id, e.g. '0x0439'
+}
+
+export interface WarmupSettings {
+  enabled: boolean;
+  auto_launch: boolean;
+  launch_timeout_seconds: number;
+  idle_cpu_percent: number;       // percent of one core
+  idle_samples: number;
+  sample_interval_ms: number;
+  max_wait_seconds: number;
+}
+
+export interface WarmupComplete {
+  launched: boolean;
+  launch_ms: number;
+  foreground: boolean;
+  focus_ms: number;
+  idle: boolean;
+  idle_ms: number;
+  total_ms: number;
 }
 
 export interface WhatsAppSession {
   is_connected: boolean;
   session_id?: string;
   qr_code?: string;
-}
\ No newline at end of file
+}
+export type PipelineStage = 'render' | 'build_url' | 'input' | 'log';
+
+export interface CampaignProjection {
+  campaign_size: number;
+  interval_seconds: number;
+  estimated_seconds_p50: number;
+  estimated_seconds_p95: number;
+}
+
+export interface BenchmarkResult {
+  machine_id: string;
+  measured_at: number;
+  sample_size: number;
+  stages: { stage: PipelineStage; p50_ms: number; p95_ms: number }[];
+  per_message_p50_ms: number;
+  per_message_p95_ms: number;
+  projection: CampaignProjection | null;
+}
+
+export interface WaitingForFocus {
+  campaign_id: string;
+  processed: number;
+  total: number;
+  waited_seconds: number;
+  refocus_attempted: boolean;
+}
+
+export interface EtaUpdate {
+  campaign_id: string;
+  remaining: number;
+  estimated_seconds_remaining: number;
+  estimated_finish_at: number;
+  focus_hold_ms: number;
+}
+
+export interface EncodedMessage {
+  url: string;
+  text: string;         // what the recipient will see
+  was_encoded: boolean; // input was percent-encoded already and was decoded once
+}
+
+export type HookEvent = 'before_campaign' | 'after_message' | 'after_campaign';
+
+export interface HookCommand {
+  program: string;
+  args?: string[];
+  timeout_seconds?: number;  // defaults to 30
+}
+
+export interface HookSettings {
+  enabled: boolean;
+  before_campaign: HookCommand | null;
+  after_message: HookCommand | null;
+  after_campaign: HookCommand | null;
+}
+
+export interface HookRun {
+  event: HookEvent;
+  program: string;
+  student_id: string | null;
+  started_at: number;
+  duration_ms: number;
+  exit_code: number | null;
+  timed_out: boolean;
+  stderr: string;
+  error: string | null;
+}
+
+export interface MessagePreview {
+  student_ids: string[];
+  name: string;
+  phone: string;
+  message: string;
+  dynamic_values: Record<string, string>;  // {today}, {send_time}, {days_overdue} as of `as_of`
+  as_of: number;
+}
+
+export type WhatsAppVariant = 'regular' | 'business';
+
+export interface InstallationInfo {
+  variant: WhatsAppVariant | null;   // what sends go through
+  installed_variants: WhatsAppVariant[];
+  running_variants: WhatsAppVariant[];
+  protocol_handler_registered: boolean | null;  // null where it can't be checked
+  open_with_dialog_visible: boolean | null;     // Windows app picker on screen; null off Windows
+}
+
+// Payload of `maintenance-mode-changed`; while active, mutating commands
+// fail with "MaintenanceMode: <reason>"
+export interface MaintenanceState {
+  active: boolean;
+  reason: string | null;
+  started_at: number | null;   // epoch ms
+  expires_at: number | null;   // ends on its own then
+}
+
+// One line of the file written by export_event_journal (JSON Lines)
+export interface JournalEntry {
+  at: number;                        // epoch ms
+  kind: 'event' | 'transition';      // transition = backend decision not emitted to the webview
+  name: string;                      // event name, or e.g. 'campaign_status', 'send_attempt_failed'
+  campaign_id?: string;
+  student_id?: string;
+  data: unknown;                     // payload, phones always masked
+}
+
+// Argument of export_message_log, which returns Confirmable<task id>; it asks for
+// confirmation first when the journal could yield over 100k rows
+export interface MessageLogExport {
+  filter?: {
+    student_id?: string;
+    phone?: string;            // compared masked, as the journal keeps phones
+    campaign_id?: string;
+    from?: number;             // epoch ms, inclusive
+    to?: number;               // epoch ms, exclusive
+    status?: string;           // e.g. 'sent', 'failed', 'failed_after_retries'
+    channel?: string;          // only 'whatsapp' so far
+  };
+  format: 'csv' | 'jsonl';
+  destination: string;
+  include_message?: boolean;   // add the rendered text
+}
+
+// One row of the message log export, a CSV line or a JSON line
+export interface MessageLogRow {
+  at: number;                  // epoch ms
+  campaign_id: string;
+  student_id: string;
+  name: string;                // empty for re-sends
+  phone: string;               // masked; empty for re-sends
+  channel: 'whatsapp';
+  status: string;
+  attempt: number;
+  error: string | null;
+  demo_mode: boolean;
+  message?: string;            // with include_message
+}
+
+export type RetentionPolicy =
+  | { keep: 'days'; days: number }     // by last write
+  | { keep: 'files'; count: number }   // the most recent ones
+  | { keep: 'unlimited' };
+
+// Drafts follow draft_retention_days and max_campaign_drafts instead
+export interface RetentionSettings {
+  journals: RetentionPolicy;       // rotated journals only; also bounds which campaigns keep their traces
+  traces: RetentionPolicy;
+  token_renames: RetentionPolicy;  // backups written by rename_token
+  heartbeat: RetentionPolicy;      // left behind by a crashed run
+}
+
+export type Artifact = 'journal' | 'trace' | 'token_rename' | 'heartbeat' | 'draft';
+
+// Returned by run_maintenance_now(only?: Artifact); payload of 'maintenance-report',
+// also sent by the daily run. Each deletion is journaled as 'artifact_deleted'
+export interface MaintenanceReport {
+  ran_at: number;              // epoch ms
+  deleted: {
+    artifact: Artifact;
+    file: string;
+    bytes: number;
+    campaign_id?: string;      // for traces
+  }[];
+  reclaimed_bytes: number;
+  kept_referenced: number;     // expired traces of campaigns still in the message log
+  errors: string[];
+}
+
+// Returned by get_storage_usage
+export interface StorageUsage {
+  categories: {
+    category: 'campaigns' | 'journal' | 'traces' | 'token_renames' | 'drafts' | 'heartbeat' | 'exclusion_lists';
+    bytes: number;
+    files: number;
+    cleanup: Artifact | null;  // pass to run_maintenance_now to clean just this
+  }[];
+  total_bytes: number;
+  available_bytes: number | null;  // free on the app data's volume
+}
+
+// Payload of 'whatsapp-supervisor-notified'
+export interface SupervisorNotified {
+  campaign_id: string;
+  outcome: 'finished' | 'auto_paused' | 'cancelled';
+  error: string | null;   // the summary is sent once and never retried
+}
+
+// Returned by replay_campaign_trace; no divergences means the run is reproducible
+export interface TraceDivergence {
+  index: number;   // message position in the run
+  field: 'student_ids' | 'skipped' | 'message_hash' | 'message_count';
+  recorded: string;
+  replayed: string;
+}
+
+export interface TraceReplay {
+  campaign_id: string;
+  messages_recorded: number;
+  messages_replayed: number;
+  divergences: TraceDivergence[];
+}
+
+// Heavy commands (build_campaign_from_csv, export_event_journal, export_message_log)
+// return a task id; follow it with 'task-progress' events or get_task_status.
+// They check their budget first and fail without starting with
+// "InsufficientDiskSpace: needed <bytes> bytes, <bytes> available" (exports) or
+// "InsufficientMemory: needed <bytes> bytes, <bytes> available" (CSV import)
+export type TaskState = 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface TaskProgress {
+  task_id: string;
+  kind: string;        // the command that started it
+  state: TaskState;
+  stage: string;
+  percent: number;     // 0-100
+  detail: string | null;
+}
+
+export interface TaskStatus extends TaskProgress {
+  started_at: number;          // epoch ms
+  finished_at: number | null;
+  result: unknown | null;      // e.g. CsvCampaignImport for build_campaign_from_csv
+  error: string | null;
+}
+
+// Closing the admin window during a campaign emits 'close-requested-while-sending';
+// answer with resolve_close_request
+export type CloseChoice = 'keep_running' | 'pause_and_close' | 'cancel_and_close';
+
+export interface CloseRequested {
+  campaign_id: string | null;
+}
+
+// From get_missed_events(sequence); pass the last sequence seen
+export interface BufferedEvent {
+  sequence: number;
+  event: string;
+  payload: unknown;
+  at: number;   // epoch ms
+}
+
+// Why a run is paused or held; several can hold at once.
+// resume_bulk_send({ force }) is refused while a blocking reason still holds
+export type PauseReason =
+  | 'failure_threshold'
+  | 'disconnected'
+  | 'app_picker'
+  | 'stalled'
+  | 'system_resume'
+  | 'wrong_focus'
+  | 'maintenance'
+  | 'operator';
+
+// Payload of 'campaign-pause-reason-changed'; reasons is empty once sending again
+export interface PauseReasonsChanged {
+  campaign_id: string | null;
+  reasons: PauseReason[];
+  blocking: PauseReason[];        // need force to resume while they hold
+  clears_itself: PauseReason[];   // the run clears these on its own
+  status_text: string;
+}
+
+export type CompletionActionKind =
+  | { action: 'export_failures_csv'; path: string }
+  | { action: 'zip_receipts'; path: string }
+  | { action: 'notify'; message: string }         // emitted as 'whatsapp-completion-notice'
+  | { action: 'shutdown'; if_no_failures: boolean };  // quits the app
+
+export type CompletionAction = CompletionActionKind & {
+  run_on_cancel?: boolean;   // also run when the campaign is cancelled
+};
+
+export type CompletionActionRun = CompletionActionKind & {
+  ran_at: number;   // epoch ms
+  outcome: 'succeeded' | 'failed' | 'skipped';
+  detail: string | null;
+};
+
+// Payload of 'whatsapp-completion-notice'
+export interface CompletionNotice {
+  campaign_id: string;
+  message: string;
+}
+
+// Applied to whole messages, after shared phones are merged. A shuffle
+// without a seed gets one when the campaign is created.
+export type OrderingStrategy =
+  | { strategy: 'input_order' }
+  | { strategy: 'shuffle'; seed?: number | null }
+  | { strategy: 'round_robin_by'; token: string }
+  | { strategy: 'sort_by'; token: string; direction?: 'ascending' | 'descending' };
+
+// From rename_token; only a report unless applied
+export interface TokenRename {
+  old: string;
+  new: string;
+  applied: boolean;
+  changes: {
+    source: 'draft' | 'campaign';   // unsent campaigns only
+    id: string;
+    name: string | null;
+    before: string;
+    after: string;
+  }[];
+  backup_path: string | null;
+}
+
+// From check_template_tokens
+export interface DeprecatedToken {
+  token: string;
+  replacement: string;
+}
+
+// Payload of 'whatsapp-deprecated-tokens', before a campaign starts
+export interface DeprecatedTokensFound {
+  campaign_id: string;
+  tokens: DeprecatedToken[];
+}
+
+// From get_performance_metrics
+export type MetricsStage =
+  | 'deeplink_open'
+  | 'focus_wait'
+  | 'key_injection'
+  | 'event_emit'
+  | 'list_campaigns'
+  | 'list_drafts';
+
+export interface StageMetrics {
+  stage: MetricsStage;
+  count: number;
+  mean_ms: number;
+  max_ms: number;
+  p50_ms: number | null;   // bucket bound, an estimate
+  p95_ms: number | null;
+  buckets: { below_ms: number | null; count: number }[];
+}
+
+export interface PerformanceMetrics {
+  read_at: number;                   // epoch ms
+  totals: StageMetrics[];            // since the app started
+  since_last_read: StageMetrics[];   // reset by every read
+  last_read_at: number | null;
+}
@@ -1,5 +1,66 @@
 import { storage } from '@/lib/database';
 
+// @ts-ignore
+const { invoke } = window.__TAURI__?.tauri || { invoke: null };
+
+/**
+ * A restored backup is an already configured library, so skip the first-run wizard
+ */
+const markOnboardingRestored = (): void => {
+  if (invoke) {
+    invoke('complete_onboarding_from_backup').catch((error: unknown) => {
+      console.warn('Failed to update onboarding state after restore:', error);
+    });
+  }
+};
+
+/**
+ * Keeps other commands (and a running campaign) out of the way while `run`
+ * rewrites the data; maintenance mode is left however `run` ends. If it
+ * can't be entered the restore goes ahead without it
+ */
+const withMaintenanceMode = async (reason: string, run: () => void): Promise<void> => {
+  let entered = false;
+  if (invoke) {
+    entered = await invoke('enter_maintenance_mode', { reason })
+      .then(() => true)
+      .catch((error: unknown) => {
+        console.warn('Failed to enter maintenance mode before restore:', error);
+        return false;
+      });
+  }
+  try {
+    run();
+  } finally {
+    if (entered) {
+      await invoke('exit_maintenance_mode').catch((error: unknown) => {
+        console.warn('Failed to leave maintenance mode after restore:', error);
+      });
+    }
+  }
+};
+
+const restoreBackup = async (data: BackupData): Promise<void> => {
+  // Validate backup structure
+  if (!data.version || !data.students || !data.feePayments || !data.expenses) {
+    throw new Error('Invalid backup file structure');
+  }
+
+  // Import all data
+  await withMaintenanceMode('Restoring backup', () => {
+    storage.set('patch_students', data.students || []);
+    storage.set('patch_fee_payments', data.feePayments || []);
+    storage.set('patch_expenses', data.expenses || []);
+    storage.set('patch_whatsapp_logs', data.whatsappLogs || []);
+
+    if (data.admin) {
+      storage.setSingle('patch_admin', data.admin);
+    }
+  });
+
+  markOnboardingRestored();
+};
+
 export interface BackupData {
   version: string;
   timestamp: string;
@@ -44,23 +105,7 @@ export const importData = (file: File): Promise<void> => {
         }
         
         const data: BackupData = JSON.parse(result);
-        
-        // Validate backup structure
-        if (!data.version || !data.students || !data.feePayments || !data.expenses) {
-          throw new Error('Invalid backup file structure');
-        }
-
-        // Import all data
-        storage.set('patch_students', data.students || []);
-        storage.set('patch_fee_payments', data.feePayments || []);
-        storage.set('patch_expenses', data.expenses || []);
-        storage.set('patch_whatsapp_logs', data.whatsappLogs || []);
-        
-        if (data.admin) {
-          storage.setSingle('patch_admin', data.admin);
-        }
-        
-        resolve();
+        restoreBackup(data).then(resolve, reject);
       } catch (error) {
         reject(error);
       }
@@ -72,22 +117,7 @@ export const importData = (file: File): Promise<void> => {
 };
 
 export const exportAllData = exportData;
-export const importAllData = (data: BackupData): void => {
-  // Validate backup structure
-  if (!data.version || !data.students || !data.feePayments || !data.expenses) {
-    throw new Error('Invalid backup file structure');
-  }
-
-  // Import all data
-  storage.set('patch_students', data.students || []);
-  storage.set('patch_fee_payments', data.feePayments || []);
-  storage.set('patch_expenses', data.expenses || []);
-  storage.set('patch_whatsapp_logs', data.whatsappLogs || []);
-  
-  if (data.admin) {
-    storage.setSingle('patch_admin', data.admin);
-  }
-};
+export const importAllData = restoreBackup;
 
 export const clearAllData = (): void => {
   const keysToKeep = ['patch_admin', 'patch_current_user'];
@@ -4,6 +4,7 @@
  */
 
 import { formatForWhatsApp } from './phone';
+import type { InputResult } from '@/types/whatsapp';
 
 // @ts-ignore
 const { invoke } = window.__TAURI__?.tauri || { invoke: null };
@@ -12,6 +13,7 @@ export interface SendResult {
   success: boolean;
   method?: string;
   error?: string;
+  demoMode?: boolean;
 }
 
 /**
@@ -47,15 +49,18 @@ export async function openDeepLink(phoneE164: string, text: string): Promise<Sen
   // Try Tauri automated sending first (desktop app)
   if (invoke) {
     try {
-      const result = await invoke('open_whatsapp_and_send', {
+      const result: InputResult = await invoke('open_whatsapp_and_send', {
         phone: phoneForLink,
         message: text
       });
       
-      console.log('WhatsApp message sent via Tauri automation');
+      console.log(result.demo_mode
+        ? 'Demo mode: WhatsApp message recorded, nothing was sent'
+        : 'WhatsApp message sent via Tauri automation');
       return {
         success: true,
-        method: 'Tauri Automated Sending'
+        method: 'Tauri Automated Sending',
+        demoMode: result.demo_mode
       };
     } catch (error) {
       console.warn('Tauri automated sending failed, falling back to manual methods:', error);
@@ -141,10 +146,11 @@ export async function openDeepLink(phoneE164: string, text: string): Promise<Sen
 export async function sendEnterKey(): Promise<SendResult> {
   if (invoke) {
     try {
-      await invoke('simulate_key_press', { key: 'Enter' });
+      const result: InputResult = await invoke('simulate_key_press', { key: 'Enter' });
       return {
         success: true,
-        method: 'Tauri Key Simulation'
+        method: 'Tauri Key Simulation',
+        demoMode: result.demo_mode
       };
     } catch (error) {
       return {